@@ -6,7 +6,6 @@
 
 mod fail;
 mod logger;
-mod path;
 mod sync;
 
 /// Runs a block of code and catches `?` operator returns.
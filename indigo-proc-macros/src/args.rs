@@ -0,0 +1,346 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::prelude::*;
+
+/// Options set with `#[args(…)]` attributes on a struct, enum, or variant.
+#[derive(Default)]
+struct CommandOptions {
+  name: Option<String>,
+}
+
+/// Options set with `#[args(…)]` attributes on a field.
+#[derive(Default)]
+struct FieldOptions {
+  long: Option<String>,
+  short: Option<Option<char>>,
+  env: Option<String>,
+  default: Option<String>,
+  positional: bool,
+  subcommand: bool,
+}
+
+/// The shape of a field type that determines how it is parsed.
+enum Shape {
+  Bool,
+  Option,
+  Vec,
+  Value,
+}
+
+/// Runs the `Args` derive macro.
+pub fn derive(item: TokenStream) -> TokenStream {
+  let item: syn::DeriveInput = match syn::parse2(item) {
+    Ok(item) => item,
+    Err(err) => return err.to_compile_error(),
+  };
+
+  let name = &item.ident;
+  let (impl_generics, type_generics, where_clause) = item.generics.split_for_impl();
+  let options = command_options(&item.attrs);
+  let about = doc_comment(&item.attrs);
+
+  match &item.data {
+    syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(fields), .. }) => {
+      let command_name = match options.name {
+        Some(name) => quote! { #name },
+        None => quote! { indigo::env::exe_name() },
+      };
+
+      let (command, construct) = fields_command(quote! { Self }, &fields.named);
+
+      quote! {
+        impl #impl_generics indigo::args::Args for #name #type_generics #where_clause {
+          fn command() -> indigo::args::Command {
+            indigo::args::Command::new(#command_name).about(#about)#command
+          }
+
+          fn from_matches(
+            matches: &mut indigo::args::Matches,
+          ) -> indigo::fail::Result<Self> {
+            Ok(#construct)
+          }
+        }
+      }
+    }
+
+    syn::Data::Enum(data) => {
+      let mut commands = Vec::new();
+      let mut arms = Vec::new();
+
+      for variant in &data.variants {
+        let ident = &variant.ident;
+        let options = command_options(&variant.attrs);
+        let about = doc_comment(&variant.attrs);
+        let name = options.name.unwrap_or_else(|| ident.to_string().to_kebab_case());
+
+        match &variant.fields {
+          syn::Fields::Unit => {
+            commands.push(quote! { indigo::args::Command::new(#name).about(#about) });
+            arms.push(quote! { #name => Ok(Self::#ident) });
+          }
+
+          syn::Fields::Named(fields) => {
+            let (command, construct) = fields_command(quote! { Self::#ident }, &fields.named);
+
+            commands.push(quote! { indigo::args::Command::new(#name).about(#about)#command });
+            arms.push(quote! { #name => Ok(#construct) });
+          }
+
+          syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+            let ty = &fields.unnamed[0].ty;
+
+            commands.push(quote! {{
+              let mut command = <#ty as indigo::args::Args>::command();
+
+              command.name = #name.into();
+
+              if !#about.is_empty() {
+                command.about = #about.into();
+              }
+
+              command
+            }});
+
+            arms.push(quote! {
+              #name => Ok(Self::#ident(<#ty as indigo::args::Args>::from_matches(matches)?))
+            });
+          }
+
+          syn::Fields::Unnamed(fields) => abort!(
+            fields.span(),
+            "An indigo::Args variant must have named fields or a single unnamed field."
+          ),
+        }
+      }
+
+      quote! {
+        impl #impl_generics indigo::args::Subcommands for #name #type_generics #where_clause {
+          fn commands() -> Vec<indigo::args::Command> {
+            vec![#(#commands),*]
+          }
+
+          fn from_matches(
+            name: &str,
+            matches: &mut indigo::args::Matches,
+          ) -> indigo::fail::Result<Self> {
+            match name {
+              #(#arms,)*
+              _ => Err(indigo::fail::Error::new(format!("Unknown command `{}`.", name))),
+            }
+          }
+        }
+      }
+    }
+
+    _ => abort!(item.span(), "Expected a struct with named fields or an enum."),
+  }
+}
+
+/// Generates builder calls that add arguments for the given fields to a
+/// `Command` and an expression that constructs a value from `matches`.
+fn fields_command(
+  path: TokenStream,
+  fields: &syn::punctuated::Punctuated<syn::Field, Token![,]>,
+) -> (TokenStream, TokenStream) {
+  let mut command = TokenStream::new();
+  let mut values = Vec::new();
+
+  for field in fields {
+    let ident = field.ident.as_ref().expect("Expected a named field.");
+    let id = ident.to_string().trim_start_matches("r#").to_string();
+    let options = field_options(&field.attrs);
+    let help = doc_comment(&field.attrs);
+    let shape = shape(&field.ty);
+
+    // Add subcommands.
+
+    if options.subcommand {
+      let (ty, value) = match (&shape, inner_type(&field.ty)) {
+        (Shape::Option, Some(ty)) => (ty, quote! { matches.subcommand()? }),
+        _ => (&field.ty, quote! { matches.required_subcommand()? }),
+      };
+
+      let required = !matches!(shape, Shape::Option);
+
+      command.extend(quote! {
+        .subcommands(<#ty as indigo::args::Subcommands>::commands(), #required)
+      });
+
+      values.push(quote! { #ident: #value });
+      continue;
+    }
+
+    // Add an argument.
+
+    let mut arg = match (&shape, options.positional) {
+      (Shape::Bool, false) => quote! { indigo::args::Arg::flag(#id) },
+      (_, false) => quote! { indigo::args::Arg::option(#id) },
+      (_, true) => quote! { indigo::args::Arg::positional(#id) },
+    };
+
+    if let Some(long) = &options.long {
+      arg.extend(quote! { .long(#long) });
+    }
+
+    if let Some(short) = options.short {
+      let short = short.unwrap_or_else(|| id.chars().next().unwrap_or_default());
+
+      arg.extend(quote! { .short(#short) });
+    }
+
+    arg.extend(quote! { .help(#help) });
+
+    if let Some(env) = &options.env {
+      arg.extend(quote! { .env(#env) });
+    }
+
+    if let Some(default) = &options.default {
+      arg.extend(quote! { .default(#default) });
+    }
+
+    match shape {
+      Shape::Value if options.default.is_none() => arg.extend(quote! { .required() }),
+      Shape::Vec => arg.extend(quote! { .multiple() }),
+      _ => {}
+    }
+
+    command.extend(quote! { .arg(#arg) });
+
+    let value = match shape {
+      Shape::Bool => quote! { matches.flag(#id)? },
+      Shape::Option => quote! { matches.value(#id)? },
+      Shape::Vec => quote! { matches.values(#id)? },
+      Shape::Value => quote! { matches.required(#id)? },
+    };
+
+    values.push(quote! { #ident: #value });
+  }
+
+  (command, quote! { #path { #(#values),* } })
+}
+
+/// Parses the `#[args(…)]` attributes of a struct, enum, or variant.
+fn command_options(attrs: &[syn::Attribute]) -> CommandOptions {
+  let mut options = CommandOptions::default();
+
+  for meta in args_meta(attrs) {
+    match &meta {
+      syn::Meta::NameValue(nv) if nv.path.is_ident("name") => {
+        options.name = Some(lit_str(&nv.lit));
+      }
+
+      _ => abort!(meta.span(), "Unknown indigo::Args option."),
+    }
+  }
+
+  options
+}
+
+/// Parses the `#[args(…)]` attributes of a field.
+fn field_options(attrs: &[syn::Attribute]) -> FieldOptions {
+  let mut options = FieldOptions::default();
+
+  for meta in args_meta(attrs) {
+    match &meta {
+      syn::Meta::Path(path) if path.is_ident("positional") => options.positional = true,
+      syn::Meta::Path(path) if path.is_ident("subcommand") => options.subcommand = true,
+      syn::Meta::Path(path) if path.is_ident("short") => options.short = Some(None),
+
+      syn::Meta::NameValue(nv) if nv.path.is_ident("short") => match &nv.lit {
+        syn::Lit::Char(c) => options.short = Some(Some(c.value())),
+        lit => abort!(lit.span(), "Expected a character."),
+      },
+
+      syn::Meta::NameValue(nv) if nv.path.is_ident("long") => options.long = Some(lit_str(&nv.lit)),
+
+      syn::Meta::NameValue(nv) if nv.path.is_ident("env") => options.env = Some(lit_str(&nv.lit)),
+
+      syn::Meta::NameValue(nv) if nv.path.is_ident("default") => {
+        options.default = Some(lit_str(&nv.lit))
+      }
+
+      _ => abort!(meta.span(), "Unknown indigo::Args option."),
+    }
+  }
+
+  options
+}
+
+/// Returns the nested meta items of `#[args(…)]` attributes.
+fn args_meta(attrs: &[syn::Attribute]) -> Vec<syn::Meta> {
+  let mut items = Vec::new();
+
+  for attr in attrs.iter().filter(|attr| attr.path.is_ident("args")) {
+    let list = match attr.parse_meta() {
+      Ok(syn::Meta::List(list)) => list,
+      Ok(meta) => abort!(meta.span(), "Expected `#[args(…)]`."),
+      Err(err) => abort!(err.span(), "{}", err),
+    };
+
+    for nested in list.nested {
+      match nested {
+        syn::NestedMeta::Meta(meta) => items.push(meta),
+        syn::NestedMeta::Lit(lit) => abort!(lit.span(), "Unknown indigo::Args option."),
+      }
+    }
+  }
+
+  items
+}
+
+/// Returns the value of a string literal or aborts.
+fn lit_str(lit: &syn::Lit) -> String {
+  match lit {
+    syn::Lit::Str(s) => s.value(),
+    _ => abort!(lit.span(), "Expected a string."),
+  }
+}
+
+/// Returns the text of the doc comments in the given attributes.
+fn doc_comment(attrs: &[syn::Attribute]) -> String {
+  let mut lines = Vec::new();
+
+  for attr in attrs.iter().filter(|attr| attr.path.is_ident("doc")) {
+    if let Ok(syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(s), .. })) =
+      attr.parse_meta()
+    {
+      lines.push(s.value().trim().to_string());
+    }
+  }
+
+  lines.join("\n").trim().into()
+}
+
+/// Returns the shape of a field type.
+fn shape(ty: &syn::Type) -> Shape {
+  match last_segment(ty).map(|s| s.ident.to_string()).as_deref() {
+    Some("bool") => Shape::Bool,
+    Some("Option") if inner_type(ty).is_some() => Shape::Option,
+    Some("Vec") if inner_type(ty).is_some() => Shape::Vec,
+    _ => Shape::Value,
+  }
+}
+
+/// Returns the last segment of a type path.
+fn last_segment(ty: &syn::Type) -> Option<&syn::PathSegment> {
+  match ty {
+    syn::Type::Path(ty) if ty.qself.is_none() => ty.path.segments.last(),
+    _ => None,
+  }
+}
+
+/// Returns the type parameter of a generic type such as `Option<T>`.
+fn inner_type(ty: &syn::Type) -> Option<&syn::Type> {
+  match &last_segment(ty)?.arguments {
+    syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+      syn::GenericArgument::Type(ty) => Some(ty),
+      _ => None,
+    },
+
+    _ => None,
+  }
+}
@@ -8,13 +8,28 @@ use crate::prelude::*;
 
 /// A list of input futures.
 struct FutureList {
+  biased: bool,
   items: Vec<syn::Expr>,
 }
 
+/// A list of `select!` branches.
+struct SelectBranches {
+  biased: bool,
+  items: Vec<SelectBranch>,
+}
+
+/// A `select!` branch in the form `pattern = future => handler`.
+struct SelectBranch {
+  pat: syn::Pat,
+  future: syn::Expr,
+  handler: syn::Expr,
+}
+
 /// Runs the `future::join` macro.
 pub fn join(args: proc_macro::TokenStream) -> TokenStream {
   let futures = match syn::parse(args) {
-    Ok(FutureList { items }) => items,
+    Ok(FutureList { biased: false, items }) => items,
+    Ok(_) => abort!(Span::call_site(), "The `biased` option is not supported by `join!`."),
     Err(err) => abort!(err.span(), err),
   };
 
@@ -25,85 +40,232 @@ pub fn join(args: proc_macro::TokenStream) -> TokenStream {
     _ => {}
   }
 
-  let future_idents: Vec<_> = futures
-    .iter()
-    .enumerate()
-    .map(|(i, f)| syn::Ident::new(&format!("_{}", i), f.span()))
-    .collect();
+  let future_idents = idents_for(&futures);
 
   quote! {{
-    #(let mut #future_idents = indigo::future::buffered(#futures);)*
+    #(let #future_idents = indigo::future::buffered(#futures);)*
 
-    unsafe {
-      #(let mut #future_idents = Pin::new_unchecked(&mut #future_idents);)*
+    indigo::sync::pin!(#(#future_idents),*);
 
-      indigo::future::poll_fn(|cx: &mut std::task::Context| -> std::task::Poll<()> {
-        let mut ready = true;
-
-        #(
-          if let std::task::Poll::Pending = Pin::as_mut(&mut #future_idents).poll(cx) {
-            ready = false;
-          }
-        )*
+    indigo::future::poll_fn(|cx: &mut std::task::Context| -> std::task::Poll<()> {
+      let mut ready = true;
 
-        match ready {
-          true => std::task::Poll::Ready(()),
-          false => std::task::Poll::Pending,
+      #(
+        if std::future::Future::poll(std::pin::Pin::as_mut(&mut #future_idents), cx).is_pending() {
+          ready = false;
         }
-      }).await;
-    }
+      )*
+
+      match ready {
+        true => std::task::Poll::Ready(()),
+        false => std::task::Poll::Pending,
+      }
+    }).await;
 
     (
-      #(#future_idents.into_output(),)*
+      #(std::pin::Pin::as_mut(&mut #future_idents).take_output_pinned(),)*
     )
   }}
 }
 
 /// Runs the `future::race` macro.
 pub fn race(args: proc_macro::TokenStream) -> TokenStream {
-  let futures = match syn::parse(args) {
-    Ok(FutureList { items }) => items,
+  let (biased, futures) = match syn::parse(args) {
+    Ok(FutureList { biased, items }) => (biased, items),
     Err(err) => abort!(err.span(), err),
   };
 
-  match futures.len() {
-    0 => return quote! { () },
-    1 => return quote! { (#(#futures)*).await },
-    2 => return quote! { indigo::future::race(#(#futures),*).await },
+  match (futures.len(), biased) {
+    (0, _) => return quote! { () },
+    (1, _) => return quote! { (#(#futures)*).await },
+    (2, false) => return quote! { indigo::future::race(#(#futures),*).await },
+    (2, true) => return quote! { indigo::future::race(#(#futures),*).biased().await },
     _ => {}
   }
 
-  let future_idents: Vec<_> = futures
-    .iter()
-    .enumerate()
-    .map(|(i, f)| syn::Ident::new(&format!("_{}", i), f.span()))
-    .collect();
+  let future_idents = idents_for(&futures);
+  let rng = rng_for(biased);
+  let poll_in_order = poll_in_order(&future_idents, biased, |_| quote! { value });
 
   quote! {{
-    #(let mut #future_idents = #futures;)*
+    #rng
 
-    unsafe {
-      #(let mut #future_idents = Pin::new_unchecked(&mut #future_idents);)*
+    #(let #future_idents = #futures;)*
 
-      indigo::future::poll_fn(|cx: &mut std::task::Context| -> std::task::Poll<_> {
-        let mut ready = true;
+    indigo::sync::pin!(#(#future_idents),*);
 
-        #(
-          if let std::task::Poll::Ready(value) = Pin::as_mut(&mut #future_idents).poll(cx) {
-            return std::task::Poll::Ready(value);
-          }
-        )*
+    indigo::future::poll_fn(|cx: &mut std::task::Context| -> std::task::Poll<_> {
+      #poll_in_order
+
+      std::task::Poll::Pending
+    }).await
+  }}
+}
+
+/// Runs the `future::select` macro.
+pub fn select(args: proc_macro::TokenStream) -> TokenStream {
+  let (biased, branches) = match syn::parse(args) {
+    Ok(SelectBranches { biased, items }) => (biased, items),
+    Err(err) => abort!(err.span(), err),
+  };
+
+  if branches.is_empty() {
+    abort!(Span::call_site(), "Expected at least one branch.");
+  }
+
+  let futures: Vec<_> = branches.iter().map(|b| &b.future).collect();
+  let pats = branches.iter().map(|b| &b.pat);
+  let handlers = branches.iter().map(|b| &b.handler);
+  let future_idents = idents_for(&futures);
+  let output = syn::Ident::new("output", Span::mixed_site());
+
+  let variants: Vec<_> =
+    (0..branches.len()).map(|i| syn::Ident::new(&format!("B{}", i), Span::call_site())).collect();
+
+  let rng = rng_for(biased);
+
+  let poll_in_order = poll_in_order(&future_idents, biased, |i| {
+    let variant = &variants[i];
+
+    quote! { SelectBranch::#variant(value) }
+  });
+
+  quote! {{
+    enum SelectBranch<#(#variants),*> {
+      #(#variants(#variants),)*
+    }
+
+    let #output = {
+      #rng
+
+      #(let #future_idents = #futures;)*
+
+      indigo::sync::pin!(#(#future_idents),*);
+
+      indigo::future::poll_fn(|cx: &mut std::task::Context| {
+        #poll_in_order
 
         std::task::Poll::Pending
       }).await
+    };
+
+    match #output {
+      #(SelectBranch::#variants(#pats) => #handlers,)*
+    }
+  }}
+}
+
+/// Runs the `future::try_join` macro.
+pub fn try_join(args: proc_macro::TokenStream) -> TokenStream {
+  let futures = match syn::parse(args) {
+    Ok(FutureList { biased: false, items }) => items,
+    Ok(_) => abort!(Span::call_site(), "The `biased` option is not supported by `try_join!`."),
+    Err(err) => abort!(err.span(), err),
+  };
+
+  if futures.is_empty() {
+    return quote! { Ok(()) };
+  }
+
+  let future_idents = idents_for(&futures);
+
+  quote! {{
+    #(let #future_idents = indigo::future::buffered(#futures);)*
+
+    indigo::sync::pin!(#(#future_idents),*);
+
+    let result = indigo::future::poll_fn(|cx: &mut std::task::Context| {
+      let mut ready = true;
+
+      #(
+        match std::pin::Pin::as_mut(&mut #future_idents).poll_try(cx) {
+          std::task::Poll::Ready(Err(err)) => return std::task::Poll::Ready(Err(err)),
+          std::task::Poll::Ready(Ok(())) => {}
+          std::task::Poll::Pending => ready = false,
+        }
+      )*
+
+      match ready {
+        true => std::task::Poll::Ready(Ok(())),
+        false => std::task::Poll::Pending,
+      }
+    }).await;
+
+    match result {
+      Ok(()) => Ok((
+        #(
+          match std::pin::Pin::as_mut(&mut #future_idents).take_output_pinned() {
+            Ok(output) => output,
+            Err(_) => unreachable!(),
+          },
+        )*
+      )),
+
+      Err(err) => Err(err),
     }
   }}
 }
 
+/// Generates code that declares the random number generator used by
+/// [`poll_in_order()`], unless `biased` is `true`.
+fn rng_for(biased: bool) -> TokenStream {
+  let rng = syn::Ident::new("rng", Span::mixed_site());
+
+  match biased {
+    true => quote! {},
+    false => quote! { let mut #rng = indigo::random::Rng::new(); },
+  }
+}
+
+/// Generates code that polls pinned futures and returns the output of the
+/// first one that is ready, mapped by the code that `map_output` returns for
+/// its index.
+///
+/// Unless `biased` is `true`, polling starts at a random future chosen with the
+/// generator declared by [`rng_for()`] so that none of them can starve the
+/// others.
+fn poll_in_order(
+  future_idents: &[syn::Ident],
+  biased: bool,
+  map_output: impl Fn(usize) -> TokenStream,
+) -> TokenStream {
+  let count = future_idents.len();
+  let indices = 0..count;
+  let outputs = indices.clone().map(map_output);
+  let rng = syn::Ident::new("rng", Span::mixed_site());
+
+  let start = match biased {
+    true => quote! { 0 },
+    false => quote! { #rng.random::<usize>() % #count },
+  };
+
+  quote! {
+    let start: usize = #start;
+
+    for i in (start..#count).chain(0..start) {
+      let poll = match i {
+        #(#indices => std::future::Future::poll(std::pin::Pin::as_mut(&mut #future_idents), cx)
+          .map(|value| #outputs),)*
+        _ => unreachable!(),
+      };
+
+      if let std::task::Poll::Ready(output) = poll {
+        return std::task::Poll::Ready(output);
+      }
+    }
+  }
+}
+
+/// Returns a list of identifiers for the futures of a macro.
+fn idents_for<T: syn::spanned::Spanned>(items: &[T]) -> Vec<syn::Ident> {
+  items.iter().enumerate().map(|(i, f)| syn::Ident::new(&format!("_{}", i), f.span())).collect()
+}
+
 // Parse a list of futures.
 
 impl Parse for FutureList {
   fn parse(input: ParseStream) -> syn::Result<Self> {
+    let biased = parse_biased(input)?;
     let mut items = Vec::new();
 
     while !input.is_empty() {
@@ -114,6 +276,51 @@ impl Parse for FutureList {
       }
     }
 
-    Ok(Self { items })
+    Ok(Self { biased, items })
+  }
+}
+
+// Parse a list of `select!` branches.
+
+impl Parse for SelectBranches {
+  fn parse(input: ParseStream) -> syn::Result<Self> {
+    let biased = parse_biased(input)?;
+    let mut items = Vec::new();
+
+    while !input.is_empty() {
+      let pat = input.parse()?;
+
+      input.parse::<Token![=]>()?;
+
+      let future = input.parse()?;
+
+      input.parse::<Token![=>]>()?;
+
+      let handler = input.parse()?;
+
+      items.push(SelectBranch { pat, future, handler });
+
+      if !input.is_empty() {
+        input.parse::<Token![,]>()?;
+      }
+    }
+
+    Ok(Self { biased, items })
+  }
+}
+
+/// Parses an optional `biased;` prefix.
+fn parse_biased(input: ParseStream) -> syn::Result<bool> {
+  let fork = input.fork();
+
+  match fork.parse::<syn::Ident>() {
+    Ok(ident) if ident == "biased" && fork.peek(Token![;]) => {
+      input.parse::<syn::Ident>()?;
+      input.parse::<Token![;]>()?;
+
+      Ok(true)
+    }
+
+    _ => Ok(false),
   }
 }
@@ -4,12 +4,23 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+mod args;
 mod future;
+mod path;
 mod prelude;
 mod runtime;
 
 use crate::prelude::*;
 
+/// A derive macro for the `indigo::args::Args` trait.
+///
+/// See the `indigo::args` module for usage.
+#[proc_macro_derive(Args, attributes(args))]
+#[proc_macro_error]
+pub fn derive_args(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+  args::derive(item.into()).into()
+}
+
 /// A derive macro for the `Error` trait that uses all the default method
 /// implementations.
 #[proc_macro_derive(Error)]
@@ -43,12 +54,79 @@ pub fn future_join(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 /// Waits for one of the given futures to complete and then returns the output.
 ///
 /// The other futures are canceled.
+///
+/// The futures are polled in random order each time so that none of them can
+/// starve the others. Begin the list with `biased;` to always poll them in
+/// order.
 #[proc_macro]
 #[proc_macro_error]
 pub fn future_race(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
   future::race(input).into()
 }
 
+/// Waits for one of the given futures to complete and then runs the handler of
+/// its branch.
+///
+/// Each branch is in the form `pattern = future => handler`. The output of
+/// the first future to complete is matched against its pattern and then the
+/// handler is evaluated. The other futures are canceled before the handler
+/// runs.
+///
+/// Like [`future_race!`], the futures are polled in random order unless the
+/// branches begin with `biased;`.
+///
+/// ## Example
+///
+/// ```ignore
+/// future::select! {
+///   msg = rx.recv() => info!("Received {:?}.", msg),
+///   _ = future::sleep(Duration::secs(5)) => warn!("Timed out."),
+/// }
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn future_select(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+  future::select(input).into()
+}
+
+/// Waits for all given futures to succeed and returns their outputs as a
+/// tuple.
+///
+/// If any future fails, the others are canceled and its error is returned
+/// immediately. All futures must have the same error type.
+#[proc_macro]
+#[proc_macro_error]
+pub fn future_try_join(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+  future::try_join(input).into()
+}
+
+/// Joins multiple paths together into a new `String`.
+///
+/// See `indigo::fs::path::join!` for usage.
+#[proc_macro]
+#[proc_macro_error]
+pub fn path_join(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+  path::join(input).into()
+}
+
+/// Joins multiple paths together into a new `String` and normalizes it.
+///
+/// See `indigo::fs::path::join!` for usage.
+#[proc_macro]
+#[proc_macro_error]
+pub fn path_normalize(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+  path::normalize(input).into()
+}
+
+/// Joins multiple paths together into a new `String` and resolves it.
+///
+/// See `indigo::fs::path::join!` for usage.
+#[proc_macro]
+#[proc_macro_error]
+pub fn path_resolve(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+  path::resolve(input).into()
+}
+
 /// Defines an async main function that runs on the Indigo runtime.
 ///
 /// ## Example
@@ -62,6 +140,28 @@ pub fn future_race(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 /// }
 /// ```
 ///
+/// ## Parsing arguments
+///
+/// The `main` function may take a single parameter of a type that derives
+/// `indigo::Args`. Command-line arguments are parsed before the runtime
+/// starts. If they are invalid, the error is written to stderr and the process
+/// exits with an exit code of `2`.
+///
+/// ```ignore
+/// #[derive(indigo::Args)]
+/// struct Args {
+///   /// Prints more information.
+///   verbose: bool,
+/// }
+///
+/// #[indigo::main]
+/// async fn main(args: Args) {
+///   if args.verbose {
+///     println!("Hello Indigo!");
+///   }
+/// }
+/// ```
+///
 /// ## Returning a result
 ///
 /// The `main` function may return a `Result<(), T>`. If the return value is an
@@ -0,0 +1,195 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::prelude::*;
+
+/// A list of input paths.
+struct PathList {
+  strict: bool,
+  items: Vec<syn::Expr>,
+}
+
+/// One part of the joined path.
+enum Part {
+  /// Adjacent string literals to be joined into one path.
+  Literal(Vec<String>, Span),
+  /// Any other expression.
+  Expr(Box<syn::Expr>),
+}
+
+/// Runs the `fs::path::join` macro.
+pub fn join(input: proc_macro::TokenStream) -> TokenStream {
+  expand(input, quote! { indigo::fs::path::join_parts })
+}
+
+/// Runs the `fs::path::normalize` macro.
+pub fn normalize(input: proc_macro::TokenStream) -> TokenStream {
+  expand(input, quote! { indigo::fs::path::normalize_parts })
+}
+
+/// Runs the `fs::path::resolve` macro.
+pub fn resolve(input: proc_macro::TokenStream) -> TokenStream {
+  expand(input, quote! { indigo::fs::path::resolve_parts })
+}
+
+/// Expands a path macro into a call to the given function with a slice of all
+/// path parts.
+fn expand(input: proc_macro::TokenStream, func: TokenStream) -> TokenStream {
+  let list: PathList = match syn::parse(input) {
+    Ok(list) => list,
+    Err(err) => abort!(err.span(), err),
+  };
+
+  if list.items.is_empty() {
+    abort!(Span::call_site(), "Expected at least one path.");
+  }
+
+  let parts = fold(list.items, list.strict);
+  let mut bindings = Vec::new();
+  let mut args = Vec::new();
+
+  for part in parts {
+    match part {
+      Part::Literal(values, span) => args.push(expand_literal(&values, span)),
+
+      Part::Expr(expr) => {
+        let ident = syn::Ident::new(&format!("_{}", bindings.len()), expr.span());
+
+        args.push(quote! { &*#ident });
+        bindings.push(quote! { let #ident = indigo::fs::path::PathLike::to_cow(#expr); });
+      }
+    }
+  }
+
+  quote! {{
+    #(#bindings)*
+
+    #func(&[#(#args),*])
+  }}
+}
+
+/// Expands adjacent string literals into one literal joined with the separator
+/// of the target platform.
+fn expand_literal(values: &[String], span: Span) -> TokenStream {
+  let unix = join_literals(values, '/', |c| c == '/');
+  let windows = join_literals(values, '\\', is_separator);
+
+  if unix == windows {
+    return syn::LitStr::new(&unix, span).into_token_stream();
+  }
+
+  let unix = syn::LitStr::new(&unix, span);
+  let windows = syn::LitStr::new(&windows, span);
+
+  quote! {
+    if std::path::MAIN_SEPARATOR == '\\' { #windows } else { #unix }
+  }
+}
+
+/// Joins string literals with the given separator like
+/// `indigo::fs::path::append()` does on a platform with that separator.
+fn join_literals(values: &[String], separator: char, is_separator: fn(char) -> bool) -> String {
+  let mut output = String::new();
+
+  for value in values {
+    if !output.is_empty() && !output.ends_with(is_separator) {
+      output.push(separator);
+    }
+
+    output.push_str(value);
+  }
+
+  output
+}
+
+/// Converts input expressions into path parts, folding adjacent string
+/// literals together.
+fn fold(items: Vec<syn::Expr>, strict: bool) -> Vec<Part> {
+  let mut parts = Vec::with_capacity(items.len());
+
+  for item in items {
+    let lit = match &item {
+      syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(lit), .. }) => lit,
+      _ => {
+        parts.push(Part::Expr(Box::new(item)));
+        continue;
+      }
+    };
+
+    let value = lit.value();
+
+    if strict {
+      check_strict(&value, lit.span());
+    }
+
+    match parts.last_mut() {
+      // Absolute paths and prefixes like `C:` depend on the target platform,
+      // so they are left for the `indigo::fs::path` functions to handle.
+      Some(Part::Literal(prev, _))
+        if !value.starts_with(is_separator) && !value.contains(':') && !value.is_empty() =>
+      {
+        prev.push(value);
+      }
+
+      _ => parts.push(Part::Literal(vec![value], lit.span())),
+    }
+  }
+
+  parts
+}
+
+/// Aborts if a literal is not a single, normal path component.
+fn check_strict(value: &str, span: Span) {
+  if value.contains(is_separator) {
+    abort!(span, "Path components must not contain separators in strict mode.");
+  }
+
+  match value {
+    "" => abort!(span, "Path components must not be empty in strict mode."),
+    "." | ".." => abort!(span, "Path components must not be `{}` in strict mode.", value),
+    _ => {}
+  }
+}
+
+/// Returns `true` if the character is a path separator on any platform.
+fn is_separator(c: char) -> bool {
+  c == '/' || c == '\\'
+}
+
+// Parse a list of paths.
+
+impl Parse for PathList {
+  fn parse(input: ParseStream) -> syn::Result<Self> {
+    let strict = parse_strict(input)?;
+    let mut items = Vec::new();
+
+    while !input.is_empty() {
+      items.push(input.parse()?);
+
+      if !input.is_empty() {
+        input.parse::<Token![,]>()?;
+      }
+    }
+
+    Ok(Self { strict, items })
+  }
+}
+
+/// Parses an optional `strict;` prefix.
+fn parse_strict(input: ParseStream) -> syn::Result<bool> {
+  let fork = input.fork();
+
+  match fork.parse::<syn::Ident>() {
+    Ok(ident) if ident == "strict" && fork.peek(Token![;]) => {
+      input.parse::<syn::Ident>()?;
+      input.parse::<Token![;]>()?;
+
+      Ok(true)
+    }
+
+    _ => Ok(false),
+  }
+}
@@ -22,13 +22,32 @@ pub fn main(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
       .into();
   }
 
-  // Require no parameters.
-
-  if !sig.inputs.is_empty() {
-    return syn::Error::new_spanned(sig.inputs, "An indigo::main function cannot have parameters.")
+  // Allow a single parameter for parsed command-line arguments.
+
+  let (parse_args, call_args) = match sig.inputs.len() {
+    0 => (TokenStream::new(), TokenStream::new()),
+
+    1 => match &sig.inputs[0] {
+      syn::FnArg::Typed(syn::PatType { ty, .. }) => {
+        (quote! { let args: #ty = indigo::args::parse_or_exit(); }, quote! { args })
+      }
+
+      input => {
+        return syn::Error::new_spanned(input, "An indigo::main function cannot take `self`.")
+          .to_compile_error()
+          .into()
+      }
+    },
+
+    _ => {
+      return syn::Error::new_spanned(
+        &sig.inputs,
+        "An indigo::main function can only have one parameter for command-line arguments.",
+      )
       .to_compile_error()
-      .into();
-  }
+      .into()
+    }
+  };
 
   // Generate code to print errors.
 
@@ -54,10 +73,12 @@ pub fn main(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
       #init
 
+      #parse_args
+
       indigo::runtime::logger::init!();
 
       indigo::runtime::run(async {
-        let result = #name().await;
+        let result = #name(#call_args).await;
 
         #wrap_result
       })
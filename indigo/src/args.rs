@@ -0,0 +1,296 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Command-line argument parsing.
+//!
+//! Arguments are usually parsed by deriving [`Args`] for a struct and adding
+//! it as the parameter of an `#[indigo::main]` function.
+//!
+//! ## Example
+//!
+//! ```ignore
+//! /// Serves files over HTTP.
+//! #[derive(indigo::Args)]
+//! struct Args {
+//!   /// The port to listen on.
+//!   #[args(short, env = "PORT", default = "8080")]
+//!   port: u16,
+//!   /// Logs every request.
+//!   #[args(short)]
+//!   verbose: bool,
+//!   /// The directory to serve.
+//!   #[args(positional)]
+//!   root: String,
+//! }
+//!
+//! #[indigo::main]
+//! async fn main(args: Args) {
+//!   info!("Serving `{}` on port {}.", args.root, args.port);
+//! }
+//! ```
+//!
+//! Fields of type `bool` are flags such as `--verbose`, fields of type
+//! `Option<T>` are optional, and fields of type `Vec<T>` can be given more
+//! than once. Other fields are required unless they have a default value or
+//! an environment variable that is set. Each field is parsed with its
+//! `FromStr` implementation.
+//!
+//! Enums can also derive [`Args`] to define subcommands. Add one to a struct
+//! with a field marked `#[args(subcommand)]`. Each variant is a subcommand
+//! named after the variant in kebab case, and may have named fields or a
+//! single field that derives [`Args`].
+
+mod help;
+mod parser;
+
+pub use self::parser::Matches;
+pub use indigo_proc_macros::Args;
+
+use crate::prelude::*;
+use std::ffi::OsString;
+use std::process::exit;
+
+/// A type that can be parsed from command-line arguments.
+///
+/// This trait is usually implemented with `#[derive(indigo::Args)]`.
+pub trait Args: Sized {
+  /// Returns a description of the command and its arguments.
+  fn command() -> Command;
+
+  /// Creates a value from matched arguments.
+  fn from_matches(matches: &mut Matches) -> Result<Self>;
+}
+
+/// A set of subcommands that can be parsed from command-line arguments.
+///
+/// This trait is implemented for enums with `#[derive(indigo::Args)]`.
+pub trait Subcommands: Sized {
+  /// Returns a description of each subcommand.
+  fn commands() -> Vec<Command>;
+
+  /// Creates a value from the name of a subcommand and its matched arguments.
+  fn from_matches(name: &str, matches: &mut Matches) -> Result<Self>;
+}
+
+/// A description of a command and its arguments.
+#[derive(Clone, Debug, Default)]
+pub struct Command {
+  /// The name of the command.
+  pub name: String,
+  /// A description of the command shown in help.
+  pub about: String,
+  /// The arguments of the command.
+  pub args: Vec<Arg>,
+  /// The subcommands of the command.
+  pub subcommands: Vec<Command>,
+  /// Whether a subcommand must be given.
+  pub subcommand_required: bool,
+}
+
+/// A description of a command-line argument.
+#[derive(Clone, Debug)]
+pub struct Arg {
+  /// The unique ID of the argument, usually the name of its field.
+  pub id: String,
+  /// The kind of argument.
+  pub kind: ArgKind,
+  /// The long name of a flag or option, such as `port` for `--port`.
+  pub long: String,
+  /// The short name of a flag or option, such as `p` for `-p`.
+  pub short: Option<char>,
+  /// A description of the argument shown in help.
+  pub help: String,
+  /// An environment variable to read the value from if it is not given.
+  pub env: Option<String>,
+  /// A default value to use if it is not given.
+  pub default: Option<String>,
+  /// Whether the argument must be given.
+  pub required: bool,
+  /// Whether the argument can be given more than once.
+  pub multiple: bool,
+}
+
+/// One of the possible kinds of [`Arg`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ArgKind {
+  /// A flag such as `--verbose` that does not take a value.
+  Flag,
+  /// An option such as `--port 80` that takes a value.
+  Option,
+  /// A positional argument.
+  Positional,
+}
+
+/// One of the possible errors returned when parsing arguments.
+#[derive(Debug, Display)]
+pub enum ParseError {
+  /// Help was requested with `--help`. Contains the help message.
+  #[display(fmt = "{}", _0)]
+  Help(String),
+  /// The arguments were invalid.
+  #[display(fmt = "{}", _0)]
+  Invalid(fail::Error),
+}
+
+/// Parses the arguments of the current process.
+pub fn parse<T: Args>() -> Result<T, ParseError> {
+  parse_from(std::env::args_os().skip(1))
+}
+
+/// Parses the given arguments, not including the name of the program.
+pub fn parse_from<T: Args>(
+  args: impl IntoIterator<Item = impl Into<OsString>>,
+) -> Result<T, ParseError> {
+  let args = args
+    .into_iter()
+    .map(|arg| {
+      arg.into().into_string().map_err(|arg| {
+        ParseError::Invalid(fail::err!(
+          "Invalid argument `{}`. Arguments must be valid Unicode.",
+          arg.to_string_lossy()
+        ))
+      })
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+
+  let command = T::command();
+  let mut matches = parser::parse(&command, &command.name, &args)?;
+
+  T::from_matches(&mut matches).map_err(ParseError::Invalid)
+}
+
+/// Parses the arguments of the current process or exits.
+///
+/// If help is requested, it is written to stdout and the process exits with
+/// an exit code of `0`. If the arguments are invalid, the error is written to
+/// stderr and the process exits with an exit code of `2`.
+pub fn parse_or_exit<T: Args>() -> T {
+  match parse() {
+    Ok(args) => args,
+
+    Err(ParseError::Help(help)) => {
+      let _ = write!(console::Term::stdout(), "{}", help);
+
+      exit(0)
+    }
+
+    Err(ParseError::Invalid(err)) => {
+      let _ = writeln!(console::Term::stderr(), "{:#}", err);
+      let _ = writeln!(console::Term::stderr(), "Run `{} --help` for usage.", T::command().name);
+
+      exit(2)
+    }
+  }
+}
+
+impl Command {
+  /// Creates a new command with the given name.
+  pub fn new(name: impl Into<String>) -> Self {
+    Self { name: name.into(), ..default() }
+  }
+
+  /// Sets the description of the command shown in help.
+  pub fn about(mut self, about: impl Into<String>) -> Self {
+    self.about = about.into();
+    self
+  }
+
+  /// Adds an argument to the command.
+  pub fn arg(mut self, arg: Arg) -> Self {
+    self.args.push(arg);
+    self
+  }
+
+  /// Adds subcommands to the command.
+  pub fn subcommands(mut self, commands: Vec<Command>, required: bool) -> Self {
+    self.subcommands.extend(commands);
+    self.subcommand_required |= required;
+    self
+  }
+}
+
+impl Arg {
+  /// Creates a new flag argument such as `--verbose`.
+  pub fn flag(id: impl Into<String>) -> Self {
+    Self::new(id.into(), ArgKind::Flag)
+  }
+
+  /// Creates a new option argument such as `--port 80`.
+  pub fn option(id: impl Into<String>) -> Self {
+    Self::new(id.into(), ArgKind::Option)
+  }
+
+  /// Creates a new positional argument.
+  pub fn positional(id: impl Into<String>) -> Self {
+    Self::new(id.into(), ArgKind::Positional)
+  }
+
+  /// Creates a new argument with a long name derived from its ID.
+  fn new(id: String, kind: ArgKind) -> Self {
+    Self {
+      long: id.replace('_', "-"),
+      id,
+      kind,
+      short: None,
+      help: default(),
+      env: None,
+      default: None,
+      required: false,
+      multiple: false,
+    }
+  }
+
+  /// Sets the long name of the argument.
+  pub fn long(mut self, long: impl Into<String>) -> Self {
+    self.long = long.into();
+    self
+  }
+
+  /// Sets the short name of the argument.
+  pub fn short(mut self, short: char) -> Self {
+    self.short = Some(short);
+    self
+  }
+
+  /// Sets the description of the argument shown in help.
+  pub fn help(mut self, help: impl Into<String>) -> Self {
+    self.help = help.into();
+    self
+  }
+
+  /// Sets an environment variable to read the value from if it is not given.
+  pub fn env(mut self, name: impl Into<String>) -> Self {
+    self.env = Some(name.into());
+    self
+  }
+
+  /// Sets a default value to use if it is not given.
+  pub fn default(mut self, value: impl Into<String>) -> Self {
+    self.default = Some(value.into());
+    self
+  }
+
+  /// Requires the argument to be given.
+  pub fn required(mut self) -> Self {
+    self.required = true;
+    self
+  }
+
+  /// Allows the argument to be given more than once.
+  pub fn multiple(mut self) -> Self {
+    self.multiple = true;
+    self
+  }
+
+  /// Returns a description of the argument for messages, such as `--port` or
+  /// `<file>`.
+  fn describe(&self) -> String {
+    match self.kind {
+      ArgKind::Positional => format!("<{}>", self.long),
+      _ => format!("--{}", self.long),
+    }
+  }
+}
@@ -0,0 +1,134 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{Arg, ArgKind, Command};
+use crate::prelude::*;
+
+/// Renders the help message of a command.
+///
+/// The path is the name of the command and its parent commands.
+pub(super) fn render(command: &Command, path: &str) -> String {
+  let mut help = String::new();
+
+  if !command.about.is_empty() {
+    writeln!(help, "{}\n", command.about).unwrap();
+  }
+
+  // Write a usage line.
+
+  write!(help, "Usage: {}", path).unwrap();
+
+  if command.args.iter().any(|arg| arg.kind != ArgKind::Positional && !arg.required) {
+    help.push_str(" [options]");
+  }
+
+  for arg in &command.args {
+    match arg.kind {
+      ArgKind::Positional => write!(help, " {}", positional_label(arg)).unwrap(),
+      _ if arg.required => write!(help, " {}", option_label(arg).trim_start()).unwrap(),
+      _ => continue,
+    }
+  }
+
+  match (command.subcommands.is_empty(), command.subcommand_required) {
+    (true, _) => {}
+    (false, true) => help.push_str(" <command>"),
+    (false, false) => help.push_str(" [<command>]"),
+  }
+
+  help.push('\n');
+
+  // Write a section for each kind of argument.
+
+  let positionals: Vec<_> = command
+    .args
+    .iter()
+    .filter(|arg| arg.kind == ArgKind::Positional)
+    .map(|arg| (positional_label(arg), describe(arg)))
+    .collect();
+
+  let options: Vec<_> = command
+    .args
+    .iter()
+    .filter(|arg| arg.kind != ArgKind::Positional)
+    .map(|arg| (option_label(arg), describe(arg)))
+    .chain(Some(("-h, --help".into(), "Prints this help message.".into())))
+    .collect();
+
+  let commands: Vec<_> = command
+    .subcommands
+    .iter()
+    .map(|command| (command.name.clone(), first_line(&command.about).into()))
+    .collect();
+
+  let width = positionals.iter().chain(&options).chain(&commands).map(|(l, _)| l.len()).max();
+  let width = width.unwrap_or_default();
+
+  for (title, rows) in &[("Arguments", positionals), ("Options", options), ("Commands", commands)] {
+    if rows.is_empty() {
+      continue;
+    }
+
+    write!(help, "\n{}:\n", title).unwrap();
+
+    for (label, description) in rows {
+      writeln!(help, "  {:width$}  {}", label, description, width = width).unwrap();
+    }
+  }
+
+  help
+}
+
+/// Returns the first line of a description.
+fn first_line(text: &str) -> &str {
+  text.lines().next().unwrap_or_default()
+}
+
+/// Returns a description of an argument including its environment variable
+/// and default value.
+fn describe(arg: &Arg) -> String {
+  let mut description = String::from(first_line(&arg.help));
+
+  if let Some(env) = &arg.env {
+    write!(description, " [env: {}]", env).unwrap();
+  }
+
+  if let Some(default) = &arg.default {
+    write!(description, " [default: {}]", default).unwrap();
+  }
+
+  description.trim_start().into()
+}
+
+/// Returns the label of a flag or option, such as `-p, --port <port>`.
+fn option_label(arg: &Arg) -> String {
+  let mut label = match arg.short {
+    Some(short) => format!("-{}, --{}", short, arg.long),
+    None => format!("    --{}", arg.long),
+  };
+
+  if arg.kind == ArgKind::Option {
+    write!(label, " <{}>", arg.long).unwrap();
+  }
+
+  label
+}
+
+/// Returns the label of a positional argument, such as `<file>` or
+/// `[<files>...]`.
+fn positional_label(arg: &Arg) -> String {
+  let mut label = format!("<{}>", arg.long);
+
+  if arg.multiple {
+    label.push_str("...");
+  }
+
+  if !arg.required {
+    label = format!("[{}]", label);
+  }
+
+  label
+}
@@ -0,0 +1,328 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{help, Arg, ArgKind, Command, ParseError, Subcommands};
+use crate::env;
+use crate::prelude::*;
+use std::collections::HashMap;
+
+/// Arguments matched by parsing the arguments of a [`Command`].
+#[derive(Debug, Default)]
+pub struct Matches {
+  values: HashMap<String, Matched>,
+  subcommand: Option<(String, Box<Matches>)>,
+}
+
+/// The values matched for an argument.
+#[derive(Debug)]
+struct Matched {
+  source: String,
+  values: Vec<String>,
+  from_env: bool,
+}
+
+/// Parses arguments for a command.
+///
+/// The path is the name of the command and its parent commands, used in help.
+pub(super) fn parse(
+  command: &Command,
+  path: &str,
+  input: &[String],
+) -> Result<Matches, ParseError> {
+  let invalid = |message: String| ParseError::Invalid(fail::Error::new(message));
+  let positionals: Vec<_> = command.args.iter().filter(|a| a.kind == ArgKind::Positional).collect();
+  let mut matches = Matches::default();
+  let mut next_positional = 0;
+  let mut only_positionals = false;
+  let mut args = input.iter().enumerate();
+
+  while let Some((i, arg)) = args.next() {
+    if !only_positionals && arg.len() > 1 && arg.starts_with('-') {
+      if arg == "--" {
+        only_positionals = true;
+        continue;
+      }
+
+      if arg == "--help" || arg == "-h" {
+        return Err(ParseError::Help(help::render(command, path)));
+      }
+
+      // Split the argument into options and an optional inline value, such
+      // as `--port=80`, `-p80`, or `-vp 80`.
+
+      let options: Vec<(&Arg, Option<String>)> = match arg.strip_prefix("--") {
+        Some(long) => {
+          let (name, value) = match long.find('=') {
+            Some(j) => (&long[..j], Some(long[j + 1..].into())),
+            None => (long, None),
+          };
+
+          let option = command
+            .args
+            .iter()
+            .find(|a| a.kind != ArgKind::Positional && a.long == name)
+            .ok_or_else(|| invalid(format!("Unknown option `--{}`.", name)))?;
+
+          vec![(option, value)]
+        }
+
+        None => {
+          let mut options = Vec::new();
+
+          for (j, short) in arg.char_indices().skip(1) {
+            let option = command
+              .args
+              .iter()
+              .find(|a| a.kind != ArgKind::Positional && a.short == Some(short))
+              .ok_or_else(|| invalid(format!("Unknown option `-{}`.", short)))?;
+
+            let rest = &arg[j + short.len_utf8()..];
+
+            if option.kind == ArgKind::Option && !rest.is_empty() {
+              options.push((option, Some(rest.into())));
+              break;
+            }
+
+            options.push((option, None));
+          }
+
+          options
+        }
+      };
+
+      for (option, value) in options {
+        let value = match (option.kind, value) {
+          (ArgKind::Flag, None) => "true".into(),
+
+          (ArgKind::Flag, Some(_)) => {
+            return Err(invalid(format!("Option `{}` does not take a value.", option.describe())))
+          }
+
+          (_, Some(value)) => value,
+
+          (_, None) => match args.next() {
+            Some((_, value)) => value.clone(),
+            None => {
+              return Err(invalid(format!("Option `{}` requires a value.", option.describe())))
+            }
+          },
+        };
+
+        if !option.multiple && matches.values.contains_key(&option.id) {
+          return Err(invalid(format!("Option `{}` was given more than once.", option.describe())));
+        }
+
+        matches.push(option, value);
+      }
+
+      continue;
+    }
+
+    // Match positional arguments in order, then subcommands.
+
+    if let Some(positional) = positionals.get(next_positional) {
+      matches.push(positional, arg.clone());
+
+      if !positional.multiple {
+        next_positional += 1;
+      }
+
+      continue;
+    }
+
+    if !command.subcommands.is_empty() {
+      let subcommand = command
+        .subcommands
+        .iter()
+        .find(|c| c.name == *arg)
+        .ok_or_else(|| invalid(format!("Unknown command `{}`.", arg)))?;
+
+      let path = format!("{} {}", path, subcommand.name);
+      let sub_matches = parse(subcommand, &path, &input[i + 1..])?;
+
+      matches.subcommand = Some((subcommand.name.clone(), Box::new(sub_matches)));
+      break;
+    }
+
+    return Err(invalid(format!("Unexpected argument `{}`.", arg)));
+  }
+
+  // Fall back to environment variables and defaults for missing arguments.
+
+  for arg in &command.args {
+    if matches.values.contains_key(&arg.id) {
+      continue;
+    }
+
+    if let Some(Ok(value)) = arg.env.as_deref().map(env::var) {
+      let source = format!("environment variable `{}`", arg.env.as_deref().unwrap_or_default());
+
+      matches
+        .values
+        .insert(arg.id.clone(), Matched { source, values: vec![value], from_env: true });
+
+      continue;
+    }
+
+    if let Some(value) = &arg.default {
+      matches.push(arg, value.clone());
+      continue;
+    }
+
+    if arg.required {
+      return Err(invalid(format!("Missing required argument `{}`.", arg.describe())));
+    }
+  }
+
+  if matches.subcommand.is_none() && command.subcommand_required {
+    let names = command.subcommands.iter().map(|c| format!("`{}`", c.name)).join(", ");
+
+    return Err(invalid(format!("Missing command. Expected one of {}.", names)));
+  }
+
+  Ok(matches)
+}
+
+impl Matches {
+  /// Returns `true` if the given flag was given.
+  ///
+  /// If the flag was read from an environment variable, its value is parsed
+  /// as an [`env::Bool`].
+  pub fn flag(&mut self, id: &str) -> Result<bool> {
+    match self.values.remove(id) {
+      Some(matched) if matched.from_env => {
+        Ok(matched.parse::<env::Bool>(&matched.values[0])?.into())
+      }
+
+      Some(_) => Ok(true),
+      None => Ok(false),
+    }
+  }
+
+  /// Returns the parsed value of the given argument, or `None` if it was not
+  /// given.
+  ///
+  /// If the argument was given more than once, the last value is returned.
+  pub fn value<T>(&mut self, id: &str) -> Result<Option<T>>
+  where
+    T: FromStr,
+    T::Err: Display,
+  {
+    let matched = match self.values.remove(id) {
+      Some(matched) => matched,
+      None => return Ok(None),
+    };
+
+    match matched.values.last() {
+      Some(value) => matched.parse(value).map(Some),
+      None => Ok(None),
+    }
+  }
+
+  /// Returns the parsed value of the given argument, or an error if it was not
+  /// given.
+  pub fn required<T>(&mut self, id: &str) -> Result<T>
+  where
+    T: FromStr,
+    T::Err: Display,
+  {
+    self.value(id)?.ok_or_else(|| fail::err!("Missing required argument `{}`.", id))
+  }
+
+  /// Returns the parsed values of the given argument.
+  ///
+  /// If the argument was read from an environment variable, its value is
+  /// split on commas.
+  pub fn values<T>(&mut self, id: &str) -> Result<Vec<T>>
+  where
+    T: FromStr,
+    T::Err: Display,
+  {
+    let matched = match self.values.remove(id) {
+      Some(matched) => matched,
+      None => return Ok(Vec::new()),
+    };
+
+    if matched.from_env {
+      return Ok(matched.parse::<env::List<T>>(&matched.values[0])?.into());
+    }
+
+    matched.values.iter().map(|value| matched.parse(value)).collect()
+  }
+
+  /// Returns the parsed subcommand, or `None` if no subcommand was given.
+  pub fn subcommand<T: Subcommands>(&mut self) -> Result<Option<T>> {
+    match self.subcommand.take() {
+      Some((name, mut matches)) => T::from_matches(&name, &mut matches).map(Some),
+      None => Ok(None),
+    }
+  }
+
+  /// Returns the parsed subcommand, or an error if no subcommand was given.
+  pub fn required_subcommand<T: Subcommands>(&mut self) -> Result<T> {
+    self.subcommand()?.ok_or_else(|| fail::err!("Missing command."))
+  }
+
+  /// Adds a value for the given argument.
+  fn push(&mut self, arg: &Arg, value: String) {
+    let matched = self.values.entry(arg.id.clone()).or_insert_with(|| Matched {
+      source: format!("`{}`", arg.describe()),
+      values: Vec::new(),
+      from_env: false,
+    });
+
+    matched.values.push(value);
+  }
+}
+
+impl Matched {
+  /// Parses one of the matched values.
+  fn parse<T>(&self, value: &str) -> Result<T>
+  where
+    T: FromStr,
+    T::Err: Display,
+  {
+    value.parse().map_err(fail::with!("Invalid value `{}` for {}", value, self.source))
+  }
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse_args(args: &[&str]) -> Result<Matches, ParseError> {
+    let command = Command::new("test")
+      .arg(Arg::flag("verbose").short('v'))
+      .arg(Arg::option("port").short('p').default("80"))
+      .arg(Arg::option("tag").multiple())
+      .arg(Arg::positional("file").required());
+
+    let args: Vec<String> = args.iter().map(|&arg| arg.into()).collect();
+
+    parse(&command, "test", &args)
+  }
+
+  #[test]
+  fn test_parse() {
+    let mut matches = parse_args(&["-vp8080", "--tag=a", "--tag", "b", "--", "-file"]).unwrap();
+
+    assert!(matches.flag("verbose").unwrap());
+    assert_eq!(matches.required::<u16>("port").unwrap(), 8080);
+    assert_eq!(matches.values::<String>("tag").unwrap(), ["a", "b"]);
+    assert_eq!(matches.required::<String>("file").unwrap(), "-file");
+
+    let mut matches = parse_args(&["file"]).unwrap();
+
+    assert!(!matches.flag("verbose").unwrap());
+    assert_eq!(matches.required::<u16>("port").unwrap(), 80);
+
+    assert!(matches!(parse_args(&["--help"]), Err(ParseError::Help(_))));
+    assert!(matches!(parse_args(&[]), Err(ParseError::Invalid(_))));
+    assert!(matches!(parse_args(&["-p", "1", "-p", "2", "file"]), Err(ParseError::Invalid(_))));
+  }
+}
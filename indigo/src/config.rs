@@ -0,0 +1,60 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Utilities for loading configuration.
+
+mod de;
+mod loader;
+
+#[cfg(feature = "fs-watch")]
+mod watched;
+
+pub use self::loader::Loader;
+
+#[cfg(feature = "fs-watch")]
+pub use self::watched::Watched;
+
+use crate::fs;
+use crate::prelude::*;
+use serde::de::DeserializeOwned;
+
+/// Parses the contents of a config file in the format indicated by its
+/// extension.
+///
+/// Currently, only JSON files with the `.json` extension are supported.
+pub fn parse<T: DeserializeOwned>(path: &str, contents: &str) -> Result<T> {
+  match fs::path::extension(path) {
+    Some("json") => json::from_str(contents).map_err(|err| {
+      fail::Error::join(format_args!("Failed to parse {}", fmt::AsPath(path).describe()), err)
+    }),
+
+    _ => fail!("Failed to parse {}. Unsupported file format.", fmt::AsPath(path).describe()),
+  }
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse() {
+    assert_eq!(parse::<Vec<u32>>("/config/a.json", "[1, 2]").unwrap(), [1, 2]);
+
+    assert_eq!(
+      parse::<u32>("/config/a.json", "a").unwrap_err().to_string(),
+      "Failed to parse `/config/a.json`. Expected value at line 1 column 1."
+    );
+
+    for path in &["/config/a.toml", "/config/json", "/config/.json"] {
+      assert_eq!(
+        parse::<u32>(path, "1").unwrap_err().to_string(),
+        format!("Failed to parse `{}`. Unsupported file format.", path)
+      );
+    }
+  }
+}
@@ -0,0 +1,439 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::prelude::*;
+use serde::de::{self, IntoDeserializer, Unexpected, Visitor};
+use std::collections::BTreeMap;
+
+/// A node in a tree of config values merged from multiple sources.
+pub(super) enum Node {
+  /// A table of named nodes.
+  Table(BTreeMap<String, Node>),
+  /// A typed value and a description of its source.
+  Value(json::Value, Rc<str>),
+  /// A text value that is parsed into the expected type and a description of
+  /// its source.
+  Text(String, Rc<str>),
+}
+
+/// An error that occurred while deserializing a [`Node`].
+#[derive(Debug)]
+pub(super) struct Error {
+  /// The message describing the error.
+  pub message: String,
+  /// The key and source of the value that failed, if known.
+  pub location: Option<String>,
+}
+
+/// A deserializer for a [`Node`] with the given key.
+struct NodeDeserializer<'a> {
+  node: &'a Node,
+  key: String,
+}
+
+/// A deserializer that parses a text value into the expected type.
+struct TextDeserializer<'a>(&'a str);
+
+/// Provides access to the entries of a table.
+struct TableAccess<'a, 'k> {
+  entries: std::collections::btree_map::Iter<'a, String, Node>,
+  key: &'k str,
+  entry: Option<(&'a String, &'a Node)>,
+}
+
+/// Deserializes a value from a tree of nodes.
+pub(super) fn deserialize<T: de::DeserializeOwned>(node: &Node) -> Result<T, Error> {
+  T::deserialize(NodeDeserializer { node, key: String::new() })
+}
+
+impl Node {
+  /// Creates a new node from a JSON value, converting objects into tables.
+  pub fn from_json(value: json::Value, source: &Rc<str>) -> Self {
+    match value {
+      json::Value::Object(map) => {
+        Node::Table(map.into_iter().map(|(k, v)| (k, Node::from_json(v, source))).collect())
+      }
+
+      value => Node::Value(value, source.clone()),
+    }
+  }
+
+  /// Inserts a node at the given nested key, replacing any existing value and
+  /// creating tables as needed.
+  pub fn insert(&mut self, keys: &[String], node: Node) {
+    let (first, rest) = match keys.split_first() {
+      Some(split) => split,
+
+      None => {
+        *self = node;
+        return;
+      }
+    };
+
+    if !matches!(self, Node::Table(_)) {
+      *self = Node::Table(default());
+    }
+
+    if let Node::Table(table) = self {
+      table.entry(first.clone()).or_insert_with(|| Node::Table(default())).insert(rest, node);
+    }
+  }
+
+  /// Merges another node into this one.
+  ///
+  /// Tables are merged recursively. Any other value replaces this one.
+  pub fn merge(&mut self, other: Node) {
+    match (self, other) {
+      (Node::Table(table), Node::Table(other)) => {
+        for (key, node) in other {
+          match table.get_mut(&key) {
+            Some(existing) => existing.merge(node),
+            None => drop(table.insert(key, node)),
+          }
+        }
+      }
+
+      (this, other) => *this = other,
+    }
+  }
+}
+
+impl NodeDeserializer<'_> {
+  /// Returns a function that adds the key and source of this node to an error
+  /// that does not already have a location.
+  fn locate<E: Display>(&self, source: Option<&str>) -> impl FnOnce(E) -> Error + '_ {
+    let key = &self.key;
+    let source = source.map(String::from);
+
+    move |err| {
+      let location = match (key.is_empty(), source) {
+        (true, None) => None,
+        (true, Some(source)) => Some(format!("from {}", source)),
+        (false, None) => Some(format!("`{}`", key)),
+        (false, Some(source)) => Some(format!("`{}` from {}", key, source)),
+      };
+
+      Error { message: err.to_string(), location }
+    }
+  }
+
+  /// Deserializes a table as a map.
+  fn deserialize_table<'de, V: Visitor<'de>>(
+    &self,
+    table: &'de BTreeMap<String, Node>,
+    visitor: V,
+  ) -> Result<V::Value, Error> {
+    let access = TableAccess { entries: table.iter(), key: &self.key, entry: None };
+
+    visitor.visit_map(access).map_err(|err| match err.location {
+      Some(_) => err,
+      None => self.locate(None)(err.message),
+    })
+  }
+}
+
+/// Implements `Deserializer` methods for a [`NodeDeserializer`] by forwarding
+/// them to the deserializer for the type of node.
+macro_rules! forward_node {
+  ($($method:ident$(($($arg:ident: $ty:ty),*))?),*) => {
+    $(
+      fn $method<V: Visitor<'de>>(self, $($($arg: $ty,)*)? visitor: V) -> Result<V::Value, Error> {
+        match self.node {
+          Node::Table(table) => self.deserialize_table(table, visitor),
+
+          Node::Value(value, source) => {
+            value.$method($($($arg,)*)? visitor).map_err(self.locate(Some(source)))
+          }
+
+          Node::Text(text, source) => {
+            TextDeserializer(text).$method($($($arg,)*)? visitor).map_err(|err| {
+              self.locate(Some(source))(err.message)
+            })
+          }
+        }
+      }
+    )*
+  };
+}
+
+impl<'de> de::Deserializer<'de> for NodeDeserializer<'de> {
+  type Error = Error;
+
+  forward_node!(
+    deserialize_any,
+    deserialize_bool,
+    deserialize_i8,
+    deserialize_i16,
+    deserialize_i32,
+    deserialize_i64,
+    deserialize_u8,
+    deserialize_u16,
+    deserialize_u32,
+    deserialize_u64,
+    deserialize_f32,
+    deserialize_f64,
+    deserialize_char,
+    deserialize_str,
+    deserialize_string,
+    deserialize_bytes,
+    deserialize_byte_buf,
+    deserialize_unit,
+    deserialize_unit_struct(name: &'static str),
+    deserialize_seq,
+    deserialize_tuple(len: usize),
+    deserialize_tuple_struct(name: &'static str, len: usize),
+    deserialize_map,
+    deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+    deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+    deserialize_identifier,
+    deserialize_ignored_any
+  );
+
+  fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+    match self.node {
+      Node::Table(_) => visitor.visit_some(self),
+      Node::Value(value, source) => {
+        value.deserialize_option(visitor).map_err(self.locate(Some(source)))
+      }
+
+      Node::Text(text, source) => TextDeserializer(text)
+        .deserialize_option(visitor)
+        .map_err(|err| self.locate(Some(source))(err.message)),
+    }
+  }
+
+  fn deserialize_newtype_struct<V: Visitor<'de>>(
+    self,
+    _: &'static str,
+    visitor: V,
+  ) -> Result<V::Value, Error> {
+    visitor.visit_newtype_struct(self)
+  }
+}
+
+/// Implements `Deserializer` methods for a [`TextDeserializer`] that parse
+/// the text into a number.
+macro_rules! parse_number {
+  ($($method:ident => $visit:ident),*) => {
+    $(
+      fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0.trim().parse() {
+          Ok(value) => visitor.$visit(value),
+          Err(err) => Err(de::Error::custom(err)),
+        }
+      }
+    )*
+  };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for TextDeserializer<'a> {
+  type Error = Error;
+
+  parse_number!(
+    deserialize_i8 => visit_i8,
+    deserialize_i16 => visit_i16,
+    deserialize_i32 => visit_i32,
+    deserialize_i64 => visit_i64,
+    deserialize_u8 => visit_u8,
+    deserialize_u16 => visit_u16,
+    deserialize_u32 => visit_u32,
+    deserialize_u64 => visit_u64,
+    deserialize_f32 => visit_f32,
+    deserialize_f64 => visit_f64
+  );
+
+  serde::forward_to_deserialize_any!(char str string identifier);
+
+  fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+    visitor.visit_str(self.0)
+  }
+
+  fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+    match self.0.trim().to_ascii_lowercase().as_str() {
+      "true" | "yes" | "on" | "1" => visitor.visit_bool(true),
+      "false" | "no" | "off" | "0" => visitor.visit_bool(false),
+      _ => Err(de::Error::invalid_value(Unexpected::Str(self.0), &visitor)),
+    }
+  }
+
+  fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+    visitor.visit_bytes(self.0.as_bytes())
+  }
+
+  fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+    visitor.visit_bytes(self.0.as_bytes())
+  }
+
+  fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+    match self.0.is_empty() {
+      true => visitor.visit_none(),
+      false => visitor.visit_some(self),
+    }
+  }
+
+  fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+    visitor.visit_unit()
+  }
+
+  fn deserialize_unit_struct<V: Visitor<'de>>(
+    self,
+    _: &'static str,
+    visitor: V,
+  ) -> Result<V::Value, Error> {
+    visitor.visit_unit()
+  }
+
+  fn deserialize_newtype_struct<V: Visitor<'de>>(
+    self,
+    _: &'static str,
+    visitor: V,
+  ) -> Result<V::Value, Error> {
+    visitor.visit_newtype_struct(self)
+  }
+
+  /// Parses a comma-separated list.
+  fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+    let items = self.0.split(',').map(str::trim).filter(|item| !item.is_empty());
+
+    visitor.visit_seq(de::value::SeqDeserializer::new(items.map(TextDeserializer)))
+  }
+
+  fn deserialize_tuple<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, Error> {
+    self.deserialize_seq(visitor)
+  }
+
+  fn deserialize_tuple_struct<V: Visitor<'de>>(
+    self,
+    _: &'static str,
+    _: usize,
+    visitor: V,
+  ) -> Result<V::Value, Error> {
+    self.deserialize_seq(visitor)
+  }
+
+  fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+    Err(de::Error::invalid_type(Unexpected::Str(self.0), &visitor))
+  }
+
+  fn deserialize_struct<V: Visitor<'de>>(
+    self,
+    _: &'static str,
+    _: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value, Error> {
+    self.deserialize_map(visitor)
+  }
+
+  fn deserialize_enum<V: Visitor<'de>>(
+    self,
+    _: &'static str,
+    _: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value, Error> {
+    visitor.visit_enum(self.0.trim().into_deserializer())
+  }
+
+  fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+    visitor.visit_unit()
+  }
+}
+
+impl<'de, 'a> IntoDeserializer<'de, Error> for TextDeserializer<'a> {
+  type Deserializer = Self;
+
+  fn into_deserializer(self) -> Self {
+    self
+  }
+}
+
+// Implement `MapAccess` to deserialize tables.
+
+impl<'de> de::MapAccess<'de> for TableAccess<'de, '_> {
+  type Error = Error;
+
+  fn next_key_seed<K: de::DeserializeSeed<'de>>(
+    &mut self,
+    seed: K,
+  ) -> Result<Option<K::Value>, Error> {
+    self.entry = self.entries.next();
+
+    match self.entry {
+      Some((name, _)) => seed.deserialize(name.as_str().into_deserializer()).map(Some),
+      None => Ok(None),
+    }
+  }
+
+  fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+    let (name, node) = self.entry.take().expect("Called `next_value_seed` before `next_key_seed`.");
+
+    let key = match self.key.is_empty() {
+      true => name.clone(),
+      false => format!("{}.{}", self.key, name),
+    };
+
+    seed.deserialize(NodeDeserializer { node, key })
+  }
+}
+
+// Implement `Error` for deserialization errors.
+
+impl de::Error for Error {
+  fn custom<T: Display>(msg: T) -> Self {
+    Self { message: msg.to_string(), location: None }
+  }
+}
+
+impl std::error::Error for Error {}
+
+impl Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match &self.location {
+      Some(location) => write!(f, "{} (at {})", self.message, location),
+      None => write!(f, "{}", self.message),
+    }
+  }
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  type Tables<T> = BTreeMap<String, BTreeMap<String, T>>;
+
+  #[test]
+  fn test_merge() {
+    let defaults: Rc<str> = "defaults".into();
+    let env: Rc<str> = "env".into();
+
+    let mut root =
+      Node::from_json(json::json!({ "db": { "port": 5432, "tls": false } }), &defaults);
+
+    root.insert(&["db".into(), "tls".into()], Node::Text("yes".into(), env.clone()));
+
+    let config: Tables<json::Value> = deserialize(&root).unwrap();
+
+    assert_eq!(config["db"]["port"], 5432);
+    assert_eq!(config["db"]["tls"], "yes");
+
+    let mut root = Node::from_json(json::json!({ "db": { "hosts": ["localhost"] } }), &defaults);
+
+    root.insert(&["db".into(), "hosts".into()], Node::Text("a, b".into(), env.clone()));
+
+    let config: Tables<Vec<String>> = deserialize(&root).unwrap();
+
+    assert_eq!(config["db"]["hosts"], ["a", "b"]);
+
+    let mut root = Node::from_json(json::json!({ "db": { "port": 5432 } }), &defaults);
+
+    root.insert(&["db".into(), "port".into()], Node::Text("x".into(), env));
+
+    let err = deserialize::<Tables<u16>>(&root).unwrap_err();
+
+    assert_eq!(err.location.as_deref(), Some("`db.port` from env"));
+  }
+}
@@ -0,0 +1,220 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::de::{self, Node};
+use crate::env;
+use crate::fs::path;
+use crate::prelude::*;
+use serde::de::DeserializeOwned;
+
+/// Loads configuration by merging values from multiple sources.
+///
+/// Sources are merged in the following order, with later sources overriding
+/// earlier ones:
+///
+/// 1. Defaults set with [`defaults()`](Self::defaults).
+/// 2. Config files added with [`file()`](Self::file), in the order they were
+///    added.
+/// 3. Prefixed variables in a `.env` file, if the `dotenv` feature is enabled.
+/// 4. Prefixed environment variables.
+///
+/// Environment variables map to nested keys by splitting on double
+/// underscores. For example, with the prefix `APP`, the variable
+/// `APP_DB__HOST` sets the `host` key of the `db` table.
+#[derive(Default)]
+pub struct Loader {
+  defaults: Option<Result<json::Value>>,
+  files: Vec<(String, bool)>,
+  env_prefix: Option<String>,
+}
+
+impl Loader {
+  /// Creates a new loader with no sources.
+  pub fn new() -> Self {
+    default()
+  }
+
+  /// Sets the default values to use for keys that are not set by any other
+  /// source.
+  pub fn defaults(mut self, value: &impl Serialize) -> Self {
+    let value = json::to_value(value).map_err(fail::with!("Failed to serialize config defaults."));
+
+    self.defaults = Some(value);
+    self
+  }
+
+  /// Sets the prefix of environment variables to load.
+  ///
+  /// Only variables that start with the prefix followed by an underscore are
+  /// loaded.
+  pub fn env_prefix(mut self, prefix: impl Into<String>) -> Self {
+    self.env_prefix = Some(prefix.into());
+    self
+  }
+
+  /// Adds an optional config file.
+  ///
+  /// If the path is relative, the file is looked up relative to the working
+  /// path, then the project path when using `cargo run`, then the path of the
+  /// current executable. The first file found is loaded.
+  ///
+  /// The format of the file is determined by its extension. See
+  /// [`config::parse()`](super::parse) for supported formats.
+  pub fn file(mut self, path: impl Into<String>) -> Self {
+    self.files.push((path.into(), false));
+    self
+  }
+
+  /// Adds a required config file.
+  ///
+  /// This is the same as [`file()`](Self::file) except that loading fails if
+  /// the file is not found.
+  pub fn required_file(mut self, path: impl Into<String>) -> Self {
+    self.files.push((path.into(), true));
+    self
+  }
+
+  /// Loads and merges all sources and deserializes the result.
+  pub fn load<T: DeserializeOwned>(&self) -> Result<T> {
+    let mut root = Node::Table(default());
+
+    // Start with the defaults.
+
+    if let Some(defaults) = &self.defaults {
+      let defaults = defaults.as_ref().map_err(Clone::clone)?;
+
+      root.merge(Node::from_json(defaults.clone(), &"defaults".into()));
+    }
+
+    // Then merge config files.
+
+    for (path, required) in &self.files {
+      let path = match find_file(path) {
+        Some(path) => path,
+        None if *required => {
+          fail!("Failed to load config file {}. File not found.", fmt::AsPath(path).describe())
+        }
+        None => continue,
+      };
+
+      let contents = std::fs::read_to_string(&path).map_err(|err| {
+        fail::Error::join(
+          format_args!("Failed to read config file {}", fmt::AsPath(&path).describe()),
+          err,
+        )
+      })?;
+
+      let value: json::Value = super::parse(&path, &contents)?;
+
+      if !value.is_object() {
+        fail!("Failed to load config file {}. Expected a table.", fmt::AsPath(&path).describe());
+      }
+
+      let source = format!("config file {}", fmt::AsPath(&path).describe());
+
+      root.merge(Node::from_json(value, &source.into()));
+    }
+
+    // Then merge prefixed variables from `.env` and the environment.
+
+    if let Some(prefix) = &self.env_prefix {
+      #[cfg(feature = "dotenv")]
+      merge_dotenv(&mut root, prefix)?;
+
+      for (name, value) in std::env::vars_os() {
+        if let (Ok(name), Ok(value)) = (name.into_string(), value.into_string()) {
+          let source: Rc<str> = format!("environment variable `{}`", name).into();
+
+          insert_var(&mut root, prefix, &name, value, &source);
+        }
+      }
+    }
+
+    de::deserialize(&root).map_err(|err| match err.location {
+      Some(location) => {
+        fail::Error::join(format_args!("Invalid config value {}", location), err.message)
+      }
+      None => fail::Error::join("Invalid config", err.message),
+    })
+  }
+}
+
+/// Finds a config file relative to the working path, project path, or
+/// executable path.
+fn find_file(file: &str) -> Option<String> {
+  if path::is_absolute(file) {
+    return Some(String::from(file)).filter(|file| is_file(file));
+  }
+
+  let mut dirs = Vec::with_capacity(3);
+
+  if let Ok(working_path) = env::working_path() {
+    dirs.push(Cow::Owned(working_path));
+  }
+
+  if env::is_cargo_run() {
+    dirs.push(env::project_path().into());
+  }
+
+  dirs.push(env::exe_path().into());
+
+  dirs.into_iter().map(|dir| path::join(dir, file).into_owned()).find(|file| is_file(file))
+}
+
+/// Returns `true` if the given path is an existing file.
+fn is_file(path: &str) -> bool {
+  std::fs::metadata(path).map(|m| m.is_file()).unwrap_or_default()
+}
+
+/// Inserts the value of a variable into the tree at its nested key if it has
+/// the given prefix.
+fn insert_var(root: &mut Node, prefix: &str, name: &str, value: String, source: &Rc<str>) {
+  let key = match name.strip_prefix(prefix).and_then(|name| name.strip_prefix('_')) {
+    Some(key) if !key.is_empty() => key,
+    _ => return,
+  };
+
+  let keys: Vec<_> = key.split("__").map(str::to_lowercase).collect();
+
+  root.insert(&keys, Node::Text(value, source.clone()));
+}
+
+/// Merges prefixed variables from a `.env` file in the working path or one of
+/// its parent directories.
+#[cfg(feature = "dotenv")]
+fn merge_dotenv(root: &mut Node, prefix: &str) -> Result {
+  let mut dir = match env::working_path() {
+    Ok(dir) => dir,
+    Err(_) => return Ok(()),
+  };
+
+  loop {
+    let file = path::join(&dir, ".env").into_owned();
+
+    if is_file(&file) {
+      let failed = |err: &dyn Display| {
+        fail::err!("Failed to load {}. {}.", fmt::AsPath(&file).describe(), err)
+      };
+
+      // The iterator is deprecated only because it does not set variables.
+      #[allow(deprecated)]
+      let vars = dotenv_crate::from_path_iter(&file).map_err(|err| failed(&err))?;
+      let source: Rc<str> = format!("file {}", fmt::AsPath(&file).describe()).into();
+
+      for var in vars {
+        let (name, value) = var.map_err(|err| failed(&err))?;
+
+        insert_var(root, prefix, &name, value, &source);
+      }
+
+      return Ok(());
+    }
+
+    if path::pop(&mut dir).is_none() {
+      return Ok(());
+    }
+  }
+}
@@ -0,0 +1,291 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::fs::{self, watch::Event, Watcher};
+use crate::prelude::*;
+use crate::sync::blocking::RwLock;
+use crate::sync::CancellationToken;
+use crate::thread;
+use serde::de::DeserializeOwned;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A config file that is reloaded when it changes.
+///
+/// Cloned handles share the same value. The file stops being watched when
+/// all handles are dropped.
+pub struct Watched<T> {
+  shared: Arc<Shared<T>>,
+  version: u64,
+}
+
+/// State shared by all handles to a [`Watched`] config file.
+struct Shared<T> {
+  path: String,
+  value: RwLock<Arc<T>>,
+  version: AtomicU64,
+  changed: event_listener::Event,
+  stop: CancellationToken,
+}
+
+/// A function that validates a config value.
+type Validator<T> = Box<dyn Fn(&T) -> Result + Send>;
+
+impl<T> Watched<T>
+where
+  T: DeserializeOwned + Send + Sync + 'static,
+{
+  /// Loads a config file and reloads it whenever it changes.
+  ///
+  /// The format of the file is determined by its extension. See
+  /// [`config::parse()`](super::parse) for supported formats.
+  pub async fn load<'a>(path: impl fs::PathLike<'a>) -> Result<Self> {
+    Self::load_validated(path, |_| Ok(())).await
+  }
+
+  /// Loads a config file and reloads it whenever it changes, rejecting values
+  /// that fail the given validation function.
+  ///
+  /// If a reload fails, the last valid value is kept and the error is logged.
+  pub async fn load_validated<'a>(
+    path: impl fs::PathLike<'a>,
+    validate: impl Fn(&T) -> Result + Send + 'static,
+  ) -> Result<Self> {
+    let path = fs::path::resolved(path.try_to_cow()?)
+      .map_err(fail::with!("Failed to resolve config file path."))?
+      .into_owned();
+
+    let contents = fs::read_to_string(&path).await?;
+    let value = load(&path, &validate, &contents)?;
+
+    // Watch the parent directory instead of the file itself so that the file
+    // can be replaced.
+
+    let mut watcher = Watcher::new()?;
+
+    watcher.watch_dir_non_recursive(fs::path::parent(&path).unwrap_or_default())?;
+
+    let shared = Arc::new(Shared {
+      path,
+      value: RwLock::new(Arc::new(value)),
+      version: AtomicU64::new(0),
+      changed: event_listener::Event::new(),
+      stop: CancellationToken::new(),
+    });
+
+    let weak = Arc::downgrade(&shared);
+    let stop = shared.stop.clone();
+
+    thread::start_detached("indigo::config::Watched", move || {
+      thread::block_on(reload_on_change(watcher, weak, stop, contents, Box::new(validate)))
+    });
+
+    Ok(Self { shared, version: 0 })
+  }
+
+  /// Waits for the config file to be reloaded with a new value, then returns
+  /// it.
+  ///
+  /// If the file has been reloaded since this handle last returned a value,
+  /// this function returns immediately.
+  pub async fn changed(&mut self) -> Arc<T> {
+    loop {
+      if self.shared.version.load(Ordering::Acquire) > self.version {
+        break;
+      }
+
+      let listener = self.shared.changed.listen();
+
+      if self.shared.version.load(Ordering::Acquire) > self.version {
+        break;
+      }
+
+      listener.await;
+    }
+
+    self.version = self.shared.version.load(Ordering::Acquire);
+    self.get()
+  }
+
+  /// Returns the current value of the config file.
+  pub fn get(&self) -> Arc<T> {
+    self.shared.value.read().clone()
+  }
+
+  /// Returns the absolute path of the config file.
+  pub fn path(&self) -> &str {
+    &self.shared.path
+  }
+}
+
+/// Parses and validates the contents of a config file.
+fn load<T: DeserializeOwned>(
+  path: &str,
+  validate: &dyn Fn(&T) -> Result,
+  contents: &str,
+) -> Result<T> {
+  let value = super::parse(path, contents)?;
+
+  validate(&value).map_err(|err| {
+    fail::Error::join(format_args!("Invalid config file {}", fmt::AsPath(path).describe()), err)
+  })?;
+
+  Ok(value)
+}
+
+/// Reloads a config file whenever the watcher outputs an event for it until
+/// all handles are dropped.
+async fn reload_on_change<T: DeserializeOwned>(
+  mut watcher: Watcher,
+  shared: ArcWeak<Shared<T>>,
+  stop: CancellationToken,
+  mut contents: String,
+  validate: Validator<T>,
+) {
+  while let Some(Some(event)) = future::until_cancelled(&stop, watcher.next()).await {
+    let shared = match shared.upgrade() {
+      Some(shared) => shared,
+      None => return,
+    };
+
+    let changed_path = match &event {
+      Ok(Event::Created(path)) | Ok(Event::Modified(path)) => Some(path),
+      Ok(Event::Renamed { to, .. }) => Some(to),
+      Ok(Event::Rescan) => None,
+      Ok(Event::MetadataChanged(_)) | Ok(Event::Removed(_)) | Ok(Event::NonUnicode(_)) => continue,
+
+      Err(err) => {
+        error!("Failed to watch config file `{}`. {}", shared.path, err);
+        continue;
+      }
+    };
+
+    if let Some(path) = changed_path {
+      if fs::path::last(path) != fs::path::last(&shared.path) {
+        continue;
+      }
+    }
+
+    // Files are often written in several steps, so ignore events for files
+    // that are missing or unchanged.
+
+    let next_contents = match std::fs::read_to_string(&shared.path) {
+      Ok(next_contents) if next_contents == contents => continue,
+      Ok(next_contents) => next_contents,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+
+      Err(err) => {
+        error!("Failed to reload config file `{}`. {}.", shared.path, err);
+        continue;
+      }
+    };
+
+    match load(&shared.path, &*validate, &next_contents) {
+      Ok(value) => {
+        contents = next_contents;
+
+        *shared.value.write() = Arc::new(value);
+
+        shared.version.fetch_add(1, Ordering::AcqRel);
+        shared.changed.notify(usize::MAX);
+
+        info!("Reloaded config file `{}`.", shared.path);
+      }
+
+      Err(err) => error!("Failed to reload config file. {}", err),
+    }
+  }
+}
+
+// Implement `Clone` to share the config value.
+
+impl<T> Clone for Watched<T> {
+  fn clone(&self) -> Self {
+    Self { shared: self.shared.clone(), version: self.version }
+  }
+}
+
+// Implement `Drop` to stop watching the file.
+
+impl<T> Drop for Shared<T> {
+  fn drop(&mut self) {
+    self.stop.cancel();
+  }
+}
+
+// Unit tests.
+
+#[cfg(all(test, feature = "runtime"))]
+mod tests {
+  use super::*;
+  use crate::fs::TempDir;
+
+  /// Waits for the next value of a config file, or `None` if it does not
+  /// change within two seconds.
+  async fn next_value(config: &mut Watched<u32>) -> Option<u32> {
+    let timeout = async {
+      future::sleep(Duration::secs(2)).await;
+      None
+    };
+
+    future::race(async { Some(*config.changed().await) }, timeout).biased().await
+  }
+
+  #[test]
+  fn test_reload() {
+    let temp = TempDir::new();
+    let path = temp.join("config.json");
+
+    std::fs::write(&path, "1").unwrap();
+
+    thread::block_on(async {
+      let mut config = Watched::<u32>::load(&path).await.unwrap();
+
+      assert_eq!(*config.get(), 1);
+
+      std::fs::write(&path, "2").unwrap();
+
+      assert_eq!(next_value(&mut config).await, Some(2));
+
+      // Invalid contents keep the last valid value.
+
+      std::fs::write(&path, "{").unwrap();
+
+      assert_eq!(next_value(&mut config).await, None);
+      assert_eq!(*config.get(), 2);
+
+      std::fs::write(&path, "3").unwrap();
+
+      assert_eq!(next_value(&mut config).await, Some(3));
+    });
+  }
+
+  #[test]
+  fn test_validation() {
+    let temp = TempDir::new();
+    let path = temp.join("config.json");
+
+    std::fs::write(&path, "1").unwrap();
+
+    thread::block_on(async {
+      let validate = |value: &u32| match *value < 10 {
+        true => Ok(()),
+        false => fail!("Value is too large."),
+      };
+
+      let mut config = Watched::load_validated(&path, validate).await.unwrap();
+
+      std::fs::write(&path, "10").unwrap();
+
+      assert_eq!(next_value(&mut config).await, None);
+      assert_eq!(*config.get(), 1);
+
+      assert_eq!(
+        Watched::load_validated(&path, validate).await.err().unwrap().to_string(),
+        format!("Invalid config file `{}`. Value is too large.", path)
+      );
+    });
+  }
+}
@@ -6,6 +6,16 @@
 
 //! Functions for inspecting the environment of the current process.
 
+mod dirs;
+mod registry;
+mod values;
+
+pub use self::dirs::{
+  app_dir, cache_dir, config_dir, create_app_dir, data_dir, runtime_dir, state_dir, AppDir,
+};
+pub use self::registry::{enable_registry, help_env, registered_vars, VarInfo};
+pub use self::values::{Bool, ByteSize, List};
+
 use crate::fs::path;
 use crate::prelude::*;
 use std::io;
@@ -161,12 +171,71 @@ pub fn working_path() -> Result<String, WorkingPathError> {
 
 /// Returns the value of the given environment variable.
 pub fn var(name: &str) -> Result<String, VarError> {
-  std::env::var(name).map_err(|err| match err {
+  let value = std::env::var(name).map_err(|err| match err {
     std::env::VarError::NotPresent => VarError::NotPresent,
     std::env::VarError::NotUnicode(_) => VarError::NotUnicode,
+  });
+
+  registry::register::<String>(name, false, !matches!(value, Err(VarError::NotPresent)));
+
+  value
+}
+
+/// Returns the value of the given environment variable parsed as `T`, or
+/// `None` if it is not set.
+///
+/// Besides standard types, this function can parse durations such as `5s`,
+/// time zones, and the types in this module such as [`Bool`] and
+/// [`ByteSize`].
+pub fn var_parsed<T>(name: &str) -> Result<Option<T>>
+where
+  T: FromStr,
+  T::Err: Display,
+{
+  parse_var(name, false)
+}
+
+/// Returns the value of the given environment variable parsed as `T`, or a
+/// default value if it is not set.
+///
+/// See [`var_parsed()`] for supported types.
+pub fn var_or<T>(name: &str, default: T) -> Result<T>
+where
+  T: FromStr,
+  T::Err: Display,
+{
+  Ok(parse_var(name, true)?.unwrap_or(default))
+}
+
+/// Parses the value of an environment variable and records it in the registry.
+fn parse_var<T>(name: &str, has_default: bool) -> Result<Option<T>>
+where
+  T: FromStr,
+  T::Err: Display,
+{
+  let value = std::env::var(name);
+
+  registry::register::<T>(name, has_default, !matches!(value, Err(std::env::VarError::NotPresent)));
+
+  let value = match value {
+    Ok(value) => value,
+    Err(std::env::VarError::NotPresent) => return Ok(None),
+
+    Err(std::env::VarError::NotUnicode(_)) => {
+      fail!("Invalid value for environment variable `{}`. {}", name, VarError::NotUnicode)
+    }
+  };
+
+  value.parse().map(Some).map_err(|err| {
+    fail::Error::join(
+      format_args!("Invalid value `{}` for environment variable `{}`", value, name),
+      err,
+    )
   })
 }
 
+// Unit tests.
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -204,6 +273,25 @@ mod tests {
     assert_eq!(var("__TEST_VAR").unwrap(), "value");
   }
 
+  #[test]
+  fn test_var_parsed() {
+    std::env::remove_var("__TEST_VAR_PARSED");
+
+    assert_eq!(var_parsed::<u16>("__TEST_VAR_PARSED").unwrap(), None);
+    assert_eq!(var_or("__TEST_VAR_PARSED", 8080u16).unwrap(), 8080);
+
+    std::env::set_var("__TEST_VAR_PARSED", "80");
+
+    assert_eq!(var_or("__TEST_VAR_PARSED", 8080u16).unwrap(), 80);
+
+    std::env::set_var("__TEST_VAR_PARSED", "eighty");
+
+    assert_eq!(
+      var_parsed::<u16>("__TEST_VAR_PARSED").unwrap_err().to_string(),
+      "Invalid value `eighty` for environment variable `__TEST_VAR_PARSED`. Invalid digit found in string."
+    );
+  }
+
   #[test]
   fn test_working_path() {
     let working_path = working_path().unwrap();
@@ -0,0 +1,210 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::var;
+use crate::fs::path;
+use crate::prelude::*;
+
+/// One of the possible kinds of per-user application directories.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum AppDir {
+  /// Configuration files.
+  Config,
+  /// Non-essential cached data that can be deleted.
+  Cache,
+  /// Data files that should be kept.
+  Data,
+  /// State that should persist between restarts but is not important enough
+  /// to be kept with data files, such as logs or history.
+  State,
+  /// Runtime files such as sockets and pid files.
+  Runtime,
+}
+
+/// Returns the path to the per-user config directory of an application.
+///
+/// See [`app_dir()`] for how the path is determined.
+pub fn config_dir(app: &str) -> Result<String> {
+  app_dir(AppDir::Config, app)
+}
+
+/// Returns the path to the per-user cache directory of an application.
+///
+/// See [`app_dir()`] for how the path is determined.
+pub fn cache_dir(app: &str) -> Result<String> {
+  app_dir(AppDir::Cache, app)
+}
+
+/// Returns the path to the per-user data directory of an application.
+///
+/// See [`app_dir()`] for how the path is determined.
+pub fn data_dir(app: &str) -> Result<String> {
+  app_dir(AppDir::Data, app)
+}
+
+/// Returns the path to the per-user state directory of an application.
+///
+/// See [`app_dir()`] for how the path is determined.
+pub fn state_dir(app: &str) -> Result<String> {
+  app_dir(AppDir::State, app)
+}
+
+/// Returns the path to the per-user runtime directory of an application.
+///
+/// See [`app_dir()`] for how the path is determined.
+pub fn runtime_dir(app: &str) -> Result<String> {
+  app_dir(AppDir::Runtime, app)
+}
+
+/// Returns the path to a per-user directory of an application.
+///
+/// The path is determined by the first of the following that applies:
+///
+/// 1. An environment variable named after the app and the kind of directory,
+///    such as `MY_APP_CONFIG_DIR` for the app `my-app`.
+/// 2. The XDG environment variable for the kind of directory, such as
+///    `XDG_CONFIG_HOME`, joined with the app name.
+/// 3. The platform default joined with the app name. On Linux, these follow
+///    the XDG Base Directory specification, such as `~/.config/my-app`.
+///
+/// If `XDG_RUNTIME_DIR` is not set on Linux, the runtime directory falls back
+/// to the cache directory.
+pub fn app_dir(kind: AppDir, app: &str) -> Result<String> {
+  let override_var = format!(
+    "{}_{}_DIR",
+    app.to_uppercase().replace(|c: char| !c.is_alphanumeric(), "_"),
+    kind.name()
+  );
+
+  if let Ok(dir) = var(&override_var) {
+    return Ok(dir);
+  }
+
+  if let Ok(base) = var(kind.xdg_var()) {
+    // The spec requires relative paths to be ignored.
+
+    if path::is_absolute(&base) {
+      return Ok(path::join(base, app).into_owned());
+    }
+  }
+
+  let base = match default_base(kind)? {
+    Some(base) => base,
+
+    None => {
+      warn!("`XDG_RUNTIME_DIR` is not set. Using the cache directory for runtime files.");
+
+      return app_dir(AppDir::Cache, app);
+    }
+  };
+
+  Ok(path::join(base, app).into_owned())
+}
+
+/// Returns the path to a per-user directory of an application, creating it if
+/// it does not exist.
+///
+/// On Unix, new directories are only accessible by the current user.
+pub fn create_app_dir(kind: AppDir, app: &str) -> Result<String> {
+  let dir = app_dir(kind, app)?;
+  let mut builder = std::fs::DirBuilder::new();
+
+  builder.recursive(true);
+
+  #[cfg(unix)]
+  std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+
+  builder
+    .create(&dir)
+    .map_err(fail::with!("Failed to create directory {}", fmt::AsPath(&dir).describe()))?;
+
+  Ok(dir)
+}
+
+/// Returns the platform default base directory for a kind of app directory,
+/// or `None` if there is no default.
+fn default_base(kind: AppDir) -> Result<Option<String>> {
+  let base = match kind {
+    _ if cfg!(windows) => {
+      let var_name = match kind {
+        AppDir::Config | AppDir::Data => "APPDATA",
+        _ => "LOCALAPPDATA",
+      };
+
+      return var(var_name).map(Some).map_err(fail::with!("Failed to read `{}`", var_name));
+    }
+
+    AppDir::Cache if cfg!(target_os = "macos") => "Library/Caches",
+    AppDir::Runtime if cfg!(target_os = "macos") => "Library/Caches",
+    _ if cfg!(target_os = "macos") => "Library/Application Support",
+
+    AppDir::Config => ".config",
+    AppDir::Cache => ".cache",
+    AppDir::Data => ".local/share",
+    AppDir::State => ".local/state",
+    AppDir::Runtime => return Ok(None),
+  };
+
+  let home = home_dir()?;
+
+  Ok(Some(path::join(home, base).into_owned()))
+}
+
+/// Returns the home directory of the current user.
+fn home_dir() -> Result<String> {
+  let var_name = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+
+  var(var_name)
+    .map_err(fail::with!("Failed to find the home directory. Failed to read `{}`", var_name))
+}
+
+impl AppDir {
+  /// Returns the name of the kind of directory in environment variables.
+  fn name(self) -> &'static str {
+    match self {
+      Self::Config => "CONFIG",
+      Self::Cache => "CACHE",
+      Self::Data => "DATA",
+      Self::State => "STATE",
+      Self::Runtime => "RUNTIME",
+    }
+  }
+
+  /// Returns the name of the XDG environment variable for the kind of
+  /// directory.
+  fn xdg_var(self) -> &'static str {
+    match self {
+      Self::Config => "XDG_CONFIG_HOME",
+      Self::Cache => "XDG_CACHE_HOME",
+      Self::Data => "XDG_DATA_HOME",
+      Self::State => "XDG_STATE_HOME",
+      Self::Runtime => "XDG_RUNTIME_DIR",
+    }
+  }
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_app_dir() {
+    std::env::set_var("XDG_DATA_HOME", "/xdg/data");
+    std::env::set_var("XDG_STATE_HOME", "relative");
+    std::env::set_var("INDIGO_TEST_APP_CACHE_DIR", "/override");
+
+    assert_eq!(data_dir("indigo-test-app").unwrap(), "/xdg/data/indigo-test-app");
+    assert_eq!(cache_dir("indigo-test-app").unwrap(), "/override");
+
+    #[cfg(target_os = "linux")]
+    assert_eq!(
+      state_dir("indigo-test-app").unwrap(),
+      path::join(var("HOME").unwrap(), ".local/state/indigo-test-app")
+    );
+  }
+}
@@ -0,0 +1,103 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::prelude::*;
+use crate::sync::blocking::Mutex;
+use std::collections::BTreeMap;
+
+/// Information about an environment variable read by the current process.
+#[derive(Clone, Debug)]
+pub struct VarInfo {
+  /// The name of the variable.
+  pub name: String,
+  /// The name of the type the variable was parsed as.
+  pub type_name: String,
+  /// Whether the variable had a default value when it was read.
+  pub has_default: bool,
+  /// Whether the variable was set when it was read.
+  pub is_set: bool,
+}
+
+/// Variables read since the registry was enabled, or `None` if it is
+/// disabled.
+static REGISTRY: Lazy<Mutex<Option<BTreeMap<String, VarInfo>>>> = Lazy::new(default);
+
+/// Begins recording every environment variable read with the functions in
+/// this module.
+pub fn enable_registry() {
+  REGISTRY.lock().get_or_insert_with(default);
+}
+
+/// Returns information about every environment variable read since
+/// [`enable_registry()`] was called, sorted by name.
+pub fn registered_vars() -> Vec<VarInfo> {
+  REGISTRY.lock().iter().flat_map(|vars| vars.values()).cloned().collect()
+}
+
+/// Returns a help message listing every environment variable read since
+/// [`enable_registry()`] was called, such as for a `--help-env` option.
+pub fn help_env() -> String {
+  let vars = registered_vars();
+  let width = vars.iter().map(|var| var.name.len()).max().unwrap_or_default();
+  let mut help = String::from("Environment variables:\n");
+
+  for var in &vars {
+    write!(help, "\n  {:width$}  {}", var.name, var.type_name, width = width).unwrap();
+
+    if var.has_default {
+      help.push_str(" (optional)");
+    }
+
+    if var.is_set {
+      help.push_str(" (set)");
+    }
+  }
+
+  help.push('\n');
+  help
+}
+
+/// Records that an environment variable was read if the registry is enabled.
+pub(super) fn register<T: ?Sized>(name: &str, has_default: bool, is_set: bool) {
+  let mut registry = REGISTRY.lock();
+
+  let vars = match &mut *registry {
+    Some(vars) => vars,
+    None => return,
+  };
+
+  let info = vars.entry(name.into()).or_insert_with(|| VarInfo {
+    name: name.into(),
+    type_name: short_type_name(std::any::type_name::<T>()),
+    has_default,
+    is_set,
+  });
+
+  info.has_default |= has_default;
+}
+
+/// Removes module paths from a type name, such as `alloc::vec::Vec<u8>`.
+fn short_type_name(name: &str) -> String {
+  let mut short = String::with_capacity(name.len());
+  let mut segment_start = 0;
+
+  for (i, c) in name.char_indices() {
+    match c {
+      ':' => segment_start = i + 1,
+
+      '<' | '>' | ',' | ' ' | '[' | ']' | ';' | '(' | ')' | '&' => {
+        short.push_str(&name[segment_start..i]);
+        short.push(c);
+        segment_start = i + 1;
+      }
+
+      _ => {}
+    }
+  }
+
+  short.push_str(&name[segment_start..]);
+  short
+}
@@ -0,0 +1,175 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::prelude::*;
+
+/// A boolean parsed from `1`, `true`, `yes`, or `on`, or from `0`, `false`,
+/// `no`, or `off`, ignoring case.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Bool(pub bool);
+
+/// A size in bytes parsed from a number with an optional unit such as `64MiB`
+/// or `1.5 GB`.
+///
+/// Units are case-insensitive. Decimal units such as `KB` are multiples of
+/// 1000, and binary units such as `KiB` or `K` are multiples of 1024.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ByteSize(pub u64);
+
+/// A list of values parsed from comma-separated text.
+///
+/// Whitespace around each item is ignored, as are empty items.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct List<T>(pub Vec<T>);
+
+/// Units of [`ByteSize`] and their multipliers.
+const BYTE_UNITS: &[(&str, u64)] = &[
+  ("", 1),
+  ("b", 1),
+  ("k", 1 << 10),
+  ("kb", 1_000),
+  ("kib", 1 << 10),
+  ("m", 1 << 20),
+  ("mb", 1_000_000),
+  ("mib", 1 << 20),
+  ("g", 1 << 30),
+  ("gb", 1_000_000_000),
+  ("gib", 1 << 30),
+  ("t", 1 << 40),
+  ("tb", 1_000_000_000_000),
+  ("tib", 1 << 40),
+];
+
+// Implement parsing.
+
+impl FromStr for Bool {
+  type Err = fail::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    match s.trim().to_lowercase().as_str() {
+      "1" | "true" | "yes" | "on" => Ok(Self(true)),
+      "0" | "false" | "no" | "off" => Ok(Self(false)),
+      _ => fail!("Expected a boolean such as `true` or `false`."),
+    }
+  }
+}
+
+impl FromStr for ByteSize {
+  type Err = fail::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let unit = unit.trim_start().to_lowercase();
+
+    let multiplier = match BYTE_UNITS.iter().find(|(name, _)| *name == unit) {
+      Some((_, multiplier)) => *multiplier,
+      None => fail!("Unknown unit `{}`. Expected a size such as `64MiB`.", unit),
+    };
+
+    if let Ok(number) = number.parse::<u64>() {
+      return number
+        .checked_mul(multiplier)
+        .map(Self)
+        .ok_or_else(|| fail::err!("Size is too large."));
+    }
+
+    match number.parse::<f64>() {
+      Ok(number) if (number * multiplier as f64) < u64::MAX as f64 => {
+        Ok(Self((number * multiplier as f64).round() as u64))
+      }
+
+      Ok(_) => fail!("Size is too large."),
+      Err(_) => fail!("Expected a size such as `64MiB`."),
+    }
+  }
+}
+
+impl<T> FromStr for List<T>
+where
+  T: FromStr,
+  T::Err: Display,
+{
+  type Err = fail::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    s.split(',')
+      .map(str::trim)
+      .filter(|item| !item.is_empty())
+      .map(|item| {
+        item.parse().map_err(|err| fail::Error::join(format_args!("Invalid item `{}`", item), err))
+      })
+      .collect::<Result<_>>()
+      .map(Self)
+  }
+}
+
+// Implement conversion to inner values.
+
+impl Deref for Bool {
+  type Target = bool;
+
+  fn deref(&self) -> &bool {
+    &self.0
+  }
+}
+
+impl From<Bool> for bool {
+  fn from(value: Bool) -> Self {
+    value.0
+  }
+}
+
+impl Deref for ByteSize {
+  type Target = u64;
+
+  fn deref(&self) -> &u64 {
+    &self.0
+  }
+}
+
+impl From<ByteSize> for u64 {
+  fn from(value: ByteSize) -> Self {
+    value.0
+  }
+}
+
+impl<T> Deref for List<T> {
+  type Target = Vec<T>;
+
+  fn deref(&self) -> &Vec<T> {
+    &self.0
+  }
+}
+
+impl<T> From<List<T>> for Vec<T> {
+  fn from(value: List<T>) -> Self {
+    value.0
+  }
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse() {
+    assert_eq!("Yes".parse::<Bool>().unwrap(), Bool(true));
+    assert_eq!("0".parse::<Bool>().unwrap(), Bool(false));
+    assert!("maybe".parse::<Bool>().is_err());
+
+    assert_eq!("512".parse::<ByteSize>().unwrap(), ByteSize(512));
+    assert_eq!("64MiB".parse::<ByteSize>().unwrap(), ByteSize(64 << 20));
+    assert_eq!("1.5 kb".parse::<ByteSize>().unwrap(), ByteSize(1500));
+    assert!("10 parsecs".parse::<ByteSize>().is_err());
+
+    assert_eq!("1, 2,,3".parse::<List<u8>>().unwrap(), List(vec![1, 2, 3]));
+    assert!("1, x".parse::<List<u8>>().is_err());
+  }
+}
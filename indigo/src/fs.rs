@@ -6,17 +6,76 @@
 
 //! File system utilities.
 //!
-//! This module does not support non-Unicode paths. Paths with non-Unicode
-//! characters are either ignored or cause an error, depending on the context.
+//! Most functions in this module take and return string paths. Paths with
+//! non-Unicode characters are either ignored or cause an error, depending on
+//! the context. Use [`NativePath`] and the functions that return it, such as
+//! [`glob_native()`], when such paths must be kept.
 
+mod glob_set;
+mod lock;
+mod native_path;
+mod ops;
 pub mod path;
 mod utils;
+mod walk;
 
+pub use self::glob_set::GlobSet;
+pub use self::lock::Lock;
+pub use self::native_path::NativePath;
+pub use self::ops::*;
 pub use self::path::PathLike;
 pub use self::utils::*;
+pub use self::walk::{walk, Walk};
 
 #[cfg(feature = "fs-watch")]
-mod watch;
+pub mod watch;
 
 #[cfg(feature = "fs-watch")]
 pub use self::watch::Watcher;
+
+use crate::prelude::*;
+use crate::sync::Semaphore;
+
+/// Options for matching glob patterns.
+const MATCH_OPTIONS: glob::MatchOptions = glob::MatchOptions {
+  // Case-insensitive on Windows.
+  case_sensitive: !cfg!(target_os = "windows"),
+  // Don't match `/` with wildcards `*` and `?`.
+  require_literal_separator: true,
+  // Require explicit `.` to match dotfiles.
+  require_literal_leading_dot: true,
+};
+
+/// A shared semaphore to limit the number of concurrent file system operations.
+static SEMAPHORE: Lazy<Semaphore> = Lazy::new(|| Semaphore::new(8));
+
+/// A temporary directory for tests, removed when dropped.
+#[cfg(test)]
+pub(crate) struct TempDir {
+  path: String,
+}
+
+#[cfg(test)]
+impl TempDir {
+  /// Creates a new, empty temporary directory.
+  pub fn new() -> Self {
+    let temp = std::env::temp_dir().into_os_string().into_string().unwrap();
+    let path = path::join(&temp, format!("indigo-test-{:016x}", random::<u64>())).into_owned();
+
+    std::fs::create_dir_all(&path).unwrap();
+
+    Self { path }
+  }
+
+  /// Returns the path of a file or directory in the temporary directory.
+  pub fn join(&self, relative: &str) -> String {
+    path::join(&self.path, relative).into_owned()
+  }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+  fn drop(&mut self) {
+    let _ = std::fs::remove_dir_all(&self.path);
+  }
+}
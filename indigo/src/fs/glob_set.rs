@@ -0,0 +1,226 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::MATCH_OPTIONS;
+use crate::prelude::*;
+
+/// A set of compiled glob patterns that include or exclude paths.
+///
+/// Patterns support `{a,b}` braces for alternatives in addition to the usual
+/// glob syntax. Patterns ending in `/**` also match the directory itself.
+#[derive(Clone, Debug, Default)]
+pub struct GlobSet {
+  include: Vec<glob::Pattern>,
+  exclude: Vec<glob::Pattern>,
+}
+
+impl GlobSet {
+  /// Creates a new, empty glob set that matches all paths.
+  pub fn new() -> Self {
+    default()
+  }
+
+  /// Creates a new glob set from a list of patterns.
+  ///
+  /// Patterns starting with `!` exclude paths. All other patterns include
+  /// paths.
+  pub fn from_patterns<'a>(patterns: impl IntoIterator<Item = &'a str>) -> Result<Self> {
+    let mut set = Self::new();
+
+    for pattern in patterns {
+      set.add(pattern)?;
+    }
+
+    Ok(set)
+  }
+
+  /// Adds a pattern to the set.
+  ///
+  /// If the pattern starts with `!`, it excludes paths. Otherwise, it includes
+  /// paths.
+  pub fn add(&mut self, pattern: &str) -> Result {
+    match pattern.strip_prefix('!') {
+      Some(pattern) => self.exclude(pattern),
+      None => self.include(pattern),
+    }
+  }
+
+  /// Adds a pattern that excludes paths from the set.
+  pub fn exclude(&mut self, pattern: &str) -> Result {
+    compile(pattern, &mut self.exclude)
+  }
+
+  /// Adds a pattern that includes paths in the set.
+  ///
+  /// If a set has no include patterns, it includes all paths.
+  pub fn include(&mut self, pattern: &str) -> Result {
+    compile(pattern, &mut self.include)
+  }
+
+  /// Adds all patterns from another set to this set.
+  pub fn extend(&mut self, other: &GlobSet) {
+    self.include.extend_from_slice(&other.include);
+    self.exclude.extend_from_slice(&other.exclude);
+  }
+
+  /// Returns `true` if the set has no patterns.
+  pub fn is_empty(&self) -> bool {
+    self.include.is_empty() && self.exclude.is_empty()
+  }
+
+  /// Returns `true` if the given path matches an exclude pattern.
+  ///
+  /// This is useful for skipping the contents of excluded directories.
+  pub fn is_excluded(&self, path: &str) -> bool {
+    self.exclude.iter().any(|p| p.matches_with(path, MATCH_OPTIONS))
+  }
+
+  /// Returns `true` if the given path matches an include pattern or the set
+  /// has no include patterns, and it does not match an exclude pattern.
+  pub fn is_match(&self, path: &str) -> bool {
+    let included =
+      self.include.is_empty() || self.include.iter().any(|p| p.matches_with(path, MATCH_OPTIONS));
+
+    included && !self.is_excluded(path)
+  }
+
+  /// Returns an iterator over the include patterns with braces expanded.
+  pub(super) fn include_patterns(&self) -> impl Iterator<Item = &glob::Pattern> {
+    self.include.iter()
+  }
+}
+
+/// Expands braces in a pattern and compiles each expanded pattern into the
+/// output list.
+fn compile(pattern: &str, output: &mut Vec<glob::Pattern>) -> Result {
+  let invalid = |err: &dyn Display| fail::err!("Invalid glob pattern `{}`. {}.", pattern, err);
+  let mut expanded = Vec::new();
+
+  expand_braces(pattern, &mut expanded).map_err(|err| invalid(&err))?;
+
+  // Make `a/**` also match `a` itself.
+
+  for i in 0..expanded.len() {
+    if let Some(dir) = expanded[i].strip_suffix("/**") {
+      expanded.push(dir.into());
+    }
+  }
+
+  for pattern in expanded {
+    output.push(glob::Pattern::new(&pattern).map_err(|err| invalid(&err.msg))?);
+  }
+
+  Ok(())
+}
+
+/// Expands all `{a,b}` braces in a pattern into the output list.
+///
+/// Braces inside `[…]` character classes are not expanded.
+fn expand_braces(pattern: &str, output: &mut Vec<String>) -> Result<(), &'static str> {
+  let open = match find_top_level(pattern, 0, |c, _| c == '{') {
+    Some(open) => open,
+
+    None => {
+      if find_top_level(pattern, 0, |c, _| c == '}').is_some() {
+        return Err("Unmatched `}`");
+      }
+
+      output.push(pattern.into());
+
+      return Ok(());
+    }
+  };
+
+  let close =
+    find_top_level(pattern, open + 1, |c, depth| c == '}' && depth == 0).ok_or("Unmatched `{`")?;
+
+  let prefix = &pattern[..open];
+  let suffix = &pattern[close + 1..];
+  let mut start = open + 1;
+
+  loop {
+    let end =
+      find_top_level(&pattern[..close], start, |c, depth| c == ',' && depth == 0).unwrap_or(close);
+
+    expand_braces(&format!("{}{}{}", prefix, &pattern[start..end], suffix), output)?;
+
+    if end == close {
+      return Ok(());
+    }
+
+    start = end + 1;
+  }
+}
+
+/// Finds the index of the first character at or after `start` that is not in
+/// a `[…]` character class and satisfies a predicate.
+///
+/// The predicate is given each character and its depth of nested braces
+/// relative to `start`.
+fn find_top_level(
+  pattern: &str,
+  start: usize,
+  mut predicate: impl FnMut(char, usize) -> bool,
+) -> Option<usize> {
+  let mut depth = 0;
+  let mut in_class = false;
+
+  for (i, c) in pattern[start..].char_indices() {
+    if in_class {
+      in_class = c != ']';
+      continue;
+    }
+
+    if predicate(c, depth) {
+      return Some(start + i);
+    }
+
+    match c {
+      '[' => in_class = true,
+      '{' => depth += 1,
+      '}' => depth = depth.saturating_sub(1),
+      _ => {}
+    }
+  }
+
+  None
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn expand(pattern: &str) -> Vec<String> {
+    let mut output = Vec::new();
+
+    expand_braces(pattern, &mut output).unwrap();
+    output
+  }
+
+  #[test]
+  fn test_expand_braces() {
+    assert_eq!(expand("*.rs"), ["*.rs"]);
+    assert_eq!(expand("*.{rs,toml}"), ["*.rs", "*.toml"]);
+    assert_eq!(expand("{a,b{c,d}}/e"), ["a/e", "bc/e", "bd/e"]);
+    assert_eq!(expand("{a,}{x,y}"), ["ax", "ay", "x", "y"]);
+    assert_eq!(expand("[{]{a,b}"), ["[{]a", "[{]b"]);
+  }
+
+  #[test]
+  fn test_is_match() {
+    let set = GlobSet::from_patterns(vec!["src/**/*.{rs,toml}", "!**/generated/**"]).unwrap();
+
+    assert!(set.is_match("src/lib.rs"));
+    assert!(set.is_match("src/fs/glob.toml"));
+    assert!(!set.is_match("src/fs/glob.txt"));
+    assert!(!set.is_match("src/generated/lib.rs"));
+    assert!(set.is_excluded("src/generated"));
+    assert!(GlobSet::new().is_match("anything"));
+    assert!(GlobSet::from_patterns(vec!["{a,b"]).is_err());
+  }
+}
@@ -0,0 +1,200 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::PathLike;
+use crate::prelude::*;
+use std::fs::File;
+use std::io;
+
+#[cfg(feature = "runtime")]
+use std::time::Instant;
+
+/// An advisory lock on a lock file.
+///
+/// The lock is released when this value is dropped. Advisory locks only
+/// exclude other processes that also lock the same file.
+#[derive(Debug)]
+pub struct Lock {
+  file: File,
+  path: String,
+  shared: bool,
+}
+
+impl Lock {
+  /// Acquires an exclusive lock on the given lock file, waiting until no
+  /// other process holds a lock on it.
+  ///
+  /// The lock file is created if it does not exist.
+  pub async fn exclusive<'a>(path: impl PathLike<'a>) -> Result<Self> {
+    Self::acquire(path.try_to_owned()?, false).await
+  }
+
+  /// Acquires an exclusive lock on the given lock file, failing if it cannot
+  /// be acquired within the given timeout.
+  ///
+  /// The lock file is created if it does not exist.
+  #[cfg(feature = "runtime")]
+  pub async fn exclusive_timeout<'a>(path: impl PathLike<'a>, timeout: Duration) -> Result<Self> {
+    Self::acquire_timeout(path.try_to_owned()?, false, timeout).await
+  }
+
+  /// Acquires a shared lock on the given lock file, waiting until no other
+  /// process holds an exclusive lock on it.
+  ///
+  /// The lock file is created if it does not exist.
+  pub async fn shared<'a>(path: impl PathLike<'a>) -> Result<Self> {
+    Self::acquire(path.try_to_owned()?, true).await
+  }
+
+  /// Acquires a shared lock on the given lock file, failing if it cannot be
+  /// acquired within the given timeout.
+  ///
+  /// The lock file is created if it does not exist.
+  #[cfg(feature = "runtime")]
+  pub async fn shared_timeout<'a>(path: impl PathLike<'a>, timeout: Duration) -> Result<Self> {
+    Self::acquire_timeout(path.try_to_owned()?, true, timeout).await
+  }
+
+  /// Returns `true` if this is a shared lock.
+  pub fn is_shared(&self) -> bool {
+    self.shared
+  }
+
+  /// Returns the path of the lock file.
+  pub fn path(&self) -> &str {
+    &self.path
+  }
+
+  /// Releases the lock.
+  pub fn release(self) {}
+
+  /// Opens a lock file and then waits until a lock on it is acquired.
+  async fn acquire(path: String, shared: bool) -> Result<Self> {
+    future::unblock! {
+      let lock = Self::open(path, shared)?;
+
+      match shared {
+        true => fs2::FileExt::lock_shared(&lock.file),
+        false => fs2::FileExt::lock_exclusive(&lock.file),
+      }
+      .map_err(|err| failed(&lock.path, err))?;
+
+      Ok(lock)
+    }
+  }
+
+  /// Opens a lock file and then polls for a lock on it with exponential
+  /// backoff until the timeout elapses.
+  #[cfg(feature = "runtime")]
+  async fn acquire_timeout(path: String, shared: bool, timeout: Duration) -> Result<Self> {
+    let timeout = timeout.to_std();
+    let started_at = Instant::now();
+    let mut delay = std::time::Duration::from_millis(1);
+    let mut lock = future::unblock! { Self::open(path, shared) }?;
+
+    loop {
+      let (returned, result) = future::unblock! {
+        let result = match shared {
+          true => fs2::FileExt::try_lock_shared(&lock.file),
+          false => fs2::FileExt::try_lock_exclusive(&lock.file),
+        };
+
+        (lock, result)
+      };
+
+      lock = returned;
+
+      match result {
+        Ok(()) => return Ok(lock),
+        Err(err) if !is_contended(&err) => return Err(failed(&lock.path, err)),
+        Err(_) => {}
+      }
+
+      let elapsed = started_at.elapsed();
+
+      if elapsed >= timeout {
+        fail!(
+          "Failed to lock {}. Timed out waiting for another process.",
+          fmt::AsPath(&lock.path).describe()
+        );
+      }
+
+      future::sleep(cmp::min(delay, timeout - elapsed).into()).await;
+
+      delay = cmp::min(delay * 2, std::time::Duration::from_millis(100));
+    }
+  }
+
+  /// Opens or creates a lock file without locking it.
+  fn open(path: String, shared: bool) -> Result<Self> {
+    let file = std::fs::OpenOptions::new()
+      .read(true)
+      .write(true)
+      .create(true)
+      .truncate(false)
+      .open(&path)
+      .map_err(|err| failed(&path, err))?;
+
+    Ok(Self { file, path, shared })
+  }
+}
+
+/// Describes an IO error that occurred while locking a lock file.
+fn failed(path: &str, err: io::Error) -> fail::Error {
+  fail::Error::join(format_args!("Failed to lock {}", fmt::AsPath(path).describe()), err)
+}
+
+/// Returns `true` if the given error indicates the lock is held by another
+/// process.
+#[cfg(feature = "runtime")]
+fn is_contended(err: &io::Error) -> bool {
+  err.raw_os_error() == fs2::lock_contended_error().raw_os_error()
+}
+
+// Implement `Drop` to release the lock.
+
+impl Drop for Lock {
+  fn drop(&mut self) {
+    let _ = fs2::FileExt::unlock(&self.file);
+  }
+}
+
+// Unit tests.
+
+#[cfg(all(test, feature = "runtime"))]
+mod tests {
+  use super::*;
+  use crate::fs::TempDir;
+  use crate::thread;
+
+  #[test]
+  fn test_contention() {
+    let temp = TempDir::new();
+    let path = temp.join("test.lock");
+    let timeout = Duration::secs_f64(0.05);
+
+    thread::block_on(async {
+      let exclusive = Lock::exclusive(&path).await.unwrap();
+
+      assert_eq!(
+        Lock::shared_timeout(&path, timeout).await.unwrap_err().to_string(),
+        format!("Failed to lock `{}`. Timed out waiting for another process.", path)
+      );
+
+      exclusive.release();
+
+      let a = Lock::shared_timeout(&path, timeout).await.unwrap();
+      let b = Lock::shared(&path).await.unwrap();
+
+      assert!(a.is_shared() && b.is_shared());
+      assert!(Lock::exclusive_timeout(&path, timeout).await.is_err());
+
+      drop((a, b));
+
+      assert!(!Lock::exclusive_timeout(&path, timeout).await.unwrap().is_shared());
+    });
+  }
+}
@@ -0,0 +1,169 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::PathLike;
+use crate::prelude::*;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+/// A path in the native format of the platform, which may contain
+/// non-Unicode characters.
+///
+/// Most functions in this module take string paths. Use this type when paths
+/// must be kept exactly, such as when they come from the file system, and
+/// convert them with [`as_str()`](Self::as_str) or
+/// [`into_string()`](Self::into_string) when needed.
+#[derive(Clone, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NativePath(PathBuf);
+
+impl NativePath {
+  /// Creates a new native path.
+  pub fn new(path: impl Into<PathBuf>) -> Self {
+    Self(path.into())
+  }
+
+  /// Returns a reference to the path as a `&Path`.
+  pub fn as_path(&self) -> &Path {
+    &self.0
+  }
+
+  /// Returns the path as a `&str`, or `None` if it contains non-Unicode
+  /// characters.
+  pub fn as_str(&self) -> Option<&str> {
+    self.0.to_str()
+  }
+
+  /// Returns `true` if the path can be converted to a string.
+  pub fn is_unicode(&self) -> bool {
+    self.as_str().is_some()
+  }
+
+  /// Converts the path into a `PathBuf`.
+  pub fn into_path_buf(self) -> PathBuf {
+    self.0
+  }
+
+  /// Converts the path into a `String`, or returns the original path if it
+  /// contains non-Unicode characters.
+  pub fn into_string(self) -> Result<String, Self> {
+    self.0.into_os_string().into_string().map_err(|path| Self(path.into()))
+  }
+
+  /// Returns the path as a string, replacing non-Unicode characters with
+  /// `U+FFFD`.
+  pub fn to_string_lossy(&self) -> Cow<'_, str> {
+    self.0.to_string_lossy()
+  }
+}
+
+// Implement conversions.
+
+impl AsRef<OsStr> for NativePath {
+  fn as_ref(&self) -> &OsStr {
+    self.0.as_os_str()
+  }
+}
+
+impl AsRef<Path> for NativePath {
+  fn as_ref(&self) -> &Path {
+    &self.0
+  }
+}
+
+impl Deref for NativePath {
+  type Target = Path;
+
+  fn deref(&self) -> &Path {
+    &self.0
+  }
+}
+
+impl From<&str> for NativePath {
+  fn from(path: &str) -> Self {
+    Self(path.into())
+  }
+}
+
+impl From<String> for NativePath {
+  fn from(path: String) -> Self {
+    Self(path.into())
+  }
+}
+
+impl From<OsString> for NativePath {
+  fn from(path: OsString) -> Self {
+    Self(path.into())
+  }
+}
+
+impl From<PathBuf> for NativePath {
+  fn from(path: PathBuf) -> Self {
+    Self(path)
+  }
+}
+
+impl From<NativePath> for PathBuf {
+  fn from(path: NativePath) -> Self {
+    path.0
+  }
+}
+
+impl TryFrom<NativePath> for String {
+  type Error = fail::Error;
+
+  fn try_from(path: NativePath) -> Result<Self> {
+    path.into_string().map_err(|path| {
+      fail::err!("Path `{}` contains non-Unicode characters.", path.to_string_lossy())
+    })
+  }
+}
+
+impl<'a> PathLike<'a> for &'a NativePath {
+  fn to_cow(self) -> Cow<'a, str> {
+    self.as_path().to_cow()
+  }
+
+  fn try_to_cow(self) -> Result<Cow<'a, str>> {
+    self.as_path().try_to_cow()
+  }
+}
+
+// Implement formatting.
+
+impl Debug for NativePath {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    Debug::fmt(&self.0, f)
+  }
+}
+
+impl Display for NativePath {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    Display::fmt(&self.0.display(), f)
+  }
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_into_string() {
+    assert_eq!(NativePath::from("/a/b").into_string().unwrap(), "/a/b");
+
+    #[cfg(unix)]
+    {
+      use std::os::unix::ffi::OsStrExt as _;
+
+      let path = NativePath::new(OsStr::from_bytes(b"/a/\xff"));
+
+      assert!(!path.is_unicode());
+      assert_eq!(path.to_string_lossy(), "/a/\u{fffd}");
+      assert!(String::try_from(path).is_err());
+    }
+  }
+}
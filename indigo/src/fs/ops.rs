@@ -0,0 +1,370 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+pub use std::fs::{FileType, Metadata, Permissions};
+
+use super::{path, PathLike, SEMAPHORE};
+use crate::prelude::*;
+use blocking::Unblock;
+use std::io::{self, Write};
+
+/// An entry in a directory returned by [`read_dir()`] or [`walk()`](super::walk()).
+#[derive(Debug)]
+pub struct DirEntry {
+  pub(super) path: String,
+  pub(super) file_type: FileType,
+  pub(super) depth: usize,
+}
+
+/// A stream of the entries in a directory returned by [`read_dir()`].
+pub struct ReadDir {
+  entries: Unblock<Entries>,
+}
+
+/// An iterator over the entries in a directory that converts them to
+/// [`DirEntry`] values.
+struct Entries {
+  inner: std::fs::ReadDir,
+  path: String,
+}
+
+/// Copies the contents and permissions of a file to another file, overwriting
+/// it if it exists.
+///
+/// Returns the number of bytes copied.
+pub async fn copy<'a, 'b>(from: impl PathLike<'a>, to: impl PathLike<'b>) -> Result<u64> {
+  let from = from.try_to_owned()?;
+  let to = to.try_to_owned()?;
+  let _permit = SEMAPHORE.acquire().await;
+
+  future::unblock! {
+    std::fs::copy(&from, &to).map_err(failed("copy", &from))
+  }
+}
+
+/// Creates a directory and all of its missing parent directories.
+pub async fn create_dir_all<'a>(path: impl PathLike<'a>) -> Result {
+  let path = path.try_to_owned()?;
+  let _permit = SEMAPHORE.acquire().await;
+
+  future::unblock! {
+    std::fs::create_dir_all(&path).map_err(failed("create directory", &path))
+  }
+}
+
+/// Returns metadata about a file or directory, following symbolic links.
+pub async fn metadata<'a>(path: impl PathLike<'a>) -> Result<Metadata> {
+  let path = path.try_to_owned()?;
+  let _permit = SEMAPHORE.acquire().await;
+
+  future::unblock! {
+    std::fs::metadata(&path).map_err(failed("read metadata of", &path))
+  }
+}
+
+/// Reads the entire contents of a file.
+pub async fn read<'a>(path: impl PathLike<'a>) -> Result<Vec<u8>> {
+  let path = path.try_to_owned()?;
+  let _permit = SEMAPHORE.acquire().await;
+
+  future::unblock! {
+    std::fs::read(&path).map_err(failed("read", &path))
+  }
+}
+
+/// Returns a stream of the entries in a directory.
+///
+/// Entries with non-Unicode names are ignored. The concurrency limit for file
+/// system operations only applies to opening the directory, not to reading
+/// its entries.
+pub async fn read_dir<'a>(path: impl PathLike<'a>) -> Result<ReadDir> {
+  let path = path.try_to_owned()?;
+  let _permit = SEMAPHORE.acquire().await;
+
+  future::unblock! {
+    let inner = std::fs::read_dir(&path).map_err(failed("read directory", &path))?;
+
+    Ok(ReadDir { entries: Unblock::new(Entries { inner, path }) })
+  }
+}
+
+/// Reads the entire contents of a file into a string.
+pub async fn read_to_string<'a>(path: impl PathLike<'a>) -> Result<String> {
+  let path = path.try_to_owned()?;
+  let _permit = SEMAPHORE.acquire().await;
+
+  future::unblock! {
+    std::fs::read_to_string(&path).map_err(failed("read", &path))
+  }
+}
+
+/// Removes a directory after removing all of its contents.
+pub async fn remove_dir_all<'a>(path: impl PathLike<'a>) -> Result {
+  let path = path.try_to_owned()?;
+  let _permit = SEMAPHORE.acquire().await;
+
+  future::unblock! {
+    std::fs::remove_dir_all(&path).map_err(failed("remove directory", &path))
+  }
+}
+
+/// Renames a file or directory, replacing the destination if it exists.
+pub async fn rename<'a, 'b>(from: impl PathLike<'a>, to: impl PathLike<'b>) -> Result {
+  let from = from.try_to_owned()?;
+  let to = to.try_to_owned()?;
+  let _permit = SEMAPHORE.acquire().await;
+
+  future::unblock! {
+    std::fs::rename(&from, &to).map_err(failed("rename", &from))
+  }
+}
+
+/// Writes the given contents to a file, replacing it if it exists.
+pub async fn write<'a>(path: impl PathLike<'a>, contents: impl Into<Vec<u8>>) -> Result {
+  let path = path.try_to_owned()?;
+  let contents = contents.into();
+  let _permit = SEMAPHORE.acquire().await;
+
+  future::unblock! {
+    std::fs::write(&path, contents).map_err(failed("write", &path))
+  }
+}
+
+/// Writes the given contents to a file atomically, replacing it if it exists.
+///
+/// The contents are written to a temporary file in the same directory, flushed
+/// to disk, and then renamed over the original file, so the file is never left
+/// partially written.
+pub async fn write_atomic<'a>(path: impl PathLike<'a>, contents: impl Into<Vec<u8>>) -> Result {
+  let path = path.try_to_owned()?;
+  let contents = contents.into();
+
+  let name = match path::last(&path) {
+    Some(name) => name,
+    None => {
+      fail!("Failed to write {}. The path is not a file path.", fmt::AsPath(&path).describe())
+    }
+  };
+
+  let dir = String::from(path::parent(&path).unwrap_or_default());
+  let temp_path = path::join(&dir, format!(".{}.{:016x}.tmp", name, random::<u64>())).into_owned();
+  let _permit = SEMAPHORE.acquire().await;
+
+  future::unblock! {
+    let result = write_and_sync(&temp_path, &contents)
+      .and_then(|_| std::fs::rename(&temp_path, &path))
+      .map_err(failed("write", &path));
+
+    if result.is_err() {
+      let _ = std::fs::remove_file(&temp_path);
+
+      return result;
+    }
+
+    // Flush the rename to disk. Not all platforms support this, so failures
+    // are ignored.
+
+    if let Ok(dir) = std::fs::File::open(if dir.is_empty() { "." } else { &dir }) {
+      let _ = dir.sync_all();
+    }
+
+    Ok(())
+  }
+}
+
+/// Writes the given contents to a new file and flushes it to disk.
+fn write_and_sync(path: &str, contents: &[u8]) -> io::Result<()> {
+  let mut file = std::fs::OpenOptions::new().write(true).create_new(true).open(path)?;
+
+  file.write_all(contents)?;
+  file.sync_all()
+}
+
+/// Returns a closure for using [`Result::map_err`] to describe an IO error
+/// that occurred during an operation on a path.
+pub(super) fn failed<'a>(
+  operation: &'a str,
+  path: &'a str,
+) -> impl FnOnce(io::Error) -> fail::Error + 'a {
+  move |err| {
+    fail::Error::join(format_args!("Failed to {} {}", operation, fmt::AsPath(path).describe()), err)
+  }
+}
+
+impl DirEntry {
+  /// Returns the depth of the entry relative to the directory being read or
+  /// walked.
+  ///
+  /// Entries directly inside the directory have a depth of 1.
+  pub fn depth(&self) -> usize {
+    self.depth
+  }
+
+  /// Returns the file type of the entry without following symbolic links.
+  pub fn file_type(&self) -> FileType {
+    self.file_type
+  }
+
+  /// Returns metadata about the entry, following symbolic links.
+  pub async fn metadata(&self) -> Result<Metadata> {
+    metadata(&self.path).await
+  }
+
+  /// Returns the file name of the entry.
+  pub fn name(&self) -> &str {
+    path::last(&self.path).unwrap_or_default()
+  }
+
+  /// Returns the full path of the entry.
+  pub fn path(&self) -> &str {
+    &self.path
+  }
+
+  /// Converts the entry into its full path.
+  pub fn into_path(self) -> String {
+    self.path
+  }
+}
+
+// Implement `Iterator` for the blocking entries iterator.
+
+impl Iterator for Entries {
+  type Item = Result<DirEntry>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      let entry = match self.inner.next()? {
+        Ok(entry) => entry,
+        Err(err) => return Some(Err(failed("read directory", &self.path)(err))),
+      };
+
+      // Ignore entries with non-Unicode names.
+
+      let path = match entry.path().into_os_string().into_string() {
+        Ok(path) => path,
+        Err(_) => continue,
+      };
+
+      return Some(match entry.file_type() {
+        Ok(file_type) => Ok(DirEntry { path, file_type, depth: 1 }),
+        Err(err) => Err(failed("read file type of", &path)(err)),
+      });
+    }
+  }
+}
+
+// Implement `Stream` for the async entries stream.
+
+impl Stream for ReadDir {
+  type Item = Result<DirEntry>;
+
+  fn poll_next(
+    mut self: Pin<&mut Self>,
+    cx: &mut future::Context,
+  ) -> future::Poll<Option<Self::Item>> {
+    Pin::new(&mut self.entries).poll_next(cx)
+  }
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fs::TempDir;
+  use crate::thread;
+
+  #[test]
+  fn test_round_trip() {
+    let temp = TempDir::new();
+    let dir = temp.join("a/b");
+    let file = path::join(&dir, "file.txt").into_owned();
+
+    thread::block_on(async {
+      create_dir_all(&dir).await.unwrap();
+      write(&file, "hello").await.unwrap();
+
+      assert_eq!(read(&file).await.unwrap(), b"hello");
+      assert_eq!(read_to_string(&file).await.unwrap(), "hello");
+      assert_eq!(metadata(&file).await.unwrap().len(), 5);
+
+      copy(&file, temp.join("a/copy.txt")).await.unwrap();
+      rename(temp.join("a/copy.txt"), temp.join("a/renamed.txt")).await.unwrap();
+
+      let mut names: Vec<_> = read_dir(temp.join("a"))
+        .await
+        .unwrap()
+        .map(|e| e.unwrap().name().to_string())
+        .collect()
+        .await;
+
+      names.sort();
+
+      assert_eq!(names, ["b", "renamed.txt"]);
+
+      remove_dir_all(temp.join("a")).await.unwrap();
+
+      assert!(metadata(temp.join("a")).await.is_err());
+    });
+  }
+
+  #[test]
+  fn test_not_found() {
+    let temp = TempDir::new();
+    let missing = temp.join("missing.txt");
+
+    thread::block_on(async {
+      let messages = [
+        read(&missing).await.unwrap_err().to_string(),
+        read_to_string(&missing).await.unwrap_err().to_string(),
+        remove_dir_all(&missing).await.unwrap_err().to_string(),
+        rename(&missing, temp.join("other.txt")).await.unwrap_err().to_string(),
+      ];
+
+      for (message, operation) in
+        messages.iter().zip(&["read", "read", "remove directory", "rename"])
+      {
+        assert_eq!(
+          *message,
+          format!("Failed to {} `{}`. No such file or directory (os error 2).", operation, missing)
+        );
+      }
+    });
+  }
+
+  #[test]
+  fn test_write_atomic() {
+    let temp = TempDir::new();
+    let file = temp.join("file.txt");
+    let dir = temp.join("dir");
+
+    thread::block_on(async {
+      write(&file, "old").await.unwrap();
+      write_atomic(&file, "new").await.unwrap();
+
+      assert_eq!(read_to_string(&file).await.unwrap(), "new");
+
+      // Renaming over a directory fails, so the temporary file must be
+      // removed.
+
+      create_dir_all(&dir).await.unwrap();
+
+      assert!(write_atomic(&dir, "new").await.is_err());
+
+      let mut names: Vec<_> = read_dir(temp.join(""))
+        .await
+        .unwrap()
+        .map(|e| e.unwrap().name().to_string())
+        .collect()
+        .await;
+
+      names.sort();
+
+      assert_eq!(names, ["dir", "file.txt"]);
+      assert!(metadata(&dir).await.unwrap().is_dir());
+    });
+  }
+}
@@ -5,8 +5,48 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 //! Path string manipulation and inspection.
-
-pub use indigo_macros::{path_join as join, path_normalize as normalize, path_resolve as resolve};
+//!
+//! ## Macros
+//!
+//! The [`join!`], [`normalize!`], and [`resolve!`] macros join any number of
+//! [`PathLike`] values into a new `String` with a single allocation. Adjacent
+//! string literals are joined at compile time.
+//!
+//! ```
+//! use indigo::fs::path;
+//!
+//! let dir = String::from("/var");
+//! let name = "app";
+//!
+//! assert_eq!(path::join!(&dir, "lib", name, "data.db"), "/var/lib/app/data.db");
+//! assert_eq!(path::normalize!(&dir, "./lib/../log", name), "/var/log/app");
+//! assert_eq!(path::join!("a", "/b", "c"), "/b/c");
+//! ```
+//!
+//! Begin the list with `strict;` to require that every string literal is a
+//! single, normal path component.
+//!
+//! ```
+//! # use indigo::fs::path;
+//! # let dir = "/var";
+//! assert_eq!(path::join!(strict; dir, "lib", "app"), "/var/lib/app");
+//! ```
+//!
+//! ```compile_fail
+//! # use indigo::fs::path;
+//! # let dir = "/var";
+//! path::join!(strict; dir, "lib/app");
+//! ```
+//!
+//! ```compile_fail
+//! # use indigo::fs::path;
+//! # let dir = "/var";
+//! path::resolve!(strict; dir, "..", "etc");
+//! ```
+
+pub use indigo_proc_macros::{
+  path_join as join, path_normalize as normalize, path_resolve as resolve,
+};
 
 #[doc(inline)]
 pub use std::path::{is_separator, MAIN_SEPARATOR as SEPARATOR};
@@ -40,6 +80,45 @@ pub fn append(base: &mut String, relative: &str) {
   }
 }
 
+/// Returns the canonical, absolute form of the given path with all
+/// intermediate components normalized and symbolic links resolved.
+///
+/// Unlike [`resolved()`], this function accesses the file system, so the path
+/// must exist.
+pub async fn canonicalize<'a>(path: impl PathLike<'a>) -> Result<String> {
+  let path = path.try_to_owned()?;
+
+  future::unblock! {
+    let canonical = std::fs::canonicalize(&path)
+      .map_err(fail::with!("Failed to canonicalize {}", fmt::AsPath(&path).describe()))?;
+
+    canonical.into_os_string().into_string().map_err(|canonical| {
+      fail::err!(
+        "Failed to canonicalize {}. Canonical path `{}` contains non-Unicode characters.",
+        fmt::AsPath(&path).describe(),
+        canonical.to_string_lossy(),
+      )
+    })
+  }
+}
+
+/// Returns an iterator over the components of the path.
+///
+/// Like [`normalized()`], repeated separators and `.` components are skipped
+/// except for a leading `.`.
+pub fn components(path: &str) -> Components<'_> {
+  Components(as_std_path(path).components())
+}
+
+/// Returns the extension of the last component of the path without the
+/// leading `.`.
+///
+/// If the last component has no extension or the path is a root or empty
+/// path, this function returns `None`.
+pub fn extension(path: &str) -> Option<&str> {
+  as_std_path(path).extension()?.to_str()
+}
+
 /// Returns `true` if the given path is absolute.
 pub fn is_absolute(path: &str) -> bool {
   as_std_path(path).is_absolute()
@@ -62,6 +141,14 @@ pub fn join<'a, 'b>(base: impl PathLike<'b>, relative: impl PathLike<'a>) -> Cow
   output.into()
 }
 
+#[doc(hidden)]
+/// Joins a list of paths into a new string with a single allocation.
+///
+/// This function is used to support the [`join!`] macro.
+pub fn join_parts(parts: &[&str]) -> String {
+  join_parts_onto(String::new(), parts)
+}
+
 /// Returns the last component of the path.
 ///
 /// If `path` is a root or empty path, this function returns `None`.
@@ -82,6 +169,55 @@ pub fn normalize(path: &mut String) {
   })
 }
 
+/// Returns the path from `base` to `path`, adding `..` components as needed.
+///
+/// Both paths are normalized first, keeping leading `..` components. If one
+/// path is absolute and the other is not, or if `base` has `..` components
+/// that `path` does not, this function returns `None`.
+pub fn relative_to<'a>(path: &'a str, base: &str) -> Option<Cow<'a, str>> {
+  if is_absolute(path) != is_absolute(base) {
+    return None;
+  }
+
+  let path_components = lexical_components(path);
+  let base_components = lexical_components(base);
+
+  let common = path_components.iter().zip(&base_components).take_while(|(a, b)| a == b).count();
+
+  // The names of the directories above the base are unknown, so the path
+  // cannot be reached from it.
+
+  if base_components[common..].contains(&"..") {
+    return None;
+  }
+
+  // If the path is already normalized and starts with the base, return the
+  // rest of it.
+
+  if common == base_components.len() {
+    if let Cow::Borrowed(path) = normalized(path) {
+      if let Some(rest) = strip_prefix(path, &normalized(base)) {
+        return Some(rest.into());
+      }
+    }
+  }
+
+  // Add a `..` component for each remaining base component, then the
+  // remaining path components.
+
+  let mut relative = String::with_capacity(path.len());
+
+  for _ in &base_components[common..] {
+    append(&mut relative, "..");
+  }
+
+  for component in &path_components[common..] {
+    append(&mut relative, component);
+  }
+
+  Some(relative.into())
+}
+
 /// Returns a normalized version of the given path.
 pub fn normalized<'a>(path: impl PathLike<'a>) -> Cow<'a, str> {
   let path = path.to_cow();
@@ -99,6 +235,18 @@ pub fn normalized<'a>(path: impl PathLike<'a>) -> Cow<'a, str> {
   })
 }
 
+#[doc(hidden)]
+/// Joins a list of paths into a new string and normalizes it.
+///
+/// This function is used to support the [`normalize!`] macro.
+pub fn normalize_parts(parts: &[&str]) -> String {
+  let mut path = join_parts(parts);
+
+  normalize(&mut path);
+
+  path
+}
+
 /// Returns the parent of the given path.
 ///
 /// If `path` is a root or empty path, this function returns `None`.
@@ -149,11 +297,85 @@ pub fn resolved<'a>(path: impl PathLike<'a>) -> Result<Cow<'a, str>, env::Workin
   Ok(path)
 }
 
+#[doc(hidden)]
+/// Joins a list of paths into a new string and resolves it.
+///
+/// This function is used to support the [`resolve!`] macro.
+pub fn resolve_parts(parts: &[&str]) -> Result<String, env::WorkingPathError> {
+  let base = match parts.iter().any(|part| is_absolute(part)) {
+    true => String::new(),
+    false => env::working_path()?,
+  };
+
+  let mut path = join_parts_onto(base, parts);
+
+  normalize(&mut path);
+
+  Ok(path)
+}
+
+/// Replaces the extension of the last component of the path, or removes it if
+/// the new extension is empty.
+///
+/// If the path is a root or empty path, this function does nothing and returns
+/// `false`.
+pub fn set_extension(path: &mut String, extension: &str) -> bool {
+  let stem_len = match stem(path) {
+    Some(stem) => stem.len(),
+    None => return false,
+  };
+
+  // Find the end of the stem, ignoring trailing separators.
+
+  let trail_seps = path.chars().rev().take_while(|c| is_separator(*c)).count();
+  let last_len = last(path).map(str::len).unwrap_or_default();
+  let stem_end = path.len() - trail_seps - last_len + stem_len;
+
+  path.truncate(stem_end);
+
+  if !extension.is_empty() {
+    path.reserve(extension.len() + 1);
+    path.push('.');
+    path.push_str(extension);
+  }
+
+  true
+}
+
 /// Returns `true` if the first path starts with the second path.
 pub fn starts_with(path: &str, prefix: &str) -> bool {
   as_std_path(path).starts_with(prefix)
 }
 
+/// Returns the last component of the path without its extension.
+///
+/// If `path` is a root or empty path, this function returns `None`.
+pub fn stem(path: &str) -> Option<&str> {
+  as_std_path(path).file_stem()?.to_str()
+}
+
+/// Returns the remainder of the first path after the second path, or `None` if
+/// the first path does not start with the second path.
+///
+/// Paths are compared by component, so `/ab` does not start with `/a`.
+pub fn strip_prefix<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+  as_std_path(path).strip_prefix(prefix).ok()?.to_str()
+}
+
+/// Returns the given path with the extension of its last component replaced,
+/// or removed if the new extension is empty.
+pub fn with_extension<'a>(path: impl PathLike<'a>, extension: &str) -> Cow<'a, str> {
+  let mut path = path.to_cow();
+
+  let expected = Some(extension).filter(|e| !e.is_empty());
+
+  if self::extension(&path) != expected {
+    set_extension(path.to_mut(), extension);
+  }
+
+  path
+}
+
 /// Returns the given path with a trailing separator if it does not already
 /// have one.
 pub fn with_trailing_sep<'a>(path: impl PathLike<'a>) -> Cow<'a, str> {
@@ -174,6 +396,29 @@ fn as_std_path(path: &str) -> &Path {
   path.as_ref()
 }
 
+/// Appends a list of paths to a base path, reserving enough capacity for all
+/// of them first.
+fn join_parts_onto(mut base: String, parts: &[&str]) -> String {
+  // Skip everything before the last absolute path, since it replaces them.
+
+  let parts = match parts.iter().rposition(|part| is_absolute(part)) {
+    Some(i) => {
+      base.clear();
+      &parts[i..]
+    }
+
+    None => parts,
+  };
+
+  base.reserve(parts.iter().map(|part| part.len() + 1).sum());
+
+  for part in parts {
+    append(&mut base, part);
+  }
+
+  base
+}
+
 /// Normalizes the given path into the output string.
 ///
 /// The output is expected to already be empty.
@@ -192,6 +437,52 @@ fn normalize_into(path: &str, output: &mut String) {
   }
 }
 
+/// Returns the components of a path with `.` and `..` components applied.
+///
+/// Unlike [`normalize_into()`], leading `..` components of relative paths are
+/// kept.
+fn lexical_components(path: &str) -> Vec<&str> {
+  let mut output = Vec::new();
+
+  for component in components(path) {
+    match component {
+      "." => continue,
+
+      ".." => match output.last() {
+        None | Some(&"..") => output.push(component),
+        Some(last) if as_std_path(last).has_root() => continue,
+        Some(_) => {
+          output.pop();
+        }
+      },
+
+      _ => output.push(component),
+    }
+  }
+
+  output
+}
+
+/// An iterator over the components of a path returned from [`components()`].
+#[derive(Clone)]
+pub struct Components<'a>(std::path::Components<'a>);
+
+impl<'a> Iterator for Components<'a> {
+  type Item = &'a str;
+
+  fn next(&mut self) -> Option<&'a str> {
+    // The path was a `&str`, so its components are valid Unicode.
+
+    self.0.next().and_then(|c| c.as_os_str().to_str())
+  }
+}
+
+impl<'a> DoubleEndedIterator for Components<'a> {
+  fn next_back(&mut self) -> Option<&'a str> {
+    self.0.next_back().and_then(|c| c.as_os_str().to_str())
+  }
+}
+
 /// A trait for values that can be used in path operations.
 pub trait PathLike<'a>: Sized {
   /// Converts this value into a `Cow<str>`.
@@ -201,6 +492,18 @@ pub trait PathLike<'a>: Sized {
   fn to_owned(self) -> String {
     self.to_cow().into()
   }
+
+  /// Converts this value into a `Cow<str>`, failing if it contains
+  /// non-Unicode characters.
+  fn try_to_cow(self) -> Result<Cow<'a, str>> {
+    Ok(self.to_cow())
+  }
+
+  /// Converts this value into a `String`, failing if it contains non-Unicode
+  /// characters.
+  fn try_to_owned(self) -> Result<String> {
+    self.try_to_cow().map(Cow::into_owned)
+  }
 }
 
 // Implement `PathLike` for common string types.
@@ -253,9 +556,50 @@ impl<'a> PathLike<'a> for &'a &'_ mut Cow<'_, str> {
   }
 }
 
+// Implement `PathLike` for native path types. File system operations fail if
+// the path contains non-Unicode characters. Path manipulation functions
+// replace them with `U+FFFD`.
+
 impl<'a> PathLike<'a> for &'a std::path::PathBuf {
   fn to_cow(self) -> Cow<'a, str> {
-    self.to_string_lossy().to_cow()
+    self.as_path().to_cow()
+  }
+
+  fn try_to_cow(self) -> Result<Cow<'a, str>> {
+    self.as_path().try_to_cow()
+  }
+}
+
+impl<'a> PathLike<'a> for &'a Path {
+  fn to_cow(self) -> Cow<'a, str> {
+    self.to_string_lossy()
+  }
+
+  fn try_to_cow(self) -> Result<Cow<'a, str>> {
+    match self.to_str() {
+      Some(path) => Ok(path.into()),
+      None => fail!("Path `{}` contains non-Unicode characters.", self.to_string_lossy()),
+    }
+  }
+}
+
+impl<'a> PathLike<'a> for &'a std::ffi::OsStr {
+  fn to_cow(self) -> Cow<'a, str> {
+    Path::new(self).to_cow()
+  }
+
+  fn try_to_cow(self) -> Result<Cow<'a, str>> {
+    Path::new(self).try_to_cow()
+  }
+}
+
+impl<'a> PathLike<'a> for &'a std::ffi::OsString {
+  fn to_cow(self) -> Cow<'a, str> {
+    Path::new(self).to_cow()
+  }
+
+  fn try_to_cow(self) -> Result<Cow<'a, str>> {
+    Path::new(self).try_to_cow()
   }
 }
 
@@ -284,6 +628,26 @@ mod tests {
     assert_eq!(p, "/a/");
   }
 
+  #[test]
+  fn test_components() {
+    assert_eq!(components("/a//b/./c/").collect::<Vec<_>>(), ["/", "a", "b", "c"]);
+    assert_eq!(components("./a/../b").collect::<Vec<_>>(), [".", "a", "..", "b"]);
+    assert_eq!(components("a/b").rev().collect::<Vec<_>>(), ["b", "a"]);
+    assert_eq!(components("").next(), None);
+  }
+
+  #[test]
+  fn test_extension() {
+    assert_eq!(extension("/a/b.tar.gz"), Some("gz"));
+    assert_eq!(extension("/a/b/"), None);
+    assert_eq!(extension("/a/.hidden"), None);
+    assert_eq!(extension("/"), None);
+
+    assert_eq!(stem("/a/b.tar.gz"), Some("b.tar"));
+    assert_eq!(stem("/a/.hidden"), Some(".hidden"));
+    assert_eq!(stem("/"), None);
+  }
+
   #[test]
   fn test_is_absolute() {
     assert!(is_absolute("/a"));
@@ -298,6 +662,19 @@ mod tests {
     assert_eq!(join("/a", ""), "/a/");
   }
 
+  #[test]
+  fn test_join_parts() {
+    let joined = join_parts(&["a", "b/", "c"]);
+
+    assert_eq!(joined, "a/b/c");
+    assert_eq!(joined.capacity(), 8);
+
+    assert_eq!(join_parts(&["a", "/b", "c"]), "/b/c");
+    assert_eq!(join_parts(&[""]), "");
+    assert_eq!(normalize_parts(&["/a/./b", "../c"]), "/a/c");
+    assert_eq!(resolve_parts(&["/a", "b/.."]).unwrap(), "/a");
+  }
+
   #[test]
   fn test_last() {
     assert_eq!(last("/a/b"), Some("b"));
@@ -307,6 +684,24 @@ mod tests {
     assert_eq!(last(""), None);
   }
 
+  #[cfg(unix)]
+  #[test]
+  fn test_non_unicode() {
+    use std::os::unix::ffi::OsStrExt as _;
+
+    let path = Path::new(std::ffi::OsStr::from_bytes(b"/a/\xff"));
+
+    assert_eq!(path.to_cow(), "/a/\u{fffd}");
+    assert_eq!(Path::new("/a").try_to_cow().unwrap(), "/a");
+
+    assert_eq!(
+      path.try_to_cow().unwrap_err().to_string(),
+      "Path `/a/\u{fffd}` contains non-Unicode characters."
+    );
+
+    assert!(crate::thread::block_on(crate::fs::read(path)).is_err());
+  }
+
   #[test]
   fn test_normalize() {
     let mut p: String = "/a/../b".into();
@@ -371,6 +766,23 @@ mod tests {
     assert_eq!(p, "");
   }
 
+  #[test]
+  fn test_relative_to() {
+    assert_eq!(relative_to("/a/b/c", "/a").unwrap(), "b/c");
+    assert_eq!(relative_to("/a/b/c", "/a/d/e").unwrap(), "../../b/c");
+    assert_eq!(relative_to("/a/./b/", "/a/b").unwrap(), "");
+    assert_eq!(relative_to("/a", "/a/b").unwrap(), "..");
+    assert_eq!(relative_to("a/b", "c").unwrap(), "../a/b");
+    assert_eq!(relative_to("/a", "a"), None);
+    assert_eq!(relative_to("../a", "b").unwrap(), "../../a");
+    assert_eq!(relative_to("../a", "../b").unwrap(), "../a");
+    assert_eq!(relative_to("/../a", "/b/..").unwrap(), "a");
+    assert_eq!(relative_to("a", ".."), None);
+    assert_eq!(relative_to("../a", "../../b"), None);
+
+    assert!(matches!(relative_to("/a/b/c", "/a/").unwrap(), Cow::Borrowed("b/c")));
+  }
+
   #[test]
   fn test_resolve() {
     let wd = env::working_path().unwrap();
@@ -388,6 +800,38 @@ mod tests {
     assert_eq!(p, format!("{}/b", wd));
   }
 
+  #[test]
+  fn test_set_extension() {
+    let mut p: String = "/a/b.txt".into();
+
+    assert!(set_extension(&mut p, "md"));
+    assert_eq!(p, "/a/b.md");
+
+    assert!(set_extension(&mut p, ""));
+    assert_eq!(p, "/a/b");
+
+    p.replace_range(.., "a/b.c/");
+    assert!(set_extension(&mut p, "d"));
+    assert_eq!(p, "a/b.d");
+
+    p.replace_range(.., "/");
+    assert!(!set_extension(&mut p, "d"));
+    assert_eq!(p, "/");
+
+    assert_eq!(with_extension("/a/b.txt", "md"), "/a/b.md");
+    assert_eq!(with_extension("/a/b", "md"), "/a/b.md");
+    assert!(matches!(with_extension("/a/b.md", "md"), Cow::Borrowed(_)));
+    assert!(matches!(with_extension("/a/b", ""), Cow::Borrowed(_)));
+  }
+
+  #[test]
+  fn test_strip_prefix() {
+    assert_eq!(strip_prefix("/a/b/c", "/a"), Some("b/c"));
+    assert_eq!(strip_prefix("/a/b/c", "/a/b/c/"), Some(""));
+    assert_eq!(strip_prefix("/ab", "/a"), None);
+    assert_eq!(strip_prefix("a/b", "a"), Some("b"));
+  }
+
   #[test]
   fn test_resolved() {
     let wd = env::working_path().unwrap();
@@ -396,4 +840,14 @@ mod tests {
     assert_eq!(resolved("/a/./b/").unwrap(), "/a/b");
     assert_eq!(resolved("a/../b/.").unwrap(), format!("{}/b", wd));
   }
+
+  #[test]
+  fn test_canonicalize() {
+    let wd = env::working_path().unwrap();
+
+    let canonical = crate::thread::block_on(canonicalize("src/../src/fs.rs")).unwrap();
+
+    assert_eq!(canonical, format!("{}/src/fs.rs", wd));
+    assert!(crate::thread::block_on(canonicalize("does/not/exist")).is_err());
+  }
 }
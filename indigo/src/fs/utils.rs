@@ -4,49 +4,303 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use super::{path, GlobSet, NativePath, MATCH_OPTIONS, SEMAPHORE};
 use crate::prelude::*;
-use crate::sync::Semaphore;
-
-/// A shared semaphore to limit the number of concurrent file system operations.
-static SEMAPHORE: Lazy<Semaphore> = Lazy::new(|| Semaphore::new(8));
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
 
 /// Finds and returns all paths matching the given glob pattern.
 ///
-/// This function ignores matching paths with non-Unicode characters.
+/// The pattern may contain `{a,b}` braces for alternatives. This function
+/// ignores matching paths with non-Unicode characters. Use [`glob_native()`]
+/// to find them.
 pub async fn glob(pattern: impl Into<String>) -> Result<Vec<String>> {
-  const MATCH_OPTS: glob::MatchOptions = glob::MatchOptions {
-    // Case-insensitive on Windows.
-    case_sensitive: !cfg!(target_os = "windows"),
-    // Don't match `/` with wildcards `*` and `?`.
-    require_literal_separator: true,
-    // Require explicit `.` to match dotfiles.
-    require_literal_leading_dot: true,
-  };
+  let mut set = GlobSet::new();
+
+  set.include(&pattern.into())?;
+
+  glob_all(&set).await
+}
+
+/// Finds and returns all paths matching an include pattern of the given glob
+/// set that do not match an exclude pattern.
+///
+/// This function ignores matching paths with non-Unicode characters. Use
+/// [`glob_all_native()`] to find them.
+pub async fn glob_all(set: &GlobSet) -> Result<Vec<String>> {
+  let paths = glob_all_native(set).await?;
+
+  Ok(paths.into_iter().filter_map(|path| path.into_string().ok()).collect())
+}
+
+/// Finds and returns all paths matching the given glob pattern, including
+/// paths with non-Unicode characters.
+///
+/// The pattern may contain `{a,b}` braces for alternatives.
+pub async fn glob_native(pattern: impl Into<String>) -> Result<Vec<NativePath>> {
+  let mut set = GlobSet::new();
+
+  set.include(&pattern.into())?;
 
-  let pattern = pattern.into();
+  glob_all_native(&set).await
+}
+
+/// Finds and returns all paths matching an include pattern of the given glob
+/// set that do not match an exclude pattern, including paths with non-Unicode
+/// characters.
+///
+/// Non-Unicode characters are replaced with `U+FFFD` when matching, so they
+/// only match wildcards.
+pub async fn glob_all_native(set: &GlobSet) -> Result<Vec<NativePath>> {
+  let set = set.clone();
   let _permit = SEMAPHORE.acquire().await;
 
   future::unblock! {
-    // Find all matching files.
+    let mut paths = Vec::new();
+    let mut output = Vec::new();
+    let mut found = HashSet::new();
 
-    let paths = glob::glob_with(&pattern, MATCH_OPTS)?;
+    for pattern in set.include_patterns() {
+      expand(pattern.as_str(), &mut paths)?;
 
-    // Collect paths as strings, ignoring any with non-Unicode characters.
+      // Collect paths that are not excluded or already found.
 
-    let mut output: Vec<String> = default();
+      for path in paths.drain(..) {
+        if !set.is_excluded(&path.to_string_lossy()) && found.insert(path.clone()) {
+          output.push(path.into());
+        }
+      }
+    }
 
-    for path in paths {
-      match path {
-        Ok(path) =>
+    Ok(output)
+  }
+}
+
+/// One component of a glob pattern.
+enum Component {
+  /// A `**` component that matches any number of directories.
+  Recursive,
+  /// A component that matches a single file or directory name.
+  Name(glob::Pattern),
+}
 
-      if let Some(path) = path.to_str() {
-        output.push(path.into());
-      },
+/// Finds all paths matching a glob pattern and adds them to the output.
+fn expand(pattern: &str, output: &mut Vec<PathBuf>) -> Result {
+  let mut base = PathBuf::new();
+  let mut components = Vec::new();
+
+  if pattern.starts_with(path::is_separator) {
+    base.push(path::SEPARATOR.to_string());
+  }
 
-      Err(err) => fail!("{} (at `{}`).", err.error(), err.path().display()),
+  // Add leading components without wildcards to the base path.
+
+  for part in pattern.split(path::is_separator).filter(|part| !part.is_empty()) {
+    match part {
+      "**" => components.push(Component::Recursive),
+      _ if !components.is_empty() || part.contains(&['*', '?', '['][..]) => {
+        let pattern = glob::Pattern::new(part)
+          .map_err(|err| fail::err!("Invalid glob pattern `{}`. {}.", pattern, err.msg))?;
+
+        components.push(Component::Name(pattern));
+      }
+
+      _ => base.push(part),
     }
   }
 
-    Ok(output)
+  if components.is_empty() {
+    if std::fs::symlink_metadata(&base).is_ok() {
+      output.push(base);
+    }
+
+    return Ok(());
+  }
+
+  expand_in(&base, &components, output)
+}
+
+/// Finds all paths in a directory matching the given pattern components and
+/// adds them to the output.
+fn expand_in(dir: &Path, components: &[Component], output: &mut Vec<PathBuf>) -> Result {
+  let (component, rest) = match components.split_first() {
+    Some(split) => split,
+    None => return Ok(()),
+  };
+
+  for (path, name, is_dir) in read_dir_sorted(dir)? {
+    let name = name.to_string_lossy();
+
+    match component {
+      Component::Name(pattern) if pattern.matches_with(&name, MATCH_OPTIONS) => {
+        match rest.is_empty() {
+          true => output.push(path),
+          false if path.is_dir() => expand_in(&path, rest, output)?,
+          false => {}
+        }
+      }
+
+      Component::Name(_) => {}
+
+      // Like other wildcards, `**` does not match hidden files.
+      Component::Recursive if name.starts_with('.') => {}
+
+      Component::Recursive => {
+        if rest.is_empty() {
+          output.push(path.clone());
+        }
+
+        if is_dir {
+          expand_in(&path, components, output)?;
+        }
+      }
+    }
+  }
+
+  // Match the rest of the components in this directory, since `**` also
+  // matches zero directories.
+
+  if let Component::Recursive = component {
+    expand_in(dir, rest, output)?;
+  }
+
+  Ok(())
+}
+
+/// Returns the paths, names, and whether each entry is a directory for the
+/// entries of a directory, sorted by name.
+///
+/// Symlinks to directories are not considered directories. If the directory
+/// does not exist, an empty list is returned.
+fn read_dir_sorted(dir: &Path) -> Result<Vec<(PathBuf, OsString, bool)>> {
+  let failed =
+    fail::with!("Failed to read directory {}", fmt::AsPath(&dir.to_string_lossy()).describe());
+
+  let entries =
+    match std::fs::read_dir(if dir.as_os_str().is_empty() { Path::new(".") } else { dir }) {
+      Ok(entries) => entries,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+      Err(err) => return Err(failed(err)),
+    };
+
+  let mut output = Vec::new();
+
+  for entry in entries {
+    let entry = entry.map_err(&failed)?;
+    let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or_default();
+    let name = entry.file_name();
+
+    output.push((dir.join(&name), name, is_dir));
+  }
+
+  output.sort_by(|a, b| a.1.cmp(&b.1));
+
+  Ok(output)
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fs::TempDir;
+  use crate::thread;
+
+  /// Creates a directory tree for expanding patterns.
+  fn create_tree() -> (TempDir, String) {
+    let temp = TempDir::new();
+
+    for dir in &["src/fs", "src/.git"] {
+      std::fs::create_dir_all(temp.join(dir)).unwrap();
+    }
+
+    for file in &["a.txt", ".hidden.txt", "src/lib.rs", "src/fs/mod.rs", "src/.git/config"] {
+      std::fs::write(temp.join(file), "").unwrap();
+    }
+
+    let root = temp.join("");
+
+    (temp, root)
+  }
+
+  /// Expands a pattern relative to the given root and returns the matching
+  /// paths relative to it.
+  fn expand(root: &str, pattern: &str) -> Vec<String> {
+    let mut output = Vec::new();
+
+    super::expand(&path::join(root, pattern), &mut output).unwrap();
+
+    output.into_iter().map(|path| path.to_str().unwrap()[root.len()..].to_string()).collect()
+  }
+
+  #[test]
+  fn test_expand() {
+    let (_temp, root) = create_tree();
+
+    assert_eq!(expand(&root, "**/*.rs"), ["src/fs/mod.rs", "src/lib.rs"]);
+    assert_eq!(expand(&root, "src/**/lib.rs"), ["src/lib.rs"]);
+    assert_eq!(expand(&root, "**"), ["a.txt", "src", "src/fs", "src/fs/mod.rs", "src/lib.rs"]);
+    assert_eq!(expand(&root, "*/*/*.rs"), ["src/fs/mod.rs"]);
+    assert!(expand(&root, "missing/**").is_empty());
+    assert!(super::expand("/a/[", &mut Vec::new()).is_err());
+  }
+
+  #[test]
+  fn test_expand_hidden() {
+    let (_temp, root) = create_tree();
+
+    assert_eq!(expand(&root, "*.txt"), ["a.txt"]);
+    assert_eq!(expand(&root, ".*.txt"), [".hidden.txt"]);
+    assert!(expand(&root, "**/config").is_empty());
+    assert_eq!(expand(&root, "src/.git/*"), ["src/.git/config"]);
+  }
+
+  #[test]
+  fn test_expand_literal() {
+    let (_temp, root) = create_tree();
+
+    assert_eq!(expand(&root, "src/lib.rs"), ["src/lib.rs"]);
+    assert_eq!(expand(&root, "src/.git"), ["src/.git"]);
+    assert!(expand(&root, "src/missing.rs").is_empty());
+  }
+
+  #[test]
+  fn test_glob_exclude() {
+    let (_temp, root) = create_tree();
+    let mut set = GlobSet::new();
+
+    set.include(&path::join(&root, "**/*.{rs,txt}")).unwrap();
+    set.exclude(&path::join(&root, "src/fs/**")).unwrap();
+
+    // Paths are output in the order of the expanded braces.
+
+    let paths = thread::block_on(glob_all(&set)).unwrap();
+
+    assert_eq!(paths, [path::join(&root, "src/lib.rs"), path::join(&root, "a.txt")]);
+  }
+
+  #[test]
+  fn test_read_dir_sorted() {
+    let (temp, root) = create_tree();
+
+    let entries: Vec<_> = read_dir_sorted(Path::new(&temp.join("src")))
+      .unwrap()
+      .into_iter()
+      .map(|(path, name, is_dir)| (path.to_str().unwrap()[root.len()..].to_string(), name, is_dir))
+      .collect();
+
+    assert_eq!(
+      entries,
+      [
+        ("src/.git".to_string(), ".git".into(), true),
+        ("src/fs".to_string(), "fs".into(), true),
+        ("src/lib.rs".to_string(), "lib.rs".into(), false),
+      ]
+    );
+
+    assert!(read_dir_sorted(Path::new(&temp.join("missing"))).unwrap().is_empty());
+    assert!(read_dir_sorted(Path::new(&temp.join("a.txt"))).is_err());
   }
 }
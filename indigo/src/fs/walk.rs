@@ -0,0 +1,537 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::ops::failed;
+use super::{DirEntry, GlobSet, PathLike, MATCH_OPTIONS};
+use crate::future::Poll;
+use crate::prelude::*;
+use blocking::Unblock;
+use std::io;
+use std::path::PathBuf;
+
+/// The names of ignore files read when [`Walk::ignore_files()`] is enabled.
+const IGNORE_FILE_NAMES: &[&str] = &[".gitignore", ".ignore"];
+
+/// A stream that recursively walks a directory, returned by [`walk()`].
+///
+/// Options can be configured with the builder methods before the stream is
+/// first polled.
+pub struct Walk {
+  walker: Option<Walker>,
+  entries: Option<Unblock<Walker>>,
+}
+
+/// A blocking iterator that walks a directory.
+struct Walker {
+  root: String,
+  max_depth: usize,
+  follow_links: bool,
+  hidden: bool,
+  ignore_files: bool,
+  globs: GlobSet,
+  sorted: bool,
+  error: Option<fail::Error>,
+  started: bool,
+  stack: Vec<Dir>,
+  next_dir: Option<(String, String, usize)>,
+}
+
+/// A directory being read by a [`Walker`].
+struct Dir {
+  path: String,
+  relative_path: String,
+  depth: usize,
+  entries: DirEntries,
+  ignore_rules: Vec<IgnoreRule>,
+  canonical_path: Option<PathBuf>,
+}
+
+/// The entries of a [`Dir`], either read as needed or read and sorted all at
+/// once.
+enum DirEntries {
+  Unsorted(std::fs::ReadDir),
+  Sorted(std::vec::IntoIter<io::Result<std::fs::DirEntry>>),
+}
+
+/// A rule from an ignore file.
+struct IgnoreRule {
+  pattern: glob::Pattern,
+  anchored: bool,
+  dirs_only: bool,
+  negated: bool,
+}
+
+/// Returns a stream that recursively walks a directory, outputting an entry
+/// for each file and directory inside it.
+///
+/// Directories are walked depth-first, and each directory is output before
+/// its contents. Entries with non-Unicode names are ignored. Errors reading
+/// individual directories are output without ending the stream.
+pub fn walk<'a>(root: impl PathLike<'a>) -> Walk {
+  let (root, error) = match root.try_to_owned() {
+    Ok(root) => (root, None),
+    Err(err) => (String::new(), Some(err)),
+  };
+
+  Walk {
+    walker: Some(Walker {
+      root,
+      max_depth: usize::MAX,
+      follow_links: false,
+      hidden: true,
+      ignore_files: false,
+      globs: default(),
+      sorted: false,
+      error,
+      started: false,
+      stack: default(),
+      next_dir: None,
+    }),
+    entries: None,
+  }
+}
+
+impl Walk {
+  /// Excludes entries whose paths relative to the root match the given glob
+  /// pattern.
+  ///
+  /// Excluded directories are not walked.
+  pub fn exclude(mut self, pattern: &str) -> Self {
+    let walker = self.walker();
+
+    if let Err(err) = walker.globs.exclude(pattern) {
+      walker.error.get_or_insert(err);
+    }
+
+    self
+  }
+
+  /// Sets whether to follow symbolic links to directories.
+  ///
+  /// Defaults to `false`. If `true`, symbolic links that would cause a loop
+  /// are output as errors.
+  pub fn follow_links(mut self, value: bool) -> Self {
+    self.walker().follow_links = value;
+    self
+  }
+
+  /// Adds the patterns of a glob set to the include and exclude patterns.
+  pub fn globs(mut self, set: &GlobSet) -> Self {
+    self.walker().globs.extend(set);
+    self
+  }
+
+  /// Sets whether to output and walk hidden files and directories whose names
+  /// start with `.`.
+  ///
+  /// Defaults to `true`.
+  pub fn hidden(mut self, value: bool) -> Self {
+    self.walker().hidden = value;
+    self
+  }
+
+  /// Sets whether to ignore entries matched by rules in `.gitignore` and
+  /// `.ignore` files.
+  ///
+  /// Defaults to `false`. If `true`, `.git` directories are also ignored. Only
+  /// ignore files inside the root directory are read.
+  pub fn ignore_files(mut self, value: bool) -> Self {
+    self.walker().ignore_files = value;
+    self
+  }
+
+  /// Only outputs entries whose paths relative to the root match the given
+  /// glob pattern or any other included pattern.
+  ///
+  /// Directories that do not match are still walked.
+  pub fn include(mut self, pattern: &str) -> Self {
+    let walker = self.walker();
+
+    if let Err(err) = walker.globs.include(pattern) {
+      walker.error.get_or_insert(err);
+    }
+
+    self
+  }
+
+  /// Sets the maximum depth of entries to output.
+  ///
+  /// Entries directly inside the root have a depth of 1.
+  pub fn max_depth(mut self, value: usize) -> Self {
+    self.walker().max_depth = value;
+    self
+  }
+
+  /// Sets whether to output the entries of each directory sorted by name.
+  ///
+  /// Defaults to `false`. If `true`, the entries of each directory are read
+  /// all at once before any are output.
+  pub fn sorted(mut self, value: bool) -> Self {
+    self.walker().sorted = value;
+    self
+  }
+
+  /// Returns a mutable reference to the walker for configuring it.
+  fn walker(&mut self) -> &mut Walker {
+    self.walker.as_mut().expect("The walk has already started.")
+  }
+}
+
+impl Walker {
+  /// Starts reading a directory and pushes it onto the stack.
+  fn push_dir(&mut self, path: String, relative_path: String, depth: usize) -> Result {
+    let mut canonical_path = None;
+
+    if self.follow_links {
+      let canonical = std::fs::canonicalize(&path).map_err(failed("walk", &path))?;
+
+      if self.stack.iter().any(|dir| dir.canonical_path.as_ref() == Some(&canonical)) {
+        fail!(
+          "Failed to walk {}. The symbolic link creates a loop.",
+          fmt::AsPath(&path).describe()
+        );
+      }
+
+      canonical_path = Some(canonical);
+    }
+
+    let inner = std::fs::read_dir(&path).map_err(failed("read directory", &path))?;
+
+    let entries = match self.sorted {
+      true => {
+        let mut entries: Vec<_> = inner.collect();
+
+        entries.sort_by_key(|entry| entry.as_ref().ok().map(std::fs::DirEntry::file_name));
+
+        DirEntries::Sorted(entries.into_iter())
+      }
+
+      false => DirEntries::Unsorted(inner),
+    };
+
+    let mut ignore_rules = Vec::new();
+
+    if self.ignore_files {
+      for name in IGNORE_FILE_NAMES {
+        let ignore_path = super::path::join(&path, *name);
+
+        match std::fs::read_to_string(&*ignore_path) {
+          Ok(contents) => ignore_rules.extend(contents.lines().filter_map(IgnoreRule::parse)),
+          Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+          Err(err) => return Err(failed("read", &ignore_path)(err)),
+        }
+      }
+    }
+
+    self.stack.push(Dir { path, relative_path, depth, entries, ignore_rules, canonical_path });
+
+    Ok(())
+  }
+
+  /// Returns `true` if the given relative path is matched by the rules of an
+  /// ignore file.
+  fn is_ignored(&self, relative_path: &str, name: &str, is_dir: bool) -> bool {
+    // Deeper ignore files take precedence, as do later rules in each file.
+
+    for dir in self.stack.iter().rev() {
+      let path = match dir.relative_path.is_empty() {
+        true => relative_path,
+        false => &relative_path[dir.relative_path.len() + 1..],
+      };
+
+      for rule in dir.ignore_rules.iter().rev() {
+        if rule.is_match(path, name, is_dir) {
+          return !rule.negated;
+        }
+      }
+    }
+
+    false
+  }
+}
+
+impl IgnoreRule {
+  /// Parses a line of an ignore file, returning `None` if it is blank, a
+  /// comment, or an invalid pattern.
+  fn parse(line: &str) -> Option<Self> {
+    let mut line = line.trim_end();
+
+    if line.is_empty() || line.starts_with('#') {
+      return None;
+    }
+
+    let negated = line.starts_with('!');
+
+    // Remove the `!` prefix or the escape character for a literal `!` or `#`.
+
+    if negated || line.starts_with("\\!") || line.starts_with("\\#") {
+      line = &line[1..];
+    }
+
+    let dirs_only = line.ends_with('/');
+
+    if dirs_only {
+      line = &line[..line.len() - 1];
+    }
+
+    // Patterns with a separator are relative to the directory of the ignore
+    // file. Other patterns match names at any depth.
+
+    let anchored = line.contains('/');
+
+    if line.starts_with('/') {
+      line = &line[1..];
+    }
+
+    let pattern = glob::Pattern::new(line).ok()?;
+
+    Some(Self { pattern, anchored, dirs_only, negated })
+  }
+
+  /// Returns `true` if the rule matches a path relative to the directory of
+  /// the ignore file.
+  fn is_match(&self, path: &str, name: &str, is_dir: bool) -> bool {
+    if self.dirs_only && !is_dir {
+      return false;
+    }
+
+    match self.anchored {
+      true => self.pattern.matches_with(path, MATCH_OPTIONS),
+      false => self.pattern.matches_with(name, MATCH_OPTIONS),
+    }
+  }
+}
+
+// Implement `Iterator` for the blocking walker.
+
+impl Iterator for Walker {
+  type Item = Result<DirEntry>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if !self.started {
+      self.started = true;
+
+      if let Some(err) = self.error.take() {
+        return Some(Err(err));
+      }
+
+      self.next_dir = Some((self.root.clone(), String::new(), 0));
+    }
+
+    loop {
+      // Start walking the last directory that was output.
+
+      if let Some((path, relative_path, depth)) = self.next_dir.take() {
+        if let Err(err) = self.push_dir(path, relative_path, depth) {
+          return Some(Err(err));
+        }
+      }
+
+      let dir = self.stack.last_mut()?;
+
+      let entry = match &mut dir.entries {
+        DirEntries::Unsorted(entries) => entries.next(),
+        DirEntries::Sorted(entries) => entries.next(),
+      };
+
+      let entry = match entry {
+        Some(Ok(entry)) => entry,
+        Some(Err(err)) => return Some(Err(failed("read directory", &dir.path)(err))),
+
+        None => {
+          self.stack.pop();
+          continue;
+        }
+      };
+
+      let depth = dir.depth + 1;
+
+      // Ignore entries with non-Unicode names.
+
+      let (name, path) =
+        match (entry.file_name().into_string(), entry.path().into_os_string().into_string()) {
+          (Ok(name), Ok(path)) => (name, path),
+          _ => continue,
+        };
+
+      if !self.hidden && name.starts_with('.') {
+        continue;
+      }
+
+      let relative_path = match dir.relative_path.is_empty() {
+        true => name.clone(),
+        false => format!("{}/{}", dir.relative_path, name),
+      };
+
+      let file_type = match entry.file_type() {
+        Ok(file_type) => file_type,
+        Err(err) => return Some(Err(failed("read file type of", &path)(err))),
+      };
+
+      let is_dir = match file_type.is_symlink() && self.follow_links {
+        true => std::fs::metadata(&path).map(|m| m.is_dir()).unwrap_or_default(),
+        false => file_type.is_dir(),
+      };
+
+      if self.ignore_files {
+        if is_dir && name == ".git" {
+          continue;
+        }
+
+        if self.is_ignored(&relative_path, &name, is_dir) {
+          continue;
+        }
+      }
+
+      if self.globs.is_excluded(&relative_path) {
+        continue;
+      }
+
+      let included = self.globs.is_match(&relative_path);
+
+      if is_dir && depth < self.max_depth {
+        self.next_dir = Some((path.clone(), relative_path, depth));
+      }
+
+      if included && depth <= self.max_depth {
+        return Some(Ok(DirEntry { path, file_type, depth }));
+      }
+    }
+  }
+}
+
+// Implement `Stream` for the async walk stream.
+
+impl Stream for Walk {
+  type Item = Result<DirEntry>;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut future::Context) -> Poll<Option<Self::Item>> {
+    if let Some(walker) = self.walker.take() {
+      self.entries = Some(Unblock::new(walker));
+    }
+
+    match &mut self.entries {
+      Some(entries) => Pin::new(entries).poll_next(cx),
+      None => Poll::Ready(None),
+    }
+  }
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fs::{self, TempDir};
+  use crate::thread;
+
+  /// Creates a directory tree for walking.
+  fn create_tree(temp: &TempDir) -> String {
+    let root = temp.join("root");
+
+    for dir in &["a/b", ".hidden", "target"] {
+      std::fs::create_dir_all(fs::path::join(&root, *dir).as_ref()).unwrap();
+    }
+
+    for file in &["a/b/c.txt", "a/d.txt", ".hidden/e.txt", "target/f.txt"] {
+      std::fs::write(fs::path::join(&root, *file).as_ref(), "").unwrap();
+    }
+
+    root
+  }
+
+  /// Walks a directory and returns the output paths relative to the root, or
+  /// error messages.
+  fn collect(root: &str, walk: Walk) -> Vec<String> {
+    thread::block_on(walk.sorted(true).collect::<Vec<_>>())
+      .into_iter()
+      .map(|entry| match entry {
+        Ok(entry) => entry.path()[root.len() + 1..].to_string(),
+        Err(err) => err.to_string(),
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_max_depth() {
+    let temp = TempDir::new();
+    let root = create_tree(&temp);
+
+    assert_eq!(collect(&root, fs::walk(&root).max_depth(1)), [".hidden", "a", "target"]);
+    assert_eq!(collect(&root, fs::walk(&root).max_depth(0)), Vec::<String>::new());
+
+    assert_eq!(
+      collect(&root, fs::walk(&root).max_depth(2)),
+      [".hidden", ".hidden/e.txt", "a", "a/b", "a/d.txt", "target", "target/f.txt"]
+    );
+
+    let depths: Vec<_> = thread::block_on(fs::walk(&root).sorted(true).collect::<Vec<_>>())
+      .into_iter()
+      .map(|entry| entry.unwrap().depth())
+      .collect();
+
+    assert_eq!(depths, [1, 2, 1, 2, 3, 2, 1, 2]);
+  }
+
+  #[test]
+  fn test_exclude() {
+    let temp = TempDir::new();
+    let root = create_tree(&temp);
+
+    assert_eq!(
+      collect(&root, fs::walk(&root).exclude("target").exclude("**/b").hidden(false)),
+      ["a", "a/d.txt"]
+    );
+
+    // Excluded directories are not walked even if their contents are
+    // included.
+
+    let globs = GlobSet::from_patterns(vec!["**/*.txt", "!a"]).unwrap();
+
+    assert!(globs.is_excluded("a"));
+    assert!(!globs.is_excluded("a/b/c.txt"));
+    assert_eq!(collect(&root, fs::walk(&root).globs(&globs)), ["target/f.txt"]);
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_symlinks() {
+    let temp = TempDir::new();
+    let root = create_tree(&temp);
+    let link = fs::path::join(&root, "link").into_owned();
+
+    std::os::unix::fs::symlink("a", &link).unwrap();
+
+    let walk = || fs::walk(&root).include("link").include("link/**");
+
+    assert_eq!(collect(&root, walk()), ["link"]);
+
+    assert_eq!(
+      collect(&root, walk().follow_links(true)),
+      ["link", "link/b", "link/b/c.txt", "link/d.txt"]
+    );
+
+    // Links back to an ancestor are output as errors.
+
+    std::os::unix::fs::symlink("..", fs::path::join(&root, "a/up").as_ref()).unwrap();
+
+    let error =
+      |path: &str| format!("Failed to walk `{}/{}`. The symbolic link creates a loop.", root, path);
+
+    assert_eq!(
+      collect(&root, walk().follow_links(true)),
+      [
+        error("a/up"),
+        "link".into(),
+        "link/b".into(),
+        "link/b/c.txt".into(),
+        "link/d.txt".into(),
+        "link/up".into(),
+        error("link/up"),
+      ]
+    );
+  }
+}
@@ -6,14 +6,23 @@
 
 //! A file system watcher.
 
-use crate::fs;
+mod poll;
+
+use crate::env;
+use crate::fs::{self, GlobSet, NativePath};
+use crate::future::Poll;
 use crate::prelude::*;
+use crate::sync::blocking::Mutex;
 use crate::sync::channel;
 use crate::thread;
 use notify::Watcher as _;
 use std::io;
+use std::path::PathBuf;
 use std::sync::mpsc;
 
+/// The environment variable that selects a polling watcher and its interval.
+const POLL_VAR: &str = "INDIGO_FS_WATCH_POLL";
+
 /// One of the possible [`Watcher`] events.
 #[derive(Debug)]
 pub enum Event {
@@ -21,109 +30,437 @@ pub enum Event {
   Created(String),
   /// A file was modified.
   Modified(String),
+  /// The metadata of a file or directory, such as its permissions or
+  /// modification time, changed.
+  MetadataChanged(String),
   /// A file was removed.
   Removed(String),
   /// A file was renamed.
   Renamed { from: String, to: String },
+  /// An event occurred for a path with non-Unicode characters.
+  NonUnicode(NativePath),
+  /// Events may have been missed, so watched directories should be rescanned.
+  Rescan,
 }
 
 /// A file system watcher that can watch paths for events.
+///
+/// The watcher is a stream of events. Errors that occur while watching are
+/// output on the stream without ending it.
 pub struct Watcher {
-  inner: notify::RecommendedWatcher,
+  backend: Backend,
+  filter: Arc<Mutex<Filter>>,
+  events: channel::Receiver<Result<Event>>,
+}
+
+/// One of the possible backends of a [`Watcher`].
+enum Backend {
+  Notify(notify::RecommendedWatcher),
+  Poll(Arc<Mutex<poll::Poller>>),
+}
+
+/// Filters events by path.
+#[derive(Default)]
+struct Filter {
+  roots: Vec<String>,
+  globs: GlobSet,
 }
 
 /// Processes debounced events received from a `notify::Watcher` and sends the
-/// mapped events to the given channel.
+/// mapped events that pass the filter to the given channel.
 ///
-/// This function regularly blocks the current thread.
-async fn map_inner_events(
+/// This function blocks the current thread until the `notify::Watcher` is
+/// dropped or the channel is closed.
+fn map_inner_events(
   inner_events: mpsc::Receiver<notify::DebouncedEvent>,
-  events: channel::Sender<Event>,
-) -> Result {
+  filter: Arc<Mutex<Filter>>,
+  events: channel::Sender<Result<Event>>,
+) {
   trace!("Waiting for events…");
 
   for inner_event in inner_events {
     trace!("Received event `{:?}`.", inner_event);
 
-    match inner_event {
-      notify::DebouncedEvent::Create(path) => {
-        if let Some(path) = path.to_str() {
-          events.send(Event::Created(path.into())).await?;
-        }
-      }
+    let event = match inner_event {
+      notify::DebouncedEvent::Create(path) => Some(Event::new(path, Event::Created)),
+      notify::DebouncedEvent::Write(path) => Some(Event::new(path, Event::Modified)),
+      notify::DebouncedEvent::Chmod(path) => Some(Event::new(path, Event::MetadataChanged)),
+      notify::DebouncedEvent::Remove(path) => Some(Event::new(path, Event::Removed)),
+      notify::DebouncedEvent::Rename(from, to) => Some(Event::renamed(from, to)),
 
-      notify::DebouncedEvent::Write(path) => {
-        if let Some(path) = path.to_str() {
-          events.send(Event::Modified(path.into())).await?;
-        }
-      }
+      notify::DebouncedEvent::Rescan => Some(Event::Rescan),
 
-      notify::DebouncedEvent::Remove(path) => {
-        if let Some(path) = path.to_str() {
-          events.send(Event::Removed(path.into())).await?;
-        }
-      }
+      notify::DebouncedEvent::Error(err, Some(path)) => {
+        let message =
+          format!("Failed to watch {}", fmt::AsPath(&path.to_string_lossy()).describe());
 
-      notify::DebouncedEvent::Rename(from, to) => {
-        if let (Some(from), Some(to)) = (from.to_str(), to.to_str()) {
-          events.send(Event::Renamed { from: from.into(), to: to.into() }).await?;
+        if events.try_send(Err(fail::Error::join(message, err))).is_err() {
+          return;
         }
-      }
 
-      notify::DebouncedEvent::Error(err, Some(path)) => {
-        error!("(at `{}`) {}", path.display(), err);
+        continue;
       }
 
       notify::DebouncedEvent::Error(err, None) => {
-        error!("{}", err);
+        if events.try_send(Err(fail::Error::join("Failed to watch files", err))).is_err() {
+          return;
+        }
+
+        continue;
       }
 
-      _ => {}
+      notify::DebouncedEvent::NoticeWrite(_) | notify::DebouncedEvent::NoticeRemove(_) => None,
+    };
+
+    let event = match event {
+      Some(event) if filter.lock().allows(&event) => event,
+      _ => continue,
+    };
+
+    if events.try_send(Ok(event)).is_err() {
+      return;
+    }
+  }
+}
+
+impl Event {
+  /// Creates a new event for a path, or a [`Event::NonUnicode`] event if the
+  /// path contains non-Unicode characters.
+  fn new(path: PathBuf, event: impl FnOnce(String) -> Self) -> Self {
+    match path.into_os_string().into_string() {
+      Ok(path) => event(path),
+      Err(path) => Self::NonUnicode(path.into()),
     }
   }
 
-  Ok(())
+  /// Creates a new [`Event::Renamed`] event, or a [`Event::NonUnicode`] event
+  /// for the first path that contains non-Unicode characters.
+  fn renamed(from: PathBuf, to: PathBuf) -> Self {
+    let from = NativePath::from(from);
+
+    match from.into_string() {
+      Ok(from) => Self::new(to, |to| Self::Renamed { from, to }),
+      Err(from) => Self::NonUnicode(from),
+    }
+  }
 }
 
 impl Watcher {
-  /// Creates a new file system watcher that sends events to the given channel.
-  pub fn new(events_tx: channel::Sender<Event>) -> Result<Self> {
+  /// Creates a new file system watcher that waits for events to stop for
+  /// 100 milliseconds before outputting them.
+  ///
+  /// If the `INDIGO_FS_WATCH_POLL` environment variable is set to an interval
+  /// such as `1s`, a polling watcher with that interval is created instead.
+  pub fn new() -> Result<Self> {
+    Self::with_debounce(std::time::Duration::from_millis(100).into())
+  }
+
+  /// Creates a new file system watcher that waits for events to stop for the
+  /// given period of time before outputting them.
+  ///
+  /// Multiple events for the same path within the period are combined. If
+  /// the `INDIGO_FS_WATCH_POLL` environment variable is set to an interval
+  /// such as `1s`, a polling watcher with that interval is created instead.
+  pub fn with_debounce(period: Duration) -> Result<Self> {
+    if let Some(interval) = env::var_parsed(POLL_VAR)? {
+      return Ok(Self::polling(interval));
+    }
+
     let (inner_events_tx, inner_events) = mpsc::channel();
-    let inner = notify::Watcher::new(inner_events_tx, std::time::Duration::from_millis(100))?;
+    let (events_tx, events) = channel::unbounded();
+    let inner = notify::Watcher::new(inner_events_tx, period.to_std())?;
+    let filter: Arc<Mutex<Filter>> = default();
+    let thread_filter = filter.clone();
 
     thread::start_detached("indigo::fs::watch::Watcher", move || {
-      thread::block_on(map_inner_events(inner_events, events_tx))
+      map_inner_events(inner_events, thread_filter, events_tx)
     });
 
-    Ok(Self { inner })
+    Ok(Self { backend: Backend::Notify(inner), filter, events })
   }
 
-  /// Begins watching the given directory for events.
+  /// Creates a new file system watcher that scans watched directories for
+  /// changes at the given interval.
+  ///
+  /// Polling is slower than the default watcher, but it works on network
+  /// mounts and in containers where the default watcher may miss events.
+  /// Changes are detected by comparing the size, modification time, and
+  /// permissions of each file. On Unix, renames are detected by comparing
+  /// inode numbers.
+  pub fn polling(interval: Duration) -> Self {
+    let (events_tx, events) = channel::unbounded();
+    let poller: Arc<Mutex<poll::Poller>> = default();
+    let filter: Arc<Mutex<Filter>> = default();
+
+    poll::start(interval, Arc::downgrade(&poller), filter.clone(), events_tx);
+
+    Self { backend: Backend::Poll(poller), filter, events }
+  }
+
+  /// Returns `true` if this watcher scans for changes at an interval.
+  pub fn is_polling(&self) -> bool {
+    matches!(self.backend, Backend::Poll(_))
+  }
+
+  /// Only outputs events for paths that match the given glob set.
+  ///
+  /// Paths are matched relative to the watched directory that contains them.
+  /// Rescan events and errors are always output.
+  pub fn set_filter(&mut self, globs: GlobSet) {
+    self.filter.lock().globs = globs;
+  }
+
+  /// Begins watching the given directory and all of its subdirectories for
+  /// events.
   pub fn watch_dir<'a>(&mut self, path: impl fs::PathLike<'a>) -> Result {
-    let path = fs::path::join(
-      fs::path::resolved(path).map_err(fail::with!("Failed to resolve directory."))?,
-      "",
-    );
+    self.watch(path, notify::RecursiveMode::Recursive)
+  }
+
+  /// Begins watching the given directory for events, ignoring events in its
+  /// subdirectories.
+  pub fn watch_dir_non_recursive<'a>(&mut self, path: impl fs::PathLike<'a>) -> Result {
+    self.watch(path, notify::RecursiveMode::NonRecursive)
+  }
+
+  /// Stops watching the given directory for events.
+  pub fn unwatch_dir<'a>(&mut self, path: impl fs::PathLike<'a>) {
+    let path = match path.try_to_cow().ok().and_then(|path| fs::path::resolved(path).ok()) {
+      Some(path) => path,
+      None => return,
+    };
 
-    self.inner.watch(path.as_ref(), notify::RecursiveMode::Recursive).map_err(|err| match err {
+    match &mut self.backend {
+      Backend::Notify(inner) => {
+        let _ = inner.unwatch(fs::path::join(&path, "").as_ref());
+      }
+
+      Backend::Poll(poller) => poller.lock().unwatch(&path),
+    }
+
+    self.filter.lock().roots.retain(|root| *root != path);
+  }
+
+  /// Begins watching a directory with the given recursive mode.
+  fn watch<'a>(&mut self, path: impl fs::PathLike<'a>, mode: notify::RecursiveMode) -> Result {
+    let root = fs::path::resolved(path.try_to_cow()?)
+      .map_err(fail::with!("Failed to resolve directory."))?;
+    let path = fs::path::join(&root, "");
+
+    let result = match &mut self.backend {
+      Backend::Notify(inner) => inner.watch(path.as_ref(), mode),
+
+      Backend::Poll(poller) => poller
+        .lock()
+        .watch(&root, mode == notify::RecursiveMode::Recursive)
+        .map_err(notify::Error::Io),
+    };
+
+    result.map_err(|err| match err {
       notify::Error::Io(err) if err.kind() == io::ErrorKind::NotFound => {
-        fail::err!("Directory does not exist.")
+        fail::err!("Failed to watch {}. Directory does not exist.", fmt::AsPath(&root).describe())
+      }
+
+      notify::Error::PathNotFound => {
+        fail::err!("Failed to watch {}. Directory does not exist.", fmt::AsPath(&root).describe())
+      }
+
+      err => {
+        fail::Error::join(format_args!("Failed to watch {}", fmt::AsPath(&root).describe()), err)
       }
-      notify::Error::Io(err) => fail::err!("IO error: {}.", err),
-      notify::Error::PathNotFound => fail::err!("Directory does not exist."),
-      _ => unreachable!(),
     })?;
 
     trace!("Watching `{}`.", path);
 
+    let mut filter = self.filter.lock();
+
+    if !filter.roots.iter().any(|r| *r == root) {
+      filter.roots.push(root.into_owned());
+    }
+
     Ok(())
   }
+}
 
-  /// Stops watching the given directory for events.
-  pub fn unwatch_dir<'a>(&mut self, path: impl fs::PathLike<'a>) {
-    let _ = match fs::path::resolved(path) {
-      Ok(path) => self.inner.unwatch(fs::path::join(path, "").as_ref()),
-      Err(_) => return,
+impl Filter {
+  /// Returns `true` if the filter allows the given event.
+  fn allows(&self, event: &Event) -> bool {
+    if self.globs.is_empty() {
+      return true;
+    }
+
+    match event {
+      Event::Created(path)
+      | Event::Modified(path)
+      | Event::MetadataChanged(path)
+      | Event::Removed(path) => self.is_match(path),
+
+      Event::Renamed { from, to } => self.is_match(from) || self.is_match(to),
+      Event::NonUnicode(path) => self.is_match(&path.to_string_lossy()),
+      Event::Rescan => true,
+    }
+  }
+
+  /// Returns `true` if the given absolute path matches the glob set relative
+  /// to the deepest watched directory that contains it.
+  fn is_match(&self, path: &str) -> bool {
+    let root = self
+      .roots
+      .iter()
+      .filter(|root| fs::path::starts_with(path, root))
+      .max_by_key(|root| root.len());
+
+    let relative = match root {
+      Some(root) => path[root.len()..].trim_start_matches(fs::path::is_separator),
+      None => path,
     };
+
+    self.globs.is_match(relative)
+  }
+}
+
+// Implement `Stream` to output events.
+
+impl Stream for Watcher {
+  type Item = Result<Event>;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut future::Context) -> Poll<Option<Self::Item>> {
+    Pin::new(&mut self.events).poll_next(cx)
+  }
+}
+
+// Unit tests.
+
+#[cfg(all(test, feature = "runtime"))]
+mod tests {
+  use super::*;
+  use crate::fs::TempDir;
+
+  /// Waits for events until none are received for half a second and describes
+  /// each with its path relative to the given root.
+  async fn events(watcher: &mut Watcher, root: &str) -> Vec<String> {
+    let relative = |path: &str| path[root.len()..].trim_start_matches('/').to_string();
+    let mut events = Vec::new();
+
+    loop {
+      let idle = async {
+        future::sleep(Duration::secs_f64(0.5)).await;
+        None
+      };
+
+      let event = match future::race(watcher.next(), idle).biased().await {
+        Some(event) => event,
+        None => return events,
+      };
+
+      events.push(match event {
+        Ok(Event::Created(path)) => format!("created {}", relative(&path)),
+        Ok(Event::Modified(path)) => format!("modified {}", relative(&path)),
+        Ok(Event::Removed(path)) => format!("removed {}", relative(&path)),
+        Ok(event) => format!("{:?}", event),
+        Err(err) => err.to_string(),
+      });
+    }
+  }
+
+  /// Creates a temporary directory and returns it with its resolved path.
+  fn temp_dir() -> (TempDir, String) {
+    let temp = TempDir::new();
+    let root = fs::path::resolved(temp.join("")).unwrap().into_owned();
+
+    (temp, root)
+  }
+
+  #[test]
+  fn test_events() {
+    let (_temp, root) = temp_dir();
+    let file = fs::path::join(&root, "a.txt").into_owned();
+    let debounced = Watcher::with_debounce(Duration::secs_f64(0.05)).unwrap();
+    let polling = Watcher::polling(Duration::secs_f64(0.05));
+
+    thread::block_on(async {
+      for mut watcher in [debounced, polling] {
+        watcher.watch_dir(&root).unwrap();
+
+        fs::write(&file, "a").await.unwrap();
+        assert_eq!(events(&mut watcher, &root).await, ["created a.txt"]);
+
+        fs::write(&file, "modified").await.unwrap();
+        assert_eq!(events(&mut watcher, &root).await, ["modified a.txt"]);
+
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(events(&mut watcher, &root).await, ["removed a.txt"]);
+
+        watcher.unwatch_dir(&root);
+
+        fs::write(&file, "a").await.unwrap();
+        assert!(events(&mut watcher, &root).await.is_empty());
+
+        std::fs::remove_file(&file).unwrap();
+      }
+    });
+  }
+
+  #[test]
+  fn test_debounce() {
+    let (_temp, root) = temp_dir();
+    let file = fs::path::join(&root, "a.txt").into_owned();
+    let mut watcher = Watcher::with_debounce(Duration::secs_f64(0.2)).unwrap();
+
+    watcher.watch_dir(&root).unwrap();
+
+    thread::block_on(async {
+      for contents in &["a", "ab", "abc"] {
+        fs::write(&file, *contents).await.unwrap();
+      }
+
+      assert_eq!(events(&mut watcher, &root).await, ["created a.txt"]);
+    });
+  }
+
+  #[test]
+  fn test_filter() {
+    let (_temp, root) = temp_dir();
+    let mut watcher = Watcher::with_debounce(Duration::secs_f64(0.05)).unwrap();
+
+    watcher.set_filter(GlobSet::from_patterns(vec!["**/*.txt", "!b/**"]).unwrap());
+    watcher.watch_dir(&root).unwrap();
+
+    thread::block_on(async {
+      fs::create_dir_all(fs::path::join(&root, "b")).await.unwrap();
+
+      for file in &["a.txt", "a.log", "b/c.txt"] {
+        fs::write(fs::path::join(&root, *file), "").await.unwrap();
+      }
+
+      assert_eq!(events(&mut watcher, &root).await, ["created a.txt"]);
+    });
+  }
+
+  #[test]
+  fn test_non_recursive() {
+    let (_temp, root) = temp_dir();
+    let mut watcher = Watcher::with_debounce(Duration::secs_f64(0.05)).unwrap();
+
+    std::fs::create_dir(fs::path::join(&root, "b").as_ref()).unwrap();
+    watcher.watch_dir_non_recursive(&root).unwrap();
+
+    thread::block_on(async {
+      fs::write(fs::path::join(&root, "b/c.txt"), "").await.unwrap();
+      fs::write(fs::path::join(&root, "a.txt"), "").await.unwrap();
+
+      assert_eq!(events(&mut watcher, &root).await, ["created a.txt"]);
+    });
+  }
+
+  #[test]
+  fn test_missing_dir() {
+    let (_temp, root) = temp_dir();
+    let missing = fs::path::join(&root, "missing").into_owned();
+    let mut watcher = Watcher::with_debounce(Duration::secs_f64(0.05)).unwrap();
+
+    assert_eq!(
+      watcher.watch_dir(&missing).unwrap_err().to_string(),
+      format!("Failed to watch `{}`. Directory does not exist.", missing)
+    );
   }
 }
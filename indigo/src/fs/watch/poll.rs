@@ -0,0 +1,420 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{Event, Filter};
+use crate::fs::ops::failed;
+use crate::prelude::*;
+use crate::sync::blocking::Mutex;
+use crate::sync::channel;
+use crate::thread;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Weak;
+use std::time::SystemTime;
+
+/// Watched directories and their last known state, shared by a polling
+/// [`Watcher`](super::Watcher) and its thread.
+#[derive(Default)]
+pub(super) struct Poller {
+  watched: Vec<(String, bool)>,
+  snapshot: BTreeMap<PathBuf, FileState>,
+  failed: BTreeSet<PathBuf>,
+}
+
+/// The state of a file or directory at the time of a scan.
+#[derive(PartialEq)]
+struct FileState {
+  is_dir: bool,
+  len: u64,
+  modified: Option<SystemTime>,
+  permissions: std::fs::Permissions,
+  id: Option<(u64, u64)>,
+}
+
+/// Starts a thread that scans the directories of a poller at the given
+/// interval and sends events for changes to the given channel.
+///
+/// The thread stops when the poller is dropped or the channel is closed.
+pub(super) fn start(
+  interval: Duration,
+  poller: Weak<Mutex<Poller>>,
+  filter: Arc<Mutex<Filter>>,
+  events: channel::Sender<Result<Event>>,
+) {
+  thread::start_detached("indigo::fs::watch::Watcher", move || loop {
+    std::thread::sleep(interval.to_std());
+
+    let poller = match poller.upgrade() {
+      Some(poller) => poller,
+      None => return,
+    };
+
+    let changes = poller.lock().poll();
+
+    for event in changes {
+      if matches!(&event, Ok(event) if !filter.lock().allows(event)) {
+        continue;
+      }
+
+      if events.try_send(event).is_err() {
+        return;
+      }
+    }
+  });
+}
+
+impl Poller {
+  /// Begins watching a directory, recording its current state without
+  /// outputting events.
+  pub fn watch(&mut self, root: &str, recursive: bool) -> io::Result<()> {
+    if !std::fs::metadata(root)?.is_dir() {
+      return Err(io::ErrorKind::NotFound.into());
+    }
+
+    match self.watched.iter_mut().find(|(path, _)| path == root) {
+      Some(watched) => watched.1 = recursive,
+      None => self.watched.push((root.into(), recursive)),
+    }
+
+    scan(root.as_ref(), recursive, &mut self.snapshot, &mut Vec::new());
+
+    Ok(())
+  }
+
+  /// Stops watching a directory.
+  pub fn unwatch(&mut self, root: &str) {
+    self.watched.retain(|(path, _)| path != root);
+
+    let mut snapshot = BTreeMap::new();
+
+    for (root, recursive) in &self.watched {
+      scan(root.as_ref(), *recursive, &mut snapshot, &mut Vec::new());
+    }
+
+    self.snapshot = snapshot;
+  }
+
+  /// Scans all watched directories and returns events for the changes since
+  /// the last scan.
+  ///
+  /// Errors are only returned for paths that could be read in the last scan,
+  /// so that a path that cannot be read is not reported at every interval.
+  fn poll(&mut self) -> Vec<Result<Event>> {
+    let mut next = BTreeMap::new();
+    let mut errors = Vec::new();
+
+    for (root, recursive) in &self.watched {
+      scan(root.as_ref(), *recursive, &mut next, &mut errors);
+    }
+
+    let failed = errors.iter().map(|(path, _)| path.clone()).collect();
+    let prev_failed = mem::replace(&mut self.failed, failed);
+
+    let mut events: Vec<_> = errors
+      .into_iter()
+      .filter(|(path, _)| !prev_failed.contains(path))
+      .map(|(_, err)| Err(err))
+      .collect();
+
+    let prev = mem::replace(&mut self.snapshot, next);
+    let next = &self.snapshot;
+    let mut created = Vec::new();
+    let mut removed = Vec::new();
+
+    for (path, state) in next {
+      match prev.get(path) {
+        None => created.push(path),
+        Some(prev) if prev == state => continue,
+
+        Some(prev) => match state.is_dir {
+          false
+            if prev.len != state.len || prev.modified != state.modified || prev.id != state.id =>
+          {
+            events.push(Ok(Event::new(path.clone(), Event::Modified)))
+          }
+
+          _ if prev.permissions != state.permissions => {
+            events.push(Ok(Event::new(path.clone(), Event::MetadataChanged)))
+          }
+
+          _ => continue,
+        },
+      }
+    }
+
+    for path in prev.keys() {
+      if !next.contains_key(path) {
+        removed.push(path);
+      }
+    }
+
+    // Pair removed and created paths with the same file ID as renames.
+
+    let mut created_ids: HashMap<_, _> =
+      created.iter().filter_map(|path| Some((next[*path].id?, *path))).collect();
+
+    let mut renames = BTreeMap::new();
+
+    removed.retain(|from| {
+      let to = match prev[*from].id.and_then(|id| created_ids.remove(&id)) {
+        Some(to) => to,
+        None => return true,
+      };
+
+      renames.insert(*from, to);
+
+      false
+    });
+
+    created.retain(|path| !renames.values().any(|to| to == path));
+
+    // Only output renames of the top-most renamed directories, not their
+    // contents.
+
+    for (from, to) in &renames {
+      let parent_renamed = match (from.parent(), to.parent()) {
+        (Some(from), Some(to)) => renames.get(&from.to_path_buf()).map(|p| p.as_path()) == Some(to),
+        _ => false,
+      };
+
+      if !parent_renamed {
+        events.push(Ok(Event::renamed((*from).clone(), (*to).clone())));
+      }
+    }
+
+    events.extend(created.into_iter().map(|path| Ok(Event::new(path.clone(), Event::Created))));
+    events.extend(removed.into_iter().map(|path| Ok(Event::new(path.clone(), Event::Removed))));
+    events
+  }
+}
+
+/// Records the state of a directory and its contents in a snapshot.
+///
+/// Errors reading the directory or its subdirectories are added to the given
+/// list. Entries that are removed during the scan are skipped.
+fn scan(
+  root: &Path,
+  recursive: bool,
+  snapshot: &mut BTreeMap<PathBuf, FileState>,
+  errors: &mut Vec<(PathBuf, fail::Error)>,
+) {
+  match std::fs::symlink_metadata(root) {
+    Ok(metadata) => scan_entry(root.into(), metadata, recursive, snapshot, errors),
+
+    Err(err) => {
+      let message = format!("Failed to watch {}", fmt::AsPath(&root.to_string_lossy()).describe());
+
+      errors.push((root.into(), fail::Error::join(message, err)));
+    }
+  }
+}
+
+/// Records the state of an entry in a snapshot, reading its contents if it is
+/// a directory.
+fn scan_entry(
+  path: PathBuf,
+  metadata: std::fs::Metadata,
+  recursive: bool,
+  snapshot: &mut BTreeMap<PathBuf, FileState>,
+  errors: &mut Vec<(PathBuf, fail::Error)>,
+) {
+  snapshot.insert(path.clone(), FileState::new(&metadata));
+
+  if !metadata.is_dir() {
+    return;
+  }
+
+  let read_failed = |path: &Path, err| failed("read directory", &path.to_string_lossy())(err);
+
+  let entries = match std::fs::read_dir(&path) {
+    Ok(entries) => entries,
+    Err(err) if err.kind() == io::ErrorKind::NotFound => return,
+    Err(err) => {
+      errors.push((path.clone(), read_failed(&path, err)));
+      return;
+    }
+  };
+
+  for entry in entries {
+    let entry = match entry {
+      Ok(entry) => entry,
+      Err(err) => {
+        errors.push((path.clone(), read_failed(&path, err)));
+        return;
+      }
+    };
+
+    let metadata = match entry.metadata() {
+      Ok(metadata) => metadata,
+      Err(_) => continue,
+    };
+
+    match recursive {
+      true => scan_entry(entry.path(), metadata, true, snapshot, errors),
+
+      false => {
+        snapshot.insert(entry.path(), FileState::new(&metadata));
+      }
+    }
+  }
+}
+
+impl FileState {
+  /// Creates a new file state from the metadata of a file or directory.
+  fn new(metadata: &std::fs::Metadata) -> Self {
+    #[cfg(unix)]
+    let id = {
+      use std::os::unix::fs::MetadataExt as _;
+
+      Some((metadata.dev(), metadata.ino()))
+    };
+
+    #[cfg(not(unix))]
+    let id = None;
+
+    Self {
+      is_dir: metadata.is_dir(),
+      len: metadata.len(),
+      modified: metadata.modified().ok(),
+      permissions: metadata.permissions(),
+      id,
+    }
+  }
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fs::TempDir;
+
+  /// Polls for changes and describes each event with paths relative to the
+  /// given root.
+  fn poll(poller: &mut Poller, root: &str) -> Vec<String> {
+    let relative = |path: &str| path[root.len()..].trim_start_matches('/').to_string();
+
+    poller
+      .poll()
+      .into_iter()
+      .map(|event| match event {
+        Ok(Event::Created(path)) => format!("created {}", relative(&path)),
+        Ok(Event::Modified(path)) => format!("modified {}", relative(&path)),
+        Ok(Event::Removed(path)) => format!("removed {}", relative(&path)),
+
+        Ok(Event::Renamed { from, to }) => {
+          format!("renamed {} to {}", relative(&from), relative(&to))
+        }
+
+        Ok(event) => format!("{:?}", event),
+        Err(err) => err.to_string(),
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_poll() {
+    let temp = TempDir::new();
+    let root = temp.join("root");
+    let mut poller = Poller::default();
+
+    std::fs::create_dir_all(temp.join("root/a")).unwrap();
+    poller.watch(&root, true).unwrap();
+
+    assert!(poll(&mut poller, &root).is_empty());
+
+    std::fs::write(temp.join("root/a/b.txt"), "b").unwrap();
+
+    assert_eq!(poll(&mut poller, &root), ["created a/b.txt"]);
+
+    std::fs::write(temp.join("root/a/b.txt"), "modified").unwrap();
+
+    assert_eq!(poll(&mut poller, &root), ["modified a/b.txt"]);
+
+    std::fs::remove_file(temp.join("root/a/b.txt")).unwrap();
+
+    assert_eq!(poll(&mut poller, &root), ["removed a/b.txt"]);
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_renames() {
+    let temp = TempDir::new();
+    let root = temp.join("root");
+    let mut poller = Poller::default();
+
+    std::fs::create_dir_all(temp.join("root/a/b")).unwrap();
+    std::fs::write(temp.join("root/a/b/c.txt"), "c").unwrap();
+    std::fs::write(temp.join("root/d.txt"), "d").unwrap();
+    poller.watch(&root, true).unwrap();
+
+    std::fs::rename(temp.join("root/d.txt"), temp.join("root/a/e.txt")).unwrap();
+
+    assert_eq!(poll(&mut poller, &root), ["renamed d.txt to a/e.txt"]);
+
+    // Only the top-most renamed directory is output.
+
+    std::fs::rename(temp.join("root/a"), temp.join("root/f")).unwrap();
+
+    assert_eq!(poll(&mut poller, &root), ["renamed a to f"]);
+
+    // Paths without a matching file ID are not paired.
+
+    std::fs::write(temp.join("root/g.txt"), "g").unwrap();
+    std::fs::remove_file(temp.join("root/f/e.txt")).unwrap();
+
+    assert_eq!(poll(&mut poller, &root), ["created g.txt", "removed f/e.txt"]);
+  }
+
+  #[test]
+  fn test_non_recursive() {
+    let temp = TempDir::new();
+    let root = temp.join("root");
+    let mut poller = Poller::default();
+
+    std::fs::create_dir_all(temp.join("root/a")).unwrap();
+    poller.watch(&root, false).unwrap();
+
+    std::fs::write(temp.join("root/a/b.txt"), "b").unwrap();
+    std::fs::write(temp.join("root/c.txt"), "c").unwrap();
+
+    assert_eq!(poll(&mut poller, &root), ["created c.txt"]);
+
+    poller.unwatch(&root);
+    std::fs::write(temp.join("root/d.txt"), "d").unwrap();
+
+    assert!(poll(&mut poller, &root).is_empty());
+  }
+
+  #[test]
+  fn test_errors() {
+    let temp = TempDir::new();
+    let root = temp.join("root");
+    let mut poller = Poller::default();
+
+    std::fs::create_dir_all(temp.join("root/a")).unwrap();
+    poller.watch(&root, true).unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(
+      poll(&mut poller, &root),
+      [
+        format!("Failed to watch `{}`. No such file or directory (os error 2).", root),
+        "removed ".into(),
+        "removed a".into(),
+      ]
+    );
+
+    // Errors are only output when a path first fails.
+
+    assert!(poll(&mut poller, &root).is_empty());
+
+    std::fs::create_dir_all(&root).unwrap();
+
+    assert_eq!(poll(&mut poller, &root), ["created "]);
+  }
+}
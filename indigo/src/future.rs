@@ -10,8 +10,11 @@ mod buffered;
 mod join;
 mod race;
 
-pub use self::join::{join, Join};
-pub use self::race::{race, Race};
+pub use self::join::{join, join_all, try_join, try_join_all, Join, JoinAll, TryJoinAll};
+pub use self::race::{race, race_all, select, FairRace, Race, RaceAll};
+
+#[doc(hidden)]
+pub use self::join::buffered;
 
 pub use blocking::unblock;
 pub use futures_lite::future::FutureExt;
@@ -20,8 +23,9 @@ pub use futures_lite::future::{poll_fn, PollFn};
 pub use std::future::Future;
 pub use std::task::{Context, Poll};
 
-use self::buffered::Buffered;
+pub(crate) use self::buffered::Buffered;
 use crate::prelude::*;
+use crate::sync::CancellationToken;
 
 /// An error returned from [`catch_unwind()`] when the future panics.
 #[derive(Debug, Display, Error)]
@@ -65,7 +69,130 @@ pub async fn sleep(duration: Duration) {
   async_io::Timer::new(duration.to_std()).await;
 }
 
+/// Waits for a future to complete unless the given token is cancelled first.
+///
+/// If the token is cancelled first, the future is dropped and this function
+/// returns `None`.
+pub async fn until_cancelled<F: Future>(token: &CancellationToken, future: F) -> Option<F::Output> {
+  if token.is_cancelled() {
+    return None;
+  }
+
+  race(async { Some(future.await) }, async {
+    token.cancelled().await;
+    None
+  })
+  .await
+}
+
 /// Yields once to other running futures or tasks.
 pub async fn yield_now() {
   futures_lite::future::yield_now().await;
 }
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::thread;
+  use std::collections::HashSet;
+
+  #[test]
+  fn test_join_all() {
+    let outputs = thread::block_on(join_all((0..3).map(|i| async move { i * 2 })));
+
+    assert_eq!(outputs, [0, 2, 4]);
+  }
+
+  #[test]
+  fn test_try_join() {
+    thread::block_on(async {
+      let result =
+        future::try_join!(async { Ok::<_, &str>(1) }, async { Ok("two") }, async { Ok(3.0) });
+
+      assert_eq!(result, Ok((1, "two", 3.0)));
+
+      // The error is output without waiting for the pending future.
+
+      let result =
+        future::try_join!(future::pending::<Result<(), &str>>(), async { Err("failed") });
+
+      assert_eq!(result, Err("failed"));
+
+      let result = try_join_all(vec![
+        Box::pin(future::pending()) as Pin<Box<dyn Future<Output = Result<(), &str>>>>,
+        Box::pin(async { Err("failed") }),
+      ])
+      .await;
+
+      assert_eq!(result, Err("failed"));
+    });
+  }
+
+  #[test]
+  fn test_race_order() {
+    thread::block_on(async {
+      let mut outputs = HashSet::new();
+
+      for _ in 0..100 {
+        outputs.insert(race(async { 1 }, async { 2 }).await);
+        outputs.insert(future::race!(async { 3 }, async { 4 }, async { 5 }));
+        outputs.insert(race_all((6..=7).map(|i| async move { i })).await.1);
+      }
+
+      assert_eq!(outputs, (1..=7).collect());
+
+      // Biased races always use the first future that is ready.
+
+      for _ in 0..100 {
+        assert_eq!(race(async { 1 }, async { 2 }).biased().await, 1);
+        assert_eq!(Race(async { 1 }, async { 2 }).await, 1);
+        assert_eq!(future::race!(biased; async { 3 }, async { 4 }, async { 5 }), 3);
+        assert_eq!(race_all((6..=7).map(|i| async move { i })).biased().await, (0, 6));
+      }
+    });
+  }
+
+  #[test]
+  fn test_race_all() {
+    thread::block_on(async {
+      let futures = (0..3).map(|i| async move {
+        if i < 2 {
+          future::pending::<()>().await;
+        }
+
+        i * 10
+      });
+
+      assert_eq!(race_all(futures).await, (2, 20));
+
+      // An empty collection never completes.
+
+      let empty = race_all(Vec::<Pending<()>>::new());
+      let output = race(async { empty.await.1 }, async {}).biased().await;
+
+      assert_eq!(output, ());
+    });
+  }
+
+  #[test]
+  fn test_select() {
+    thread::block_on(async {
+      let output = future::select! {
+        (a, b) = future::pending::<(i32, i32)>() => a + b,
+        (x, _) = async { (1, "ignored") } => x + 10,
+      };
+
+      assert_eq!(output, 11);
+
+      let output = future::select! {
+        biased;
+        x = async { 1 } => x,
+        y = async { 2 } => y * 10,
+      };
+
+      assert_eq!(output, 1);
+    });
+  }
+}
@@ -19,6 +19,11 @@ impl<F: Future> Buffered<F> {
     Buffered::Pending(future)
   }
 
+  /// Returns `true` if the output value of the future is available.
+  pub fn is_ready(&self) -> bool {
+    matches!(self, Self::Ready(_))
+  }
+
   /// Returns the output value of the future.
   ///
   /// If the output value is not available, this function will panic.
@@ -40,8 +45,46 @@ impl<F: Future> Buffered<F> {
 
     taken.into_output()
   }
+
+  /// Removes and returns the output value of a pinned future.
+  ///
+  /// If the output value is not available, this function will panic.
+  pub fn take_output_pinned(self: Pin<&mut Self>) -> F::Output {
+    assert!(self.is_ready(), "The output is not available.");
+
+    // The future is no longer pinned once it has completed.
+
+    unsafe { self.get_unchecked_mut() }.take_output()
+  }
+
+  /// Polls a future that outputs a `Result`, removing and returning the error
+  /// value if it fails.
+  ///
+  /// If the future succeeds, its output value remains available.
+  pub fn poll_try<T, E>(mut self: Pin<&mut Self>, cx: &mut future::Context) -> Poll<Result<(), E>>
+  where
+    F: Future<Output = Result<T, E>>,
+  {
+    if Pin::as_mut(&mut self).poll(cx).is_pending() {
+      return Poll::Pending;
+    }
+
+    match &*self {
+      Self::Ready(Err(_)) => match self.take_output_pinned() {
+        Err(err) => Poll::Ready(Err(err)),
+        Ok(_) => unreachable!(),
+      },
+
+      _ => Poll::Ready(Ok(())),
+    }
+  }
 }
 
+// Implement `Unpin` if the future is `Unpin`. The output value is never
+// pinned.
+
+impl<F: Future + Unpin> Unpin for Buffered<F> {}
+
 // Implement polling.
 
 impl<F: Future> Future for Buffered<F> {
@@ -4,18 +4,55 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-pub use indigo_proc_macros::future_join as join;
+pub use indigo_proc_macros::{future_join as join, future_try_join as try_join};
 
 use super::*;
 
 /// A future that waits for both of two futures to complete.
 pub struct Join<A: Future, B: Future>(Buffered<A>, Buffered<B>);
 
+/// A future that waits for all futures in a collection to complete.
+pub struct JoinAll<F: Future> {
+  futures: Vec<Buffered<Pin<Box<F>>>>,
+}
+
+/// A future that waits for all futures in a collection to succeed or for one
+/// of them to fail.
+pub struct TryJoinAll<F: Future> {
+  futures: Vec<Buffered<Pin<Box<F>>>>,
+}
+
 /// Returns a future that waits for both of two futures to complete.
 pub fn join<A: Future, B: Future>(a: A, b: B) -> Join<A, B> {
   Join(Buffered::new(a), Buffered::new(b))
 }
 
+/// Returns a future that waits for all futures in a collection to complete
+/// and outputs their results in the original order.
+pub fn join_all<F: Future>(futures: impl IntoIterator<Item = F>) -> JoinAll<F> {
+  JoinAll { futures: futures.into_iter().map(|f| Buffered::new(Box::pin(f))).collect() }
+}
+
+/// Returns a future that waits for all futures in a collection to succeed and
+/// outputs their results in the original order.
+///
+/// If any future fails, the others are dropped and its error is output
+/// immediately.
+pub fn try_join_all<F, T, E>(futures: impl IntoIterator<Item = F>) -> TryJoinAll<F>
+where
+  F: Future<Output = Result<T, E>>,
+{
+  TryJoinAll { futures: futures.into_iter().map(|f| Buffered::new(Box::pin(f))).collect() }
+}
+
+#[doc(hidden)]
+/// Returns a new buffered future.
+///
+/// This function is used to support the [`join!`] and [`try_join!`] macros.
+pub fn buffered<F: Future>(future: F) -> Buffered<F> {
+  Buffered::new(future)
+}
+
 // Implement Future for Join.
 
 impl<A: Future, B: Future> Future for Join<A, B> {
@@ -43,3 +80,59 @@ impl<A: Future, B: Future> Future for Join<A, B> {
     Poll::Pending
   }
 }
+
+// Implement Future for JoinAll and TryJoinAll.
+
+impl<F: Future> Future for JoinAll<F> {
+  type Output = Vec<F::Output>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut future::Context) -> Poll<Self::Output> {
+    let this = self.get_mut();
+    let mut ready = true;
+
+    for future in &mut this.futures {
+      if Pin::new(future).poll(cx).is_pending() {
+        ready = false;
+      }
+    }
+
+    if ready {
+      return Poll::Ready(this.futures.drain(..).map(Buffered::into_output).collect());
+    }
+
+    Poll::Pending
+  }
+}
+
+impl<F, T, E> Future for TryJoinAll<F>
+where
+  F: Future<Output = Result<T, E>>,
+{
+  type Output = Result<Vec<T>, E>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut future::Context) -> Poll<Self::Output> {
+    let this = self.get_mut();
+    let mut ready = true;
+
+    for i in 0..this.futures.len() {
+      match Pin::new(&mut this.futures[i]).poll_try(cx) {
+        Poll::Ready(Err(err)) => {
+          this.futures.clear();
+
+          return Poll::Ready(Err(err));
+        }
+
+        Poll::Ready(Ok(())) => {}
+        Poll::Pending => ready = false,
+      }
+    }
+
+    if ready {
+      let outputs = this.futures.drain(..).map(Buffered::into_output);
+
+      return Poll::Ready(outputs.collect());
+    }
+
+    Poll::Pending
+  }
+}
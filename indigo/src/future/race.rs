@@ -4,16 +4,87 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-pub use indigo_proc_macros::future_race as race;
+pub use indigo_proc_macros::{future_race as race, future_select as select};
 
 use super::*;
+use crate::random::Rng;
 
-/// A future that waits for one of two futures to complete.
+/// A future that waits for one of two futures to complete, always polling the
+/// first future first.
+///
+/// If both futures are ready, the output of the first is used.
 pub struct Race<A, B>(pub A, pub B);
 
+/// A future that waits for one of two futures to complete, returned by
+/// [`race()`].
+///
+/// The futures are polled in random order each time so that neither can
+/// starve the other. Use [`FairRace::biased()`] to always poll the first
+/// future first.
+pub struct FairRace<A, B> {
+  race: Race<A, B>,
+  rng: Rng,
+}
+
+/// A future that waits for one future in a collection to complete.
+///
+/// By default, polling starts at a random future each time so that none of
+/// them can starve the others. Use [`RaceAll::biased()`] to always poll the
+/// futures in order.
+pub struct RaceAll<F> {
+  futures: Vec<Pin<Box<F>>>,
+  order: Order,
+}
+
+/// The order in which a race polls its futures.
+enum Order {
+  /// Always poll the futures in order.
+  Biased,
+  /// Start polling at a random future each time.
+  Random(Rng),
+}
+
 /// Returns a future that waits for one of two futures to complete.
-pub fn race<A: Future, B: Future>(a: A, b: B) -> Race<A, B> {
-  Race(a, b)
+pub fn race<A: Future, B: Future>(a: A, b: B) -> FairRace<A, B> {
+  FairRace { race: Race(a, b), rng: Rng::new() }
+}
+
+/// Returns a future that waits for one future in a collection to complete and
+/// outputs its index and result.
+///
+/// The other futures are dropped. If the collection is empty, the returned
+/// future never completes.
+pub fn race_all<F: Future>(futures: impl IntoIterator<Item = F>) -> RaceAll<F> {
+  RaceAll { futures: futures.into_iter().map(Box::pin).collect(), order: Order::Random(Rng::new()) }
+}
+
+impl<A, B> FairRace<A, B> {
+  /// Returns a [`Race`] that always polls the first future first.
+  ///
+  /// If both futures are ready, the output of the first is used.
+  pub fn biased(self) -> Race<A, B> {
+    self.race
+  }
+}
+
+impl<F> RaceAll<F> {
+  /// Always polls the futures in order.
+  ///
+  /// If more than one future is ready, the output of the first is used.
+  pub fn biased(mut self) -> Self {
+    self.order = Order::Biased;
+    self
+  }
+}
+
+impl Order {
+  /// Returns the index of the first of `len` futures to poll.
+  fn start(&mut self, len: usize) -> usize {
+    match self {
+      Self::Random(rng) if len > 1 => rng.random::<usize>() % len,
+      _ => 0,
+    }
+  }
 }
 
 // Implement Future for Race.
@@ -41,3 +112,55 @@ where
     Poll::Pending
   }
 }
+
+// Implement Future for FairRace.
+
+impl<O, A, B> Future for FairRace<A, B>
+where
+  A: Future<Output = O>,
+  B: Future<Output = O>,
+{
+  type Output = O;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut future::Context) -> Poll<Self::Output> {
+    unsafe {
+      let this = self.get_unchecked_mut();
+
+      if !this.rng.random::<bool>() {
+        return Pin::new_unchecked(&mut this.race).poll(cx);
+      }
+
+      if let Poll::Ready(output) = Pin::new_unchecked(&mut this.race.1).poll(cx) {
+        return Poll::Ready(output);
+      }
+
+      if let Poll::Ready(output) = Pin::new_unchecked(&mut this.race.0).poll(cx) {
+        return Poll::Ready(output);
+      }
+    }
+
+    Poll::Pending
+  }
+}
+
+// Implement Future for RaceAll.
+
+impl<F: Future> Future for RaceAll<F> {
+  type Output = (usize, F::Output);
+
+  fn poll(self: Pin<&mut Self>, cx: &mut future::Context) -> Poll<Self::Output> {
+    let this = self.get_mut();
+    let len = this.futures.len();
+    let start = this.order.start(len);
+
+    for i in (start..len).chain(0..start) {
+      if let Poll::Ready(output) = this.futures[i].as_mut().poll(cx) {
+        this.futures.clear();
+
+        return Poll::Ready((i, output));
+      }
+    }
+
+    Poll::Pending
+  }
+}
@@ -0,0 +1,174 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A minimal HTTP/1.1 server and client.
+//!
+//! This module is intended for small internal services such as health checks,
+//! webhooks, and admin endpoints. It supports plain `http://` connections
+//! only; request and response bodies are always read fully into memory.
+//!
+//! A [`Server`] dispatches requests to the handlers of a [`Router`]. Handlers
+//! return `Result<Response, E>` where `E` is either an [`Error`] with a specific
+//! status or a [`fail::Error`](crate::fail::Error), which is reported as
+//! `500 Internal Server Error`. Error responses have a JSON body such as
+//! `{"error":"Not found."}`.
+
+mod client;
+mod error;
+mod headers;
+mod method;
+mod request;
+mod response;
+mod router;
+mod server;
+mod status;
+mod wire;
+
+pub use self::client::Client;
+pub use self::error::Error;
+pub use self::headers::Headers;
+pub use self::method::Method;
+pub use self::request::Request;
+pub use self::response::Response;
+pub use self::router::Router;
+pub use self::server::Server;
+pub use self::status::Status;
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::prelude::*;
+  use crate::runtime::{executor, task};
+  use crate::sync::CancellationToken;
+
+  /// Runs a test server with the given router while running the given client
+  /// future on the runtime executor.
+  fn with_server<F: Future>(router: Router, client: impl FnOnce(String) -> F) -> F::Output {
+    executor().run(async {
+      let token = CancellationToken::new();
+      let server = Server::bind("127.0.0.1:0", router).await.unwrap().shutdown_on(token.clone());
+      let url = format!("http://{}", server.local_addr().unwrap());
+      let server = task::start(server.run());
+
+      let output = client(url).await;
+
+      token.cancel();
+      server.await.unwrap();
+
+      output
+    })
+  }
+
+  fn router() -> Router {
+    Router::new()
+      .get("/hello/:name", |req: Request| async move {
+        Ok::<_, Error>(Response::text(format!("Hello, {}!", req.param("name").unwrap())))
+      })
+      .post("/sum", |req: Request| async move {
+        let numbers: Vec<i64> = req.json()?;
+
+        Response::json(&numbers.iter().sum::<i64>())
+      })
+      .get("/fail", |_| async { fail::Result::<Response>::Err(fail::err!("Database is down")) })
+      .get("/files/*path", |req: Request| async move {
+        Ok::<_, Error>(Response::text(req.param("path").unwrap().to_string()))
+      })
+  }
+
+  #[test]
+  fn test_server() {
+    with_server(router(), |url| async move {
+      let client = Client::new().timeout(Duration::secs(5));
+
+      let res = client.get(&format!("{}/hello/world%21", url)).await.unwrap();
+
+      assert_eq!(res.status(), Status::OK);
+      assert_eq!(res.header("content-type"), Some("text/plain; charset=utf-8"));
+      assert_eq!(res.text_body().unwrap(), "Hello, world!!");
+
+      let req = Request::post(format!("{}/sum", url)).with_json(&[1, 2, 3]).unwrap();
+      let res = client.send(req).await.unwrap();
+
+      assert_eq!(res.json_body::<i64>().unwrap(), 6);
+
+      let res = client.send(Request::post(format!("{}/sum", url)).with_body("nope")).await.unwrap();
+
+      assert_eq!(res.status(), Status::BAD_REQUEST);
+
+      let res = client.get(&format!("{}/fail", url)).await.unwrap();
+
+      assert_eq!(res.status(), Status::INTERNAL_SERVER_ERROR);
+      assert_eq!(res.text_body().unwrap(), r#"{"error":"Database is down."}"#);
+
+      let res = client.get(&format!("{}/files/a/b.txt", url)).await.unwrap();
+
+      assert_eq!(res.text_body().unwrap(), "a/b.txt");
+
+      let res = client.get(&format!("{}/missing", url)).await.unwrap();
+
+      assert_eq!(res.status(), Status::NOT_FOUND);
+
+      let res = client.send(Request::new(Method::Delete, format!("{}/sum", url))).await.unwrap();
+
+      assert_eq!(res.status(), Status::METHOD_NOT_ALLOWED);
+      assert_eq!(res.header("allow"), Some("POST"));
+    });
+  }
+
+  #[test]
+  fn test_header_injection() {
+    let router = Router::new().get("/echo/:id", |req: Request| async move {
+      Ok::<_, Error>(
+        Response::new(Status::NO_CONTENT).with_header("X-Id", req.param("id").unwrap()),
+      )
+    });
+
+    with_server(router, |url| async move {
+      let client = Client::new().timeout(Duration::secs(5));
+      let res = client.get(&format!("{}/echo/a%0D%0AX-Injected:%201", url)).await.unwrap();
+
+      assert_eq!(res.header("x-id"), Some("aX-Injected: 1"));
+      assert_eq!(res.header("x-injected"), None);
+      assert_eq!(
+        res.headers().iter().map(|(name, _)| name).collect::<Vec<_>>(),
+        ["X-Id", "Connection"]
+      );
+
+      let url = format!("{}/echo/a HTTP/1.1\r\nX-Injected: 1\r\n", url);
+
+      let err = client.get(&url).await.unwrap_err().to_string();
+
+      assert!(err.ends_with("It contains whitespace or control characters."));
+    });
+  }
+
+  #[test]
+  #[should_panic(expected = "Invalid header name `X-Id\\r\\nX-Injected`.")]
+  fn test_invalid_header_name() {
+    Headers::new().set("X-Id\r\nX-Injected", "1");
+  }
+
+  #[test]
+  fn test_client_timeout() {
+    let router = Router::new().get("/slow", |_| async {
+      future::sleep(Duration::secs(1)).await;
+
+      Ok::<_, Error>(Response::new(Status::NO_CONTENT))
+    });
+
+    with_server(router, |url| async move {
+      let client = Client::new().timeout(Duration::secs_f64(0.05));
+      let url = format!("{}/slow", url);
+
+      assert_eq!(
+        client.get(&url).await.unwrap_err().to_string(),
+        format!("Request to `{}` timed out after 50ms.", url)
+      );
+    });
+  }
+}
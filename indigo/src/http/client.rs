@@ -0,0 +1,152 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{wire, Method, Request, Response, Status};
+use crate::net::TcpStream;
+use crate::prelude::*;
+use futures_lite::io::BufReader;
+
+/// An HTTP/1.1 client.
+///
+/// Each request is sent on a new connection. Only `http://` URLs are
+/// supported.
+#[derive(Clone)]
+pub struct Client {
+  timeout: Duration,
+  max_body_size: usize,
+}
+
+impl Client {
+  /// Creates a new client with default options.
+  pub fn new() -> Self {
+    default()
+  }
+
+  /// Sets the maximum size of response bodies in bytes.
+  ///
+  /// The default is 16 MiB.
+  pub fn max_body_size(mut self, size: usize) -> Self {
+    self.max_body_size = size;
+    self
+  }
+
+  /// Sets how long a request can take, including connecting and reading the
+  /// response, before it fails.
+  ///
+  /// The default is 30 seconds.
+  pub fn timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = timeout;
+    self
+  }
+
+  /// Sends a `GET` request to the given URL.
+  pub async fn get(&self, url: &str) -> Result<Response> {
+    self.send(Request::get(url)).await
+  }
+
+  /// Sends a request to the URL in its target and returns the response.
+  ///
+  /// Responses with error statuses are returned successfully. Check
+  /// [`Response::status()`] to handle them.
+  pub async fn send(&self, request: Request) -> Result<Response> {
+    let url = &request.target;
+
+    let send =
+      async { self.exchange(&request).await.map_err(fail::with!("Request to `{}` failed", url)) };
+
+    future::race(send, async {
+      future::sleep(self.timeout).await;
+
+      fail!("Request to `{}` timed out after {:?}.", url, self.timeout.to_std());
+    })
+    .await
+  }
+
+  /// Connects to the server, sends a request, and reads the response.
+  async fn exchange(&self, request: &Request) -> Result<Response> {
+    let (authority, target) = parse_url(&request.target)?;
+
+    let addr = match authority.rfind([':', ']']) {
+      Some(i) if authority[i..].starts_with(':') => authority.into(),
+      _ => format!("{}:80", authority),
+    };
+
+    let stream = TcpStream::connect(&addr).await?;
+
+    wire::write_request(&mut &stream, request, authority, &target).await?;
+
+    let mut reader = BufReader::new(&stream);
+
+    loop {
+      let head = wire::read_head(&mut reader)
+        .await?
+        .ok_or_else(|| fail::err!("Connection closed before a response was received."))?;
+
+      let status = wire::parse_status_line(&head.line)?;
+
+      // Skip interim responses such as `100 Continue`.
+
+      if status.code() < 200 {
+        continue;
+      }
+
+      let has_body = request.method != Method::Head
+        && status != Status::NO_CONTENT
+        && status != Status::NOT_MODIFIED;
+
+      let body = match has_body {
+        true => wire::read_body(&mut reader, &head.headers, self.max_body_size, true).await?,
+        false => Vec::new(),
+      };
+
+      return Ok(Response { status, headers: head.headers, body });
+    }
+  }
+}
+
+/// Parses an `http://` URL into its authority, such as `localhost:8080`, and
+/// its target, such as `/index.html`.
+fn parse_url(url: &str) -> Result<(&str, Cow<'_, str>)> {
+  let rest = match url.strip_prefix("http://") {
+    Some(rest) => rest,
+    None if url.starts_with("https://") => fail!("HTTPS is not supported."),
+    None => fail!("Invalid URL `{}`.", url),
+  };
+
+  let (authority, target) = match rest.find(['/', '?', '#']) {
+    Some(i) => rest.split_at(i),
+    None => (rest, ""),
+  };
+
+  if authority.is_empty() {
+    fail!("Invalid URL `{}`. Missing host.", url);
+  }
+
+  // Whitespace and control characters would change the request line or add
+  // headers.
+
+  if rest.contains(|c: char| c.is_whitespace() || c.is_control()) {
+    fail!("Invalid URL `{}`. It contains whitespace or control characters.", url.escape_debug());
+  }
+
+  let target = match target.find('#') {
+    Some(i) => &target[..i],
+    None => target,
+  };
+
+  match target.starts_with('/') {
+    true => Ok((authority, target.into())),
+    false => Ok((authority, format!("/{}", target).into())),
+  }
+}
+
+// Implement `Default` to provide default options.
+
+impl Default for Client {
+  fn default() -> Self {
+    Self { timeout: Duration::secs(30), max_body_size: 16 * 1024 * 1024 }
+  }
+}
@@ -0,0 +1,77 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{Response, Status};
+use crate::prelude::*;
+
+/// An error with an HTTP status, returned from request handlers.
+///
+/// A [`fail::Error`] converts into an error with status `500 Internal Server
+/// Error`.
+#[derive(Clone)]
+pub struct Error {
+  status: Status,
+  inner: fail::Error,
+}
+
+impl Error {
+  /// Creates a new error with the given status and message.
+  pub fn new(status: Status, message: impl Into<String>) -> Self {
+    Self { status, inner: fail::Error::new(message) }
+  }
+
+  /// Creates a new `400 Bad Request` error.
+  pub fn bad_request(message: impl Into<String>) -> Self {
+    Self::new(Status::BAD_REQUEST, message)
+  }
+
+  /// Creates a new `404 Not Found` error.
+  pub fn not_found(message: impl Into<String>) -> Self {
+    Self::new(Status::NOT_FOUND, message)
+  }
+
+  /// Returns the status of the error.
+  pub fn status(&self) -> Status {
+    self.status
+  }
+
+  /// Returns a response describing the error.
+  pub fn to_response(&self) -> Response {
+    let mut response = Response::new(self.status);
+
+    response.headers.set("Content-Type", "application/json");
+    response.body = json::to_vec(&json::json!({ "error": self.inner })).unwrap_or_default();
+    response
+  }
+}
+
+// Implement conversion to and from `fail::Error`.
+
+impl From<fail::Error> for Error {
+  fn from(inner: fail::Error) -> Self {
+    Self { status: Status::INTERNAL_SERVER_ERROR, inner }
+  }
+}
+
+impl From<Error> for fail::Error {
+  fn from(err: Error) -> Self {
+    err.inner
+  }
+}
+
+// Implement formatting.
+
+impl Debug for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("Error").field("status", &self.status).field("message", &self.inner).finish()
+  }
+}
+
+impl Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    Display::fmt(&self.inner, f)
+  }
+}
@@ -0,0 +1,118 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::prelude::*;
+
+/// A list of HTTP headers.
+///
+/// Header names are compared case-insensitively and keep the case they were
+/// added with.
+#[derive(Clone, Debug, Default)]
+pub struct Headers {
+  entries: Vec<(String, String)>,
+}
+
+impl Headers {
+  /// Creates an empty list of headers.
+  pub fn new() -> Self {
+    default()
+  }
+
+  /// Adds a header without replacing existing headers with the same name.
+  ///
+  /// CR, LF, and NUL characters are removed from the value so that it cannot
+  /// add headers or end the message head.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the name is not a valid header name.
+  pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+    let name = validated(name.into());
+
+    self.entries.push((name, sanitized(value.into())));
+  }
+
+  /// Returns `true` if a header with the given name exists.
+  pub fn contains(&self, name: &str) -> bool {
+    self.get(name).is_some()
+  }
+
+  /// Returns the value of the first header with the given name.
+  pub fn get(&self, name: &str) -> Option<&str> {
+    self.entries.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+  }
+
+  /// Returns the values of all headers with the given name.
+  pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    self.entries.iter().filter(move |(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+  }
+
+  /// Returns `true` if there are no headers.
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  /// Returns an iterator over the names and values of all headers.
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+    self.entries.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+  }
+
+  /// Returns the number of headers.
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  /// Removes all headers with the given name.
+  pub fn remove(&mut self, name: &str) {
+    self.entries.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+  }
+
+  /// Sets a header, replacing any existing headers with the same name.
+  ///
+  /// CR, LF, and NUL characters are removed from the value so that it cannot
+  /// add headers or end the message head.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the name is not a valid header name.
+  pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+    let name = validated(name.into());
+
+    self.remove(&name);
+    self.entries.push((name, sanitized(value.into())));
+  }
+
+  /// Returns `true` if the comma-separated values of the header with the given
+  /// name contain the given token, ignoring case.
+  pub(super) fn has_token(&self, name: &str, token: &str) -> bool {
+    self.get_all(name).flat_map(|v| v.split(',')).any(|t| t.trim().eq_ignore_ascii_case(token))
+  }
+}
+
+/// Returns `true` if the given header name is a valid HTTP token.
+pub(super) fn is_valid_name(name: &str) -> bool {
+  let is_valid_char = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+
+  !name.is_empty() && name.chars().all(is_valid_char)
+}
+
+/// Returns the given header name, panicking if it is not valid.
+fn validated(name: String) -> String {
+  if !is_valid_name(&name) {
+    panic!("Invalid header name `{}`.", name.escape_debug());
+  }
+
+  name
+}
+
+/// Removes characters that would end a header line from a header value.
+fn sanitized(mut value: String) -> String {
+  if value.contains(['\r', '\n', '\0']) {
+    value.retain(|c| !matches!(c, '\r' | '\n' | '\0'));
+  }
+
+  value
+}
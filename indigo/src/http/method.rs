@@ -0,0 +1,62 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{Error, Status};
+use crate::prelude::*;
+
+/// An HTTP request method.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Method {
+  Delete,
+  Get,
+  Head,
+  Options,
+  Patch,
+  Post,
+  Put,
+}
+
+impl Method {
+  /// Returns the name of the method, such as `GET`.
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Delete => "DELETE",
+      Self::Get => "GET",
+      Self::Head => "HEAD",
+      Self::Options => "OPTIONS",
+      Self::Patch => "PATCH",
+      Self::Post => "POST",
+      Self::Put => "PUT",
+    }
+  }
+}
+
+// Implement parsing.
+
+impl FromStr for Method {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Self, Error> {
+    Ok(match s {
+      "DELETE" => Self::Delete,
+      "GET" => Self::Get,
+      "HEAD" => Self::Head,
+      "OPTIONS" => Self::Options,
+      "PATCH" => Self::Patch,
+      "POST" => Self::Post,
+      "PUT" => Self::Put,
+      _ => return Err(Error::new(Status::NOT_IMPLEMENTED, format!("Unsupported method `{}`", s))),
+    })
+  }
+}
+
+// Implement formatting.
+
+impl Display for Method {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
@@ -0,0 +1,191 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{Error, Headers, Method};
+use crate::net::SocketAddr;
+use crate::prelude::*;
+use serde::de::DeserializeOwned;
+
+/// An HTTP request.
+///
+/// Requests received by a [`Server`][super::Server] have a target such as
+/// `/users/1?full=true`. Requests sent with a [`Client`][super::Client] have a
+/// full URL as their target, such as `http://localhost:8080/users/1`.
+#[derive(Clone, Debug)]
+pub struct Request {
+  pub(super) method: Method,
+  pub(super) target: String,
+  pub(super) headers: Headers,
+  pub(super) body: Vec<u8>,
+  pub(super) params: Vec<(String, String)>,
+  pub(super) remote_addr: Option<SocketAddr>,
+}
+
+impl Request {
+  /// Creates a new request with the given method and target.
+  pub fn new(method: Method, target: impl Into<String>) -> Self {
+    Self {
+      method,
+      target: target.into(),
+      headers: default(),
+      body: default(),
+      params: default(),
+      remote_addr: None,
+    }
+  }
+
+  /// Creates a new `GET` request for the given target.
+  pub fn get(target: impl Into<String>) -> Self {
+    Self::new(Method::Get, target)
+  }
+
+  /// Creates a new `POST` request for the given target.
+  pub fn post(target: impl Into<String>) -> Self {
+    Self::new(Method::Post, target)
+  }
+
+  /// Sets the body of the request.
+  pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+    self.body = body.into();
+    self
+  }
+
+  /// Sets a header of the request, replacing any existing values.
+  pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+    self.headers.set(name, value);
+    self
+  }
+
+  /// Sets the body of the request to the JSON representation of a value.
+  pub fn with_json(mut self, value: &impl Serialize) -> Result<Self> {
+    self.body = json::to_vec(value)?;
+    self.headers.set("Content-Type", "application/json");
+
+    Ok(self)
+  }
+
+  /// Returns the body of the request.
+  pub fn body(&self) -> &[u8] {
+    &self.body
+  }
+
+  /// Returns the value of the first header with the given name.
+  pub fn header(&self, name: &str) -> Option<&str> {
+    self.headers.get(name)
+  }
+
+  /// Returns the headers of the request.
+  pub fn headers(&self) -> &Headers {
+    &self.headers
+  }
+
+  /// Returns a mutable reference to the headers of the request.
+  pub fn headers_mut(&mut self) -> &mut Headers {
+    &mut self.headers
+  }
+
+  /// Deserializes the body of the request from JSON.
+  ///
+  /// If the body is not valid JSON for the given type, this function returns a
+  /// `400 Bad Request` error.
+  pub fn json<T: DeserializeOwned>(&self) -> Result<T, Error> {
+    json::from_slice(&self.body)
+      .map_err(|err| Error::bad_request(format!("Invalid JSON body. {}", fail::from(err))))
+  }
+
+  /// Returns the method of the request.
+  pub fn method(&self) -> Method {
+    self.method
+  }
+
+  /// Returns the value of a named parameter from the route that matched the
+  /// request.
+  pub fn param(&self, name: &str) -> Option<&str> {
+    self.params.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+  }
+
+  /// Returns the path of the target, without the query string.
+  pub fn path(&self) -> &str {
+    match self.target.find('?') {
+      Some(i) => &self.target[..i],
+      None => &self.target,
+    }
+  }
+
+  /// Returns the query string of the target, if any.
+  pub fn query(&self) -> Option<&str> {
+    self.target.find('?').map(|i| &self.target[i + 1..])
+  }
+
+  /// Returns the decoded value of the first query string parameter with the
+  /// given name.
+  pub fn query_param(&self, name: &str) -> Option<Cow<'_, str>> {
+    self.query()?.split('&').find_map(|pair| {
+      let mut pair = pair.splitn(2, '=');
+      let key = pair.next()?;
+
+      match percent_decode(key, true) == name {
+        true => Some(percent_decode(pair.next().unwrap_or_default(), true)),
+        false => None,
+      }
+    })
+  }
+
+  /// Returns the address of the client that sent the request, if known.
+  pub fn remote_addr(&self) -> Option<SocketAddr> {
+    self.remote_addr
+  }
+
+  /// Returns the target of the request.
+  pub fn target(&self) -> &str {
+    &self.target
+  }
+
+  /// Returns the body of the request as text.
+  ///
+  /// If the body is not valid UTF-8, this function returns a `400 Bad Request`
+  /// error.
+  pub fn text(&self) -> Result<&str, Error> {
+    str::from_utf8(&self.body).map_err(|_| Error::bad_request("Body is not valid UTF-8."))
+  }
+}
+
+/// Decodes percent-encoded characters in part of a URL.
+///
+/// If `plus_as_space` is `true`, `+` is decoded as a space as in query strings.
+/// Invalid escapes are left as is and invalid UTF-8 is replaced.
+pub(super) fn percent_decode(input: &str, plus_as_space: bool) -> Cow<'_, str> {
+  if !(input.contains('%') || plus_as_space && input.contains('+')) {
+    return input.into();
+  }
+
+  let bytes = input.as_bytes();
+  let mut output = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+
+  while i < bytes.len() {
+    let hex = || str::from_utf8(bytes.get(i + 1..i + 3)?).ok();
+
+    match bytes[i] {
+      b'%' => match hex().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+        Some(byte) => {
+          output.push(byte);
+          i += 3;
+          continue;
+        }
+
+        None => output.push(b'%'),
+      },
+
+      b'+' if plus_as_space => output.push(b' '),
+      byte => output.push(byte),
+    }
+
+    i += 1;
+  }
+
+  String::from_utf8_lossy(&output).into_owned().into()
+}
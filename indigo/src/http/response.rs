@@ -0,0 +1,97 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{Error, Headers, Status};
+use crate::prelude::*;
+use serde::de::DeserializeOwned;
+
+/// An HTTP response.
+#[derive(Clone, Debug)]
+pub struct Response {
+  pub(super) status: Status,
+  pub(super) headers: Headers,
+  pub(super) body: Vec<u8>,
+}
+
+impl Response {
+  /// Creates a new response with the given status and an empty body.
+  pub fn new(status: Status) -> Self {
+    Self { status, headers: default(), body: default() }
+  }
+
+  /// Creates a new `200 OK` response with the JSON representation of a value
+  /// as its body.
+  pub fn json(value: &impl Serialize) -> Result<Self, Error> {
+    Ok(Self::new(Status::OK).with_header("Content-Type", "application/json").with_body(
+      json::to_vec(value).map_err(fail::with!("Failed to serialize the response body"))?,
+    ))
+  }
+
+  /// Creates a new `200 OK` response with the given plain text body.
+  pub fn text(text: impl Into<String>) -> Self {
+    Self::new(Status::OK)
+      .with_header("Content-Type", "text/plain; charset=utf-8")
+      .with_body(text.into())
+  }
+
+  /// Sets the body of the response.
+  pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+    self.body = body.into();
+    self
+  }
+
+  /// Sets a header of the response, replacing any existing values.
+  pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+    self.headers.set(name, value);
+    self
+  }
+
+  /// Sets the status of the response.
+  pub fn with_status(mut self, status: Status) -> Self {
+    self.status = status;
+    self
+  }
+
+  /// Returns the body of the response.
+  pub fn body(&self) -> &[u8] {
+    &self.body
+  }
+
+  /// Returns the value of the first header with the given name.
+  pub fn header(&self, name: &str) -> Option<&str> {
+    self.headers.get(name)
+  }
+
+  /// Returns the headers of the response.
+  pub fn headers(&self) -> &Headers {
+    &self.headers
+  }
+
+  /// Returns a mutable reference to the headers of the response.
+  pub fn headers_mut(&mut self) -> &mut Headers {
+    &mut self.headers
+  }
+
+  /// Converts the response into its body.
+  pub fn into_body(self) -> Vec<u8> {
+    self.body
+  }
+
+  /// Deserializes the body of the response from JSON.
+  pub fn json_body<T: DeserializeOwned>(&self) -> Result<T> {
+    json::from_slice(&self.body).map_err(fail::with!("Failed to parse the response body as JSON"))
+  }
+
+  /// Returns the status of the response.
+  pub fn status(&self) -> Status {
+    self.status
+  }
+
+  /// Returns the body of the response as text.
+  pub fn text_body(&self) -> Result<&str> {
+    str::from_utf8(&self.body).map_err(fail::with!("Response body is not valid UTF-8"))
+  }
+}
@@ -0,0 +1,255 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::request::percent_decode;
+use super::{Error, Method, Request, Response, Status};
+use crate::prelude::*;
+
+/// A boxed request handler.
+type Handler = Arc<
+  dyn Fn(Request) -> Pin<Box<dyn Future<Output = Result<Response, Error>> + Send>> + Send + Sync,
+>;
+
+/// Routes requests to handlers based on their method and path.
+///
+/// Route patterns are paths such as `/users/:id/posts`. A segment starting
+/// with `:` matches any single segment and a final segment starting with `*`
+/// matches the rest of the path. The matched values are available from
+/// [`Request::param()`]. Routes are tried in the order they were added.
+#[derive(Clone, Default)]
+pub struct Router {
+  routes: Vec<Route>,
+}
+
+/// A route in a [`Router`].
+#[derive(Clone)]
+struct Route {
+  method: Method,
+  segments: Vec<Segment>,
+  handler: Handler,
+}
+
+/// A segment of a route pattern.
+#[derive(Clone, Debug)]
+enum Segment {
+  Literal(String),
+  Param(String),
+  Rest(String),
+}
+
+impl Router {
+  /// Creates a new router with no routes.
+  pub fn new() -> Self {
+    default()
+  }
+
+  /// Adds a route for the given method and pattern.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the pattern does not start with `/` or has a `*` segment that
+  /// is not the last segment.
+  pub fn route<F, R, E>(mut self, method: Method, pattern: &str, handler: F) -> Self
+  where
+    F: Fn(Request) -> R + Send + Sync + 'static,
+    R: Future<Output = Result<Response, E>> + Send + 'static,
+    E: Into<Error>,
+  {
+    self.routes.push(Route {
+      method,
+      segments: parse_pattern(pattern),
+      handler: Arc::new(move |request| {
+        let future = handler(request);
+
+        Box::pin(async move { future.await.map_err(Into::into) })
+      }),
+    });
+
+    self
+  }
+
+  /// Adds a route for `DELETE` requests.
+  pub fn delete<F, R, E>(self, pattern: &str, handler: F) -> Self
+  where
+    F: Fn(Request) -> R + Send + Sync + 'static,
+    R: Future<Output = Result<Response, E>> + Send + 'static,
+    E: Into<Error>,
+  {
+    self.route(Method::Delete, pattern, handler)
+  }
+
+  /// Adds a route for `GET` requests.
+  ///
+  /// `HEAD` requests are also handled by the route unless an earlier route
+  /// handles them.
+  pub fn get<F, R, E>(self, pattern: &str, handler: F) -> Self
+  where
+    F: Fn(Request) -> R + Send + Sync + 'static,
+    R: Future<Output = Result<Response, E>> + Send + 'static,
+    E: Into<Error>,
+  {
+    self.route(Method::Get, pattern, handler)
+  }
+
+  /// Adds a route for `PATCH` requests.
+  pub fn patch<F, R, E>(self, pattern: &str, handler: F) -> Self
+  where
+    F: Fn(Request) -> R + Send + Sync + 'static,
+    R: Future<Output = Result<Response, E>> + Send + 'static,
+    E: Into<Error>,
+  {
+    self.route(Method::Patch, pattern, handler)
+  }
+
+  /// Adds a route for `POST` requests.
+  pub fn post<F, R, E>(self, pattern: &str, handler: F) -> Self
+  where
+    F: Fn(Request) -> R + Send + Sync + 'static,
+    R: Future<Output = Result<Response, E>> + Send + 'static,
+    E: Into<Error>,
+  {
+    self.route(Method::Post, pattern, handler)
+  }
+
+  /// Adds a route for `PUT` requests.
+  pub fn put<F, R, E>(self, pattern: &str, handler: F) -> Self
+  where
+    F: Fn(Request) -> R + Send + Sync + 'static,
+    R: Future<Output = Result<Response, E>> + Send + 'static,
+    E: Into<Error>,
+  {
+    self.route(Method::Put, pattern, handler)
+  }
+
+  /// Handles a request with the first matching route.
+  ///
+  /// If no route matches the path, the response is `404 Not Found`. If a route
+  /// matches the path but not the method, the response is `405 Method Not
+  /// Allowed`. Errors returned from handlers are converted into responses and
+  /// server errors are logged.
+  pub async fn handle(&self, mut request: Request) -> Response {
+    let path = request.path().to_string();
+    let mut allowed = Vec::new();
+
+    for route in &self.routes {
+      let params = match route.match_path(&path) {
+        Some(params) => params,
+        None => continue,
+      };
+
+      let method_matches = route.method == request.method
+        || (route.method == Method::Get && request.method == Method::Head);
+
+      if !method_matches {
+        if !allowed.contains(&route.method.as_str()) {
+          allowed.push(route.method.as_str());
+        }
+
+        continue;
+      }
+
+      let method = request.method;
+
+      request.params = params;
+
+      return match (route.handler)(request).await {
+        Ok(response) => response,
+
+        Err(err) => {
+          if err.status().is_server_error() {
+            warn!("{} {} failed. {}", method, path, err);
+          }
+
+          err.to_response()
+        }
+      };
+    }
+
+    if allowed.is_empty() {
+      return Error::not_found("Not found.").to_response();
+    }
+
+    Error::new(Status::METHOD_NOT_ALLOWED, "Method not allowed.")
+      .to_response()
+      .with_header("Allow", allowed.join(", "))
+  }
+}
+
+impl Route {
+  /// Matches a path against the route pattern, returning the decoded values of
+  /// its parameters if it matches.
+  fn match_path(&self, path: &str) -> Option<Vec<(String, String)>> {
+    let mut params = Vec::new();
+    let mut remaining = Some(path.strip_prefix('/')?);
+
+    for segment in &self.segments {
+      let rest = remaining?;
+
+      if let Segment::Rest(name) = segment {
+        params.push((name.clone(), percent_decode(rest, false).into_owned()));
+        return Some(params);
+      }
+
+      let part = match rest.find('/') {
+        Some(i) => {
+          remaining = Some(&rest[i + 1..]);
+          &rest[..i]
+        }
+
+        None => {
+          remaining = None;
+          rest
+        }
+      };
+
+      match segment {
+        Segment::Literal(literal) if *percent_decode(part, false) == **literal => {}
+        Segment::Param(name) if !part.is_empty() => {
+          params.push((name.clone(), percent_decode(part, false).into_owned()));
+        }
+
+        _ => return None,
+      }
+    }
+
+    match remaining {
+      None => Some(params),
+      Some(_) => None,
+    }
+  }
+}
+
+/// Parses a route pattern into segments.
+fn parse_pattern(pattern: &str) -> Vec<Segment> {
+  let segments = match pattern.strip_prefix('/') {
+    Some(segments) => segments.split('/'),
+    None => panic!("Route pattern `{}` does not start with `/`.", pattern),
+  };
+
+  let segments: Vec<_> = segments
+    .map(|segment| match segment.chars().next() {
+      Some(':') => Segment::Param(segment[1..].into()),
+      Some('*') => Segment::Rest(segment[1..].into()),
+      _ => Segment::Literal(segment.into()),
+    })
+    .collect();
+
+  let rest = segments.iter().position(|s| matches!(s, Segment::Rest(_)));
+
+  if matches!(rest, Some(i) if i + 1 != segments.len()) {
+    panic!("Route pattern `{}` has a `*` segment that is not last.", pattern);
+  }
+
+  segments
+}
+
+// Implement formatting.
+
+impl Debug for Router {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_list().entries(self.routes.iter().map(|r| (r.method, &r.segments))).finish()
+  }
+}
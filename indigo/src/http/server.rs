@@ -0,0 +1,288 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{wire, Error, Method, Request, Router, Status};
+use crate::net::{SocketAddr, TcpListener, TcpStream};
+use crate::prelude::*;
+use crate::runtime::{self, task};
+use crate::sync::{CancellationToken, Event};
+use futures_lite::io::{AsyncBufRead, BufReader};
+use std::sync::atomic::{self, AtomicUsize};
+
+/// An HTTP/1.1 server that handles requests with a [`Router`].
+///
+/// The server stops accepting connections when its cancellation token is
+/// cancelled, which by default happens when the runtime shuts down. It then
+/// waits for requests that are being handled to complete.
+pub struct Server {
+  listener: TcpListener,
+  router: Arc<Router>,
+  token: CancellationToken,
+  options: Options,
+  shutdown_timeout: Duration,
+}
+
+/// Options for handling connections.
+struct Options {
+  idle_timeout: Duration,
+  max_body_size: usize,
+}
+
+/// Tracks the number of open connections.
+#[derive(Default)]
+struct Connections {
+  count: AtomicUsize,
+  closed: Event,
+}
+
+/// A guard that marks a connection as closed when dropped.
+struct ConnectionGuard(Arc<Connections>);
+
+impl Server {
+  /// Binds a new server to the given address.
+  ///
+  /// Use port `0` to bind to any available port.
+  pub async fn bind(addr: &str, router: Router) -> Result<Self> {
+    Ok(Self {
+      listener: TcpListener::bind(addr).await?,
+      router: Arc::new(router),
+      token: runtime::cancellation_token(),
+      options: Options { idle_timeout: Duration::secs(60), max_body_size: 1024 * 1024 },
+      shutdown_timeout: Duration::secs(30),
+    })
+  }
+
+  /// Sets how long a connection can be idle or take to send a request before
+  /// it is closed.
+  ///
+  /// The default is 60 seconds.
+  pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+    self.options.idle_timeout = timeout;
+    self
+  }
+
+  /// Sets the maximum size of request bodies in bytes.
+  ///
+  /// Requests with larger bodies receive a `413 Payload Too Large` response.
+  /// The default is 1 MiB.
+  pub fn max_body_size(mut self, size: usize) -> Self {
+    self.options.max_body_size = size;
+    self
+  }
+
+  /// Sets the cancellation token that shuts down the server.
+  ///
+  /// The default is a token from [`runtime::cancellation_token()`].
+  pub fn shutdown_on(mut self, token: CancellationToken) -> Self {
+    self.token = token;
+    self
+  }
+
+  /// Sets how long to wait for open connections to close when shutting down.
+  ///
+  /// The default is 30 seconds.
+  pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+    self.shutdown_timeout = timeout;
+    self
+  }
+
+  /// Returns the local address the server is bound to.
+  pub fn local_addr(&self) -> Result<SocketAddr> {
+    self.listener.local_addr()
+  }
+
+  /// Runs the server until it is shut down.
+  ///
+  /// Each connection is handled in a separate task. After the cancellation
+  /// token is cancelled, connections are closed once their current request is
+  /// handled and this function waits for them to close.
+  pub async fn run(self) -> Result {
+    let Self { listener, router, token, options, shutdown_timeout } = self;
+    let connections = Arc::new(Connections::default());
+    let options = Arc::new(options);
+    let mut incoming = listener.incoming();
+
+    if let Ok(addr) = listener.local_addr() {
+      debug!("Listening for HTTP connections on {}.", addr);
+    }
+
+    while let Some(Some(next)) = future::until_cancelled(&token, incoming.next()).await {
+      let stream = match next {
+        Ok(stream) => stream,
+
+        Err(err) => {
+          // Errors such as running out of file descriptors usually resolve
+          // after a short delay.
+
+          warn!("{}", err);
+          future::sleep(Duration::secs_f64(0.1)).await;
+          continue;
+        }
+      };
+
+      let guard = connections.open();
+      let router = router.clone();
+      let token = token.clone();
+      let options = options.clone();
+
+      task::start_detached(async move {
+        serve(stream, &router, &token, &options).await;
+        drop(guard);
+      });
+    }
+
+    drop(incoming);
+
+    let closed = connections.wait_closed();
+
+    let closed = future::race(
+      async {
+        closed.await;
+        true
+      },
+      async {
+        future::sleep(shutdown_timeout).await;
+        false
+      },
+    );
+
+    if !closed.await {
+      warn!(
+        "Shut down the HTTP server with {} connections still open.",
+        connections.count.load(atomic::Ordering::Acquire)
+      );
+    }
+
+    Ok(())
+  }
+}
+
+/// Serves requests on a connection until it is closed.
+async fn serve(stream: TcpStream, router: &Router, token: &CancellationToken, options: &Options) {
+  let remote_addr = stream.peer_addr().ok();
+  let mut reader = BufReader::new(&stream);
+  let mut writer = &stream;
+
+  loop {
+    // Wait for the next request, closing the connection if the server shuts
+    // down or it is idle for too long.
+
+    let ready = future::race(has_data(&mut reader), async {
+      future::race(token.cancelled(), future::sleep(options.idle_timeout)).await;
+      false
+    });
+
+    if !ready.await {
+      return;
+    }
+
+    let request = future::race(read_request(&mut reader, &mut writer, options), async {
+      future::sleep(options.idle_timeout).await;
+
+      Err(Error::new(Status::REQUEST_TIMEOUT, "Timed out reading the request."))
+    });
+
+    let (response, head_only, keep_alive) = match request.await {
+      Ok(Some((mut request, keep_alive))) => {
+        request.remote_addr = remote_addr;
+
+        let head_only = request.method == Method::Head;
+        let response = router.handle(request).await;
+
+        (response, head_only, keep_alive && !token.is_cancelled())
+      }
+
+      Ok(None) => return,
+      Err(err) => (err.to_response(), false, false),
+    };
+
+    let written = wire::write_response(&mut writer, &response, head_only, keep_alive).await;
+
+    if written.is_err() || !keep_alive {
+      return;
+    }
+  }
+}
+
+/// Waits until data is available to read, returning `false` if the stream
+/// ends or fails.
+async fn has_data(reader: &mut (impl AsyncBufRead + Unpin)) -> bool {
+  let poll = |cx: &mut future::Context| {
+    Pin::new(&mut *reader)
+      .poll_fill_buf(cx)
+      .map(|result| matches!(result, Ok(buf) if !buf.is_empty()))
+  };
+
+  future::poll_fn(poll).await
+}
+
+/// Reads a request from a connection.
+///
+/// Returns the request and whether the connection should be kept alive after
+/// the response, or `None` if the connection was closed.
+async fn read_request(
+  reader: &mut BufReader<&TcpStream>,
+  writer: &mut &TcpStream,
+  options: &Options,
+) -> Result<Option<(Request, bool)>, Error> {
+  let head = match wire::read_head(reader).await? {
+    Some(head) => head,
+    None => return Ok(None),
+  };
+
+  let (method, target, is_http11) = wire::parse_request_line(&head.line)?;
+
+  let keep_alive = match is_http11 {
+    true => !head.headers.has_token("Connection", "close"),
+    false => head.headers.has_token("Connection", "keep-alive"),
+  };
+
+  // Clients that send `Expect: 100-continue` wait for an interim response
+  // before sending the body.
+
+  if head.headers.has_token("Expect", "100-continue") {
+    wire::write_continue(writer).await.map_err(fail::Error::from)?;
+  }
+
+  let mut request = Request::new(method, target);
+
+  request.body = wire::read_body(reader, &head.headers, options.max_body_size, false).await?;
+  request.headers = head.headers;
+
+  Ok(Some((request, keep_alive)))
+}
+
+impl Connections {
+  /// Marks a new connection as open.
+  fn open(self: &Arc<Self>) -> ConnectionGuard {
+    self.count.fetch_add(1, atomic::Ordering::AcqRel);
+
+    ConnectionGuard(self.clone())
+  }
+
+  /// Waits until all connections are closed.
+  async fn wait_closed(&self) {
+    loop {
+      let listener = self.closed.listen();
+
+      if self.count.load(atomic::Ordering::Acquire) == 0 {
+        return;
+      }
+
+      listener.await;
+    }
+  }
+}
+
+// Implement `Drop` to mark connections as closed.
+
+impl Drop for ConnectionGuard {
+  fn drop(&mut self) {
+    if self.0.count.fetch_sub(1, atomic::Ordering::AcqRel) == 1 {
+      self.0.closed.notify(usize::MAX);
+    }
+  }
+}
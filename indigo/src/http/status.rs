@@ -0,0 +1,110 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::prelude::*;
+
+/// An HTTP response status code.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Status(u16);
+
+impl Status {
+  pub const OK: Self = Self(200);
+  pub const CREATED: Self = Self(201);
+  pub const ACCEPTED: Self = Self(202);
+  pub const NO_CONTENT: Self = Self(204);
+  pub const MOVED_PERMANENTLY: Self = Self(301);
+  pub const FOUND: Self = Self(302);
+  pub const NOT_MODIFIED: Self = Self(304);
+  pub const BAD_REQUEST: Self = Self(400);
+  pub const UNAUTHORIZED: Self = Self(401);
+  pub const FORBIDDEN: Self = Self(403);
+  pub const NOT_FOUND: Self = Self(404);
+  pub const METHOD_NOT_ALLOWED: Self = Self(405);
+  pub const REQUEST_TIMEOUT: Self = Self(408);
+  pub const CONFLICT: Self = Self(409);
+  pub const PAYLOAD_TOO_LARGE: Self = Self(413);
+  pub const UNPROCESSABLE_ENTITY: Self = Self(422);
+  pub const TOO_MANY_REQUESTS: Self = Self(429);
+  pub const HEADER_FIELDS_TOO_LARGE: Self = Self(431);
+  pub const INTERNAL_SERVER_ERROR: Self = Self(500);
+  pub const NOT_IMPLEMENTED: Self = Self(501);
+  pub const BAD_GATEWAY: Self = Self(502);
+  pub const SERVICE_UNAVAILABLE: Self = Self(503);
+  pub const GATEWAY_TIMEOUT: Self = Self(504);
+
+  /// Creates a status from a numeric code.
+  ///
+  /// Returns `None` if the code is not between 100 and 999.
+  pub fn new(code: u16) -> Option<Self> {
+    match code {
+      100..=999 => Some(Self(code)),
+      _ => None,
+    }
+  }
+
+  /// Returns the numeric code of the status.
+  pub fn code(self) -> u16 {
+    self.0
+  }
+
+  /// Returns `true` if the status is a client error (4xx).
+  pub fn is_client_error(self) -> bool {
+    self.0 >= 400 && self.0 < 500
+  }
+
+  /// Returns `true` if the status is a server error (5xx).
+  pub fn is_server_error(self) -> bool {
+    self.0 >= 500 && self.0 < 600
+  }
+
+  /// Returns `true` if the status is successful (2xx).
+  pub fn is_success(self) -> bool {
+    self.0 >= 200 && self.0 < 300
+  }
+
+  /// Returns the standard reason phrase of the status, such as `Not Found`.
+  ///
+  /// Returns an empty string for unknown status codes.
+  pub fn reason(self) -> &'static str {
+    match self.0 {
+      200 => "OK",
+      201 => "Created",
+      202 => "Accepted",
+      204 => "No Content",
+      301 => "Moved Permanently",
+      302 => "Found",
+      304 => "Not Modified",
+      400 => "Bad Request",
+      401 => "Unauthorized",
+      403 => "Forbidden",
+      404 => "Not Found",
+      405 => "Method Not Allowed",
+      408 => "Request Timeout",
+      409 => "Conflict",
+      413 => "Payload Too Large",
+      422 => "Unprocessable Entity",
+      429 => "Too Many Requests",
+      431 => "Request Header Fields Too Large",
+      500 => "Internal Server Error",
+      501 => "Not Implemented",
+      502 => "Bad Gateway",
+      503 => "Service Unavailable",
+      504 => "Gateway Timeout",
+      _ => "",
+    }
+  }
+}
+
+// Implement formatting.
+
+impl Display for Status {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.reason() {
+      "" => write!(f, "{}", self.0),
+      reason => write!(f, "{} {}", self.0, reason),
+    }
+  }
+}
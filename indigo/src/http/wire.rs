@@ -0,0 +1,342 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::headers::is_valid_name;
+use super::{Error, Headers, Method, Request, Response, Status};
+use crate::prelude::*;
+use futures_lite::io::{AsyncBufRead, AsyncBufReadExt as _, AsyncReadExt as _};
+use futures_lite::io::{AsyncWrite, AsyncWriteExt as _};
+use std::io;
+
+/// The maximum size of the start line and headers of a message.
+const MAX_HEAD_SIZE: u64 = 64 * 1024;
+
+/// The maximum length of a line containing a chunk size.
+const MAX_CHUNK_LINE: u64 = 1024;
+
+/// The start line and headers of a message.
+pub(super) struct Head {
+  pub line: String,
+  pub headers: Headers,
+}
+
+/// Reads the start line and headers of a message.
+///
+/// Returns `None` if the stream ends before any data is read.
+pub(super) async fn read_head<R>(reader: &mut R) -> Result<Option<Head>, Error>
+where
+  R: AsyncBufRead + Unpin,
+{
+  let mut reader = (&mut *reader).take(MAX_HEAD_SIZE);
+  let mut buf = String::new();
+  let mut start: Option<String> = None;
+  let mut headers = Headers::new();
+
+  loop {
+    buf.clear();
+    reader.read_line(&mut buf).await.map_err(read_failed)?;
+
+    if !buf.ends_with('\n') {
+      if buf.is_empty() && start.is_none() {
+        return Ok(None);
+      }
+
+      if reader.limit() == 0 {
+        return Err(Error::new(Status::HEADER_FIELDS_TOO_LARGE, "Message head is too large."));
+      }
+
+      return Err(Error::bad_request("Unexpected end of message head."));
+    }
+
+    let line = buf.trim_end_matches(['\r', '\n']);
+
+    // Ignore empty lines before the start line, which some clients send after
+    // a request body.
+
+    if start.is_none() {
+      if !line.is_empty() {
+        start = Some(line.into());
+      }
+
+      continue;
+    }
+
+    if line.is_empty() {
+      return Ok(start.map(|line| Head { line, headers }));
+    }
+
+    match line.find(':') {
+      Some(i) if is_valid_name(line[..i].trim()) => {
+        headers.append(line[..i].trim(), line[i + 1..].trim())
+      }
+
+      _ => return Err(Error::bad_request(format!("Invalid header `{}`.", line))),
+    }
+  }
+}
+
+/// Reads the body of a message based on its headers.
+///
+/// If the message has neither a `Content-Length` nor a chunked
+/// `Transfer-Encoding`, the body is empty unless `until_eof` is `true`, in
+/// which case it is read until the end of the stream.
+pub(super) async fn read_body<R>(
+  reader: &mut R,
+  headers: &Headers,
+  max_size: usize,
+  until_eof: bool,
+) -> Result<Vec<u8>, Error>
+where
+  R: AsyncBufRead + Unpin,
+{
+  if headers.has_token("Transfer-Encoding", "chunked") {
+    return read_chunked(reader, max_size).await;
+  }
+
+  if let Some(len) = headers.get("Content-Length") {
+    let len: usize =
+      len.parse().map_err(|_| Error::bad_request(format!("Invalid content length `{}`.", len)))?;
+
+    if len > max_size {
+      return Err(too_large(max_size));
+    }
+
+    let mut body = vec![0; len];
+
+    reader.read_exact(&mut body).await.map_err(read_failed)?;
+
+    return Ok(body);
+  }
+
+  let mut body = Vec::new();
+
+  if until_eof {
+    (&mut *reader).take(max_size as u64 + 1).read_to_end(&mut body).await.map_err(read_failed)?;
+
+    if body.len() > max_size {
+      return Err(too_large(max_size));
+    }
+  }
+
+  Ok(body)
+}
+
+/// Parses the start line of a request into its method and target.
+///
+/// Returns `true` as the last element if the request uses HTTP/1.1.
+pub(super) fn parse_request_line(line: &str) -> Result<(Method, String, bool), Error> {
+  let invalid = || Error::bad_request(format!("Invalid request line `{}`.", line));
+  let mut parts = line.split(' ');
+
+  let method = parts.next().ok_or_else(invalid)?;
+  let target = parts.next().filter(|t| !t.is_empty()).ok_or_else(invalid)?;
+
+  let is_http11 = match parts.next() {
+    Some("HTTP/1.1") => true,
+    Some("HTTP/1.0") => false,
+    _ => return Err(invalid()),
+  };
+
+  if parts.next().is_some() {
+    return Err(invalid());
+  }
+
+  Ok((method.parse()?, target.into(), is_http11))
+}
+
+/// Parses the start line of a response into its status.
+pub(super) fn parse_status_line(line: &str) -> Result<Status> {
+  let mut parts = line.splitn(3, ' ');
+
+  let status = match (parts.next(), parts.next()) {
+    (Some(version), Some(code)) if version.starts_with("HTTP/1.") => {
+      code.parse().ok().and_then(Status::new)
+    }
+
+    _ => None,
+  };
+
+  status.ok_or_else(|| fail::err!("Invalid status line `{}`.", line))
+}
+
+/// Writes a request to a stream.
+///
+/// The request is sent with `Connection: close` so that the response body
+/// can be read until the end of the stream.
+pub(super) async fn write_request<W>(
+  writer: &mut W,
+  request: &Request,
+  host: &str,
+  target: &str,
+) -> io::Result<()>
+where
+  W: AsyncWrite + Unpin,
+{
+  let mut buf = Vec::with_capacity(256 + request.body.len());
+
+  write!(buf, "{} {} HTTP/1.1\r\nHost: {}\r\n", request.method, target, host)?;
+  write_headers(&mut buf, &request.headers)?;
+
+  let has_body = matches!(request.method, Method::Patch | Method::Post | Method::Put);
+
+  if has_body || !request.body.is_empty() {
+    write!(buf, "Content-Length: {}\r\n", request.body.len())?;
+  }
+
+  write!(buf, "Connection: close\r\n\r\n")?;
+  buf.extend_from_slice(&request.body);
+
+  writer.write_all(&buf).await?;
+  writer.flush().await
+}
+
+/// Writes a response to a stream.
+///
+/// If `head_only` is `true`, the body is omitted as in a response to a `HEAD`
+/// request. If `keep_alive` is `false`, the response tells the client that the
+/// connection will be closed.
+pub(super) async fn write_response<W>(
+  writer: &mut W,
+  response: &Response,
+  head_only: bool,
+  keep_alive: bool,
+) -> io::Result<()>
+where
+  W: AsyncWrite + Unpin,
+{
+  let mut buf = Vec::with_capacity(256 + response.body.len());
+  let status = response.status;
+
+  write!(buf, "HTTP/1.1 {} {}\r\n", status.code(), status.reason())?;
+  write_headers(&mut buf, &response.headers)?;
+
+  if status != Status::NO_CONTENT && status != Status::NOT_MODIFIED {
+    write!(buf, "Content-Length: {}\r\n", response.body.len())?;
+  }
+
+  if !keep_alive {
+    write!(buf, "Connection: close\r\n")?;
+  }
+
+  write!(buf, "\r\n")?;
+
+  if !head_only {
+    buf.extend_from_slice(&response.body);
+  }
+
+  writer.write_all(&buf).await?;
+  writer.flush().await
+}
+
+/// Writes a `100 Continue` interim response to a stream.
+pub(super) async fn write_continue<W>(writer: &mut W) -> io::Result<()>
+where
+  W: AsyncWrite + Unpin,
+{
+  writer.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").await?;
+  writer.flush().await
+}
+
+/// Reads a body with chunked transfer encoding.
+async fn read_chunked<R>(reader: &mut R, max_size: usize) -> Result<Vec<u8>, Error>
+where
+  R: AsyncBufRead + Unpin,
+{
+  let mut body = Vec::new();
+  let mut line = String::new();
+
+  loop {
+    line.clear();
+    (&mut *reader).take(MAX_CHUNK_LINE).read_line(&mut line).await.map_err(read_failed)?;
+
+    let size = line.split(';').next().unwrap_or_default().trim();
+
+    let size = usize::from_str_radix(size, 16)
+      .ok()
+      .filter(|_| line.ends_with('\n'))
+      .ok_or_else(|| Error::bad_request("Invalid chunk size."))?;
+
+    // A chunk of size zero is the last chunk. It is followed by optional
+    // trailers, which are ignored.
+
+    if size == 0 {
+      read_head_lines(reader).await?;
+
+      return Ok(body);
+    }
+
+    if size > max_size - body.len() {
+      return Err(too_large(max_size));
+    }
+
+    let start = body.len();
+
+    body.resize(start + size, 0);
+    reader.read_exact(&mut body[start..]).await.map_err(read_failed)?;
+
+    line.clear();
+    (&mut *reader).take(MAX_CHUNK_LINE).read_line(&mut line).await.map_err(read_failed)?;
+
+    if line != "\r\n" && line != "\n" {
+      return Err(Error::bad_request("Invalid chunk terminator."));
+    }
+  }
+}
+
+/// Reads and discards header lines until an empty line.
+async fn read_head_lines<R>(reader: &mut R) -> Result<(), Error>
+where
+  R: AsyncBufRead + Unpin,
+{
+  let mut reader = (&mut *reader).take(MAX_HEAD_SIZE);
+  let mut line = String::new();
+
+  loop {
+    line.clear();
+    reader.read_line(&mut line).await.map_err(read_failed)?;
+
+    if !line.ends_with('\n') {
+      return Err(Error::bad_request("Unexpected end of chunk trailers."));
+    }
+
+    if line.trim_end_matches(['\r', '\n']).is_empty() {
+      return Ok(());
+    }
+  }
+}
+
+/// Writes headers to a buffer, skipping headers that are determined by the
+/// message framing.
+fn write_headers(buf: &mut Vec<u8>, headers: &Headers) -> io::Result<()> {
+  const SKIPPED: &[&str] = &["Connection", "Content-Length", "Host", "Transfer-Encoding"];
+
+  for (name, value) in headers.iter() {
+    if !SKIPPED.iter().any(|s| s.eq_ignore_ascii_case(name)) {
+      write!(buf, "{}: {}\r\n", name, value)?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Converts an IO error that occurred while reading a message into an error.
+///
+/// Invalid UTF-8 in the message head is reported as a bad request.
+fn read_failed(err: io::Error) -> Error {
+  match err.kind() {
+    io::ErrorKind::InvalidData => Error::bad_request("Message head is not valid UTF-8."),
+    io::ErrorKind::UnexpectedEof => Error::bad_request("Unexpected end of message."),
+    _ => fail::Error::from(err).into(),
+  }
+}
+
+/// Returns an error for a body that is larger than the maximum size.
+fn too_large(max_size: usize) -> Error {
+  Error::new(
+    Status::PAYLOAD_TOO_LARGE,
+    format!("Body is larger than the limit of {} bytes.", max_size),
+  )
+}
@@ -4,6 +4,8 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+pub mod args;
+pub mod config;
 pub mod derive;
 pub mod encoding;
 pub mod env;
@@ -14,6 +16,7 @@ pub mod future;
 pub mod iter;
 pub mod log;
 pub mod math;
+pub mod metrics;
 pub mod prelude;
 pub mod random;
 pub mod stream;
@@ -23,6 +26,7 @@ pub mod thread;
 pub mod time;
 pub mod uuid;
 
+pub use self::args::Args;
 pub use self::fail::fail;
 pub use self::random::{random, Random};
 pub use self::symbol::Symbol;
@@ -32,6 +36,15 @@ pub use indigo_macros::{attempt, attempt_async};
 #[cfg(feature = "postgres")]
 pub mod postgres;
 
+#[cfg(feature = "runtime")]
+pub mod http;
+
+#[cfg(feature = "runtime")]
+pub mod net;
+
+#[cfg(feature = "runtime")]
+pub mod process;
+
 #[cfg(feature = "runtime")]
 pub mod runtime;
 
@@ -0,0 +1,176 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Lock-free counters, gauges, and histograms.
+//!
+//! Metrics are registered in a [`Registry`] by name and a list of labels,
+//! which are interned as [`Symbol`][crate::Symbol] values. Registering the
+//! same name and labels again returns a handle to the same metric, but
+//! handles are cheap to clone and should be kept rather than looked up for
+//! every update.
+//!
+//! The functions in this module register metrics in the global registry
+//! returned by [`registry()`], which can be exported in the Prometheus text
+//! format or as JSON.
+//!
+//! ```
+//! use indigo::metrics;
+//!
+//! let requests = metrics::counter("requests_total", "Total requests.", &[("method", "GET")]);
+//!
+//! requests.increment();
+//!
+//! assert!(metrics::registry().to_prometheus().contains("requests_total{method=\"GET\"} 1\n"));
+//! ```
+
+mod counter;
+mod gauge;
+mod histogram;
+mod registry;
+
+pub use self::counter::Counter;
+pub use self::gauge::Gauge;
+pub use self::histogram::{Histogram, DEFAULT_BUCKETS};
+pub use self::registry::Registry;
+
+use crate::prelude::*;
+use std::sync::atomic::{self, AtomicU64};
+
+/// Returns the global registry.
+pub fn registry() -> &'static Registry {
+  static REGISTRY: Lazy<Registry> = Lazy::new(default);
+
+  &REGISTRY
+}
+
+/// Registers a counter in the global registry or returns the existing counter
+/// with the same name and labels.
+///
+/// See [`Registry::counter()`].
+pub fn counter(name: &str, help: &str, labels: &[(&str, &str)]) -> Counter {
+  registry().counter(name, help, labels)
+}
+
+/// Registers a gauge in the global registry or returns the existing gauge with
+/// the same name and labels.
+///
+/// See [`Registry::gauge()`].
+pub fn gauge(name: &str, help: &str, labels: &[(&str, &str)]) -> Gauge {
+  registry().gauge(name, help, labels)
+}
+
+/// Registers a histogram with the default buckets in the global registry or
+/// returns the existing histogram with the same name and labels.
+///
+/// See [`Registry::histogram()`].
+pub fn histogram(name: &str, help: &str, labels: &[(&str, &str)]) -> Histogram {
+  registry().histogram(name, help, labels)
+}
+
+/// Atomically adds to an `f64` stored as bits in an `AtomicU64`.
+fn atomic_add_f64(bits: &AtomicU64, amount: f64) {
+  let mut current = bits.load(atomic::Ordering::Relaxed);
+
+  loop {
+    let new = (f64::from_bits(current) + amount).to_bits();
+
+    match bits.compare_exchange_weak(
+      current,
+      new,
+      atomic::Ordering::Relaxed,
+      atomic::Ordering::Relaxed,
+    ) {
+      Ok(_) => return,
+      Err(actual) => current = actual,
+    }
+  }
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::thread;
+
+  #[test]
+  fn test_prometheus() {
+    let registry = Registry::new();
+
+    registry.counter("test_total", "A \"test\".\nCounter.", &[("b", "2"), ("a", "x\"y")]).add(3);
+    registry.gauge("test_gauge", "A gauge.", &[]).set(-1.5);
+
+    let histogram =
+      registry.histogram_with_buckets("test_seconds", "A histogram.", &[], &[0.1, 1.0]);
+
+    histogram.observe(0.05);
+    histogram.observe(0.5);
+    histogram.observe(5.0);
+
+    assert_eq!(
+      registry.to_prometheus(),
+      concat!(
+        "# HELP test_gauge A gauge.\n",
+        "# TYPE test_gauge gauge\n",
+        "test_gauge -1.5\n",
+        "# HELP test_seconds A histogram.\n",
+        "# TYPE test_seconds histogram\n",
+        "test_seconds_bucket{le=\"0.1\"} 1\n",
+        "test_seconds_bucket{le=\"1\"} 2\n",
+        "test_seconds_bucket{le=\"+Inf\"} 3\n",
+        "test_seconds_sum 5.55\n",
+        "test_seconds_count 3\n",
+        "# HELP test_total A \"test\".\\nCounter.\n",
+        "# TYPE test_total counter\n",
+        "test_total{a=\"x\\\"y\",b=\"2\"} 3\n",
+      )
+    );
+  }
+
+  #[test]
+  fn test_registry() {
+    let registry = Registry::new();
+
+    registry.counter("test_total", "A test.", &[("a", "1")]).increment();
+    registry.counter("test_total", "A test.", &[("a", "1")]).increment();
+    registry.counter("test_total", "A test.", &[("a", "")]).increment();
+
+    assert_eq!(registry.counter("test_total", "A test.", &[("a", "1")]).get(), 2);
+
+    assert_eq!(
+      registry.snapshot(),
+      json::json!({
+        "test_total": {
+          "type": "counter",
+          "help": "A test.",
+          "values": [
+            { "labels": { "a": "" }, "value": 1 },
+            { "labels": { "a": "1" }, "value": 2 },
+          ],
+        },
+      })
+    );
+
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+      registry.gauge("test_total", "A test.", &[]);
+    }));
+
+    assert!(result.is_err());
+  }
+
+  #[test]
+  fn test_histogram_time() {
+    let histogram = Histogram::new();
+
+    thread::block_on(
+      histogram.time(async { std::thread::sleep(std::time::Duration::from_millis(10)) }),
+    );
+
+    assert_eq!(histogram.count(), 1);
+    assert!(histogram.sum() >= 0.01);
+    assert_eq!(histogram.buckets()[0], (0.005, 0));
+  }
+}
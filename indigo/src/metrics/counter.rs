@@ -0,0 +1,38 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::prelude::*;
+use std::sync::atomic::{self, AtomicU64};
+
+/// A metric that counts up, such as the number of requests handled.
+///
+/// Cloning a counter returns a handle to the same counter.
+#[derive(Clone, Debug, Default)]
+pub struct Counter {
+  value: Arc<AtomicU64>,
+}
+
+impl Counter {
+  /// Creates a new counter that is not registered.
+  pub fn new() -> Self {
+    default()
+  }
+
+  /// Adds to the value of the counter.
+  pub fn add(&self, amount: u64) {
+    self.value.fetch_add(amount, atomic::Ordering::Relaxed);
+  }
+
+  /// Returns the value of the counter.
+  pub fn get(&self) -> u64 {
+    self.value.load(atomic::Ordering::Relaxed)
+  }
+
+  /// Adds one to the value of the counter.
+  pub fn increment(&self) {
+    self.add(1);
+  }
+}
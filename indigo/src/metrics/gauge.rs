@@ -0,0 +1,50 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::atomic_add_f64;
+use crate::prelude::*;
+use std::sync::atomic::{self, AtomicU64};
+
+/// A metric that can go up and down, such as the number of open connections.
+///
+/// Cloning a gauge returns a handle to the same gauge.
+#[derive(Clone, Debug, Default)]
+pub struct Gauge {
+  /// The bits of the `f64` value.
+  bits: Arc<AtomicU64>,
+}
+
+impl Gauge {
+  /// Creates a new gauge that is not registered.
+  pub fn new() -> Self {
+    default()
+  }
+
+  /// Adds to the value of the gauge.
+  pub fn add(&self, amount: f64) {
+    atomic_add_f64(&self.bits, amount);
+  }
+
+  /// Subtracts one from the value of the gauge.
+  pub fn decrement(&self) {
+    self.add(-1.0);
+  }
+
+  /// Returns the value of the gauge.
+  pub fn get(&self) -> f64 {
+    f64::from_bits(self.bits.load(atomic::Ordering::Relaxed))
+  }
+
+  /// Adds one to the value of the gauge.
+  pub fn increment(&self) {
+    self.add(1.0);
+  }
+
+  /// Sets the value of the gauge.
+  pub fn set(&self, value: f64) {
+    self.bits.store(value.to_bits(), atomic::Ordering::Relaxed);
+  }
+}
@@ -0,0 +1,127 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::atomic_add_f64;
+use crate::prelude::*;
+use std::sync::atomic::{self, AtomicU64};
+use std::time::Instant;
+
+/// The default bucket bounds of a histogram, suitable for durations in
+/// seconds.
+pub const DEFAULT_BUCKETS: &[f64] =
+  &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// A metric that counts observed values in buckets, such as request
+/// durations.
+///
+/// Cloning a histogram returns a handle to the same histogram.
+#[derive(Clone, Debug)]
+pub struct Histogram {
+  inner: Arc<Inner>,
+}
+
+/// The shared state of a histogram.
+#[derive(Debug)]
+struct Inner {
+  /// The upper bound of each bucket, excluding the last `+Inf` bucket.
+  bounds: Box<[f64]>,
+  /// The number of values in each bucket, including the last `+Inf` bucket.
+  counts: Box<[AtomicU64]>,
+  /// The bits of the `f64` sum of all values.
+  sum: AtomicU64,
+}
+
+impl Histogram {
+  /// Creates a new histogram with the default buckets that is not registered.
+  pub fn new() -> Self {
+    Self::with_buckets(DEFAULT_BUCKETS)
+  }
+
+  /// Creates a new histogram with the given bucket upper bounds that is not
+  /// registered.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the bounds are not finite and in increasing order.
+  pub fn with_buckets(bounds: &[f64]) -> Self {
+    let is_valid =
+      bounds.iter().all(|b| b.is_finite()) && bounds.windows(2).all(|pair| pair[0] < pair[1]);
+
+    if !is_valid {
+      panic!("Histogram buckets {:?} are not finite and in increasing order.", bounds);
+    }
+
+    Self {
+      inner: Arc::new(Inner {
+        bounds: bounds.into(),
+        counts: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+        sum: default(),
+      }),
+    }
+  }
+
+  /// Returns the upper bound of each bucket and the number of observed values
+  /// less than or equal to it.
+  ///
+  /// The last bucket has an upper bound of infinity and contains every value.
+  pub fn buckets(&self) -> Vec<(f64, u64)> {
+    let bounds = self.inner.bounds.iter().copied().chain(iter::once(f64::INFINITY));
+    let mut total = 0;
+
+    bounds
+      .zip(self.inner.counts.iter())
+      .map(|(bound, count)| {
+        total += count.load(atomic::Ordering::Relaxed);
+
+        (bound, total)
+      })
+      .collect()
+  }
+
+  /// Returns the number of observed values.
+  pub fn count(&self) -> u64 {
+    self.inner.counts.iter().map(|count| count.load(atomic::Ordering::Relaxed)).sum()
+  }
+
+  /// Observes a value.
+  pub fn observe(&self, value: f64) {
+    let index = self.inner.bounds.iter().position(|&bound| value <= bound);
+    let index = index.unwrap_or(self.inner.bounds.len());
+
+    self.inner.counts[index].fetch_add(1, atomic::Ordering::Relaxed);
+
+    atomic_add_f64(&self.inner.sum, value);
+  }
+
+  /// Observes a duration as a number of seconds.
+  pub fn observe_duration(&self, duration: Duration) {
+    self.observe(duration.as_secs());
+  }
+
+  /// Returns the sum of all observed values.
+  pub fn sum(&self) -> f64 {
+    f64::from_bits(self.inner.sum.load(atomic::Ordering::Relaxed))
+  }
+
+  /// Waits for a future to complete and observes how long it took in
+  /// seconds.
+  pub async fn time<F: Future>(&self, future: F) -> F::Output {
+    let started = Instant::now();
+    let output = future.await;
+
+    self.observe_duration(started.elapsed().into());
+
+    output
+  }
+}
+
+// Implement `Default` to create a histogram with the default buckets.
+
+impl Default for Histogram {
+  fn default() -> Self {
+    Self::new()
+  }
+}
@@ -0,0 +1,353 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{Counter, Gauge, Histogram, DEFAULT_BUCKETS};
+use crate::prelude::*;
+use crate::sync::blocking::RwLock;
+use crate::Symbol;
+use std::collections::HashMap;
+
+/// A collection of named metrics.
+#[derive(Debug, Default)]
+pub struct Registry {
+  families: RwLock<HashMap<Symbol, Family>>,
+}
+
+/// Metrics with the same name and different labels.
+#[derive(Clone, Debug)]
+struct Family {
+  help: String,
+  kind: &'static str,
+  metrics: HashMap<Labels, Metric>,
+}
+
+/// A list of label names and values, sorted by name.
+type Labels = Box<[(Symbol, Symbol)]>;
+
+/// One of the types of metric.
+#[derive(Clone, Debug)]
+enum Metric {
+  Counter(Counter),
+  Gauge(Gauge),
+  Histogram(Histogram),
+}
+
+impl Registry {
+  /// Creates a new, empty registry.
+  pub fn new() -> Self {
+    default()
+  }
+
+  /// Registers a counter or returns the existing counter with the same name and
+  /// labels.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the name or a label name is invalid, or if a metric of a
+  /// different type is registered with the same name.
+  pub fn counter(&self, name: &str, help: &str, labels: &[(&str, &str)]) -> Counter {
+    match self.get_or_register(name, help, labels, || Metric::Counter(default())) {
+      Metric::Counter(counter) => counter,
+      _ => unreachable!(),
+    }
+  }
+
+  /// Registers a gauge or returns the existing gauge with the same name and
+  /// labels.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the name or a label name is invalid, or if a metric of a
+  /// different type is registered with the same name.
+  pub fn gauge(&self, name: &str, help: &str, labels: &[(&str, &str)]) -> Gauge {
+    match self.get_or_register(name, help, labels, || Metric::Gauge(default())) {
+      Metric::Gauge(gauge) => gauge,
+      _ => unreachable!(),
+    }
+  }
+
+  /// Registers a histogram with the default buckets or returns the existing
+  /// histogram with the same name and labels.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the name or a label name is invalid, or if a metric of a
+  /// different type is registered with the same name.
+  pub fn histogram(&self, name: &str, help: &str, labels: &[(&str, &str)]) -> Histogram {
+    self.histogram_with_buckets(name, help, labels, DEFAULT_BUCKETS)
+  }
+
+  /// Registers a histogram with the given bucket upper bounds or returns the
+  /// existing histogram with the same name and labels.
+  ///
+  /// If the histogram already exists, the given buckets are ignored.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the name or a label name is invalid, if the buckets are not
+  /// finite and in increasing order, or if a metric of a different type is
+  /// registered with the same name.
+  pub fn histogram_with_buckets(
+    &self,
+    name: &str,
+    help: &str,
+    labels: &[(&str, &str)],
+    buckets: &[f64],
+  ) -> Histogram {
+    let create = || Metric::Histogram(Histogram::with_buckets(buckets));
+
+    match self.get_or_register(name, help, labels, create) {
+      Metric::Histogram(histogram) => histogram,
+      _ => unreachable!(),
+    }
+  }
+
+  /// Returns a JSON snapshot of the current value of every metric.
+  ///
+  /// The snapshot is an object with a key for each metric name. Each value
+  /// has the `type` and `help` of the metric and a list of `values`, each with
+  /// its `labels`. Histogram values have a `count`, `sum`, and cumulative
+  /// `buckets`.
+  pub fn snapshot(&self) -> json::Value {
+    let mut snapshot = json::Map::new();
+
+    for (name, family) in self.sorted() {
+      let values: Vec<_> = sorted_metrics(&family)
+        .map(|(labels, metric)| {
+          let labels: json::Map<_, _> =
+            labels.iter().map(|(k, v)| (k.to_string(), v.as_str().into())).collect();
+
+          match metric {
+            Metric::Counter(counter) => json::json!({ "labels": labels, "value": counter.get() }),
+            Metric::Gauge(gauge) => json::json!({ "labels": labels, "value": gauge.get() }),
+
+            Metric::Histogram(histogram) => {
+              let buckets: Vec<_> = histogram
+                .buckets()
+                .into_iter()
+                .filter(|(bound, _)| bound.is_finite())
+                .map(|(bound, count)| json::json!({ "le": bound, "count": count }))
+                .collect();
+
+              json::json!({
+                "labels": labels,
+                "count": histogram.count(),
+                "sum": histogram.sum(),
+                "buckets": buckets,
+              })
+            }
+          }
+        })
+        .collect();
+
+      snapshot.insert(
+        name.to_string(),
+        json::json!({ "type": family.kind, "help": family.help, "values": values }),
+      );
+    }
+
+    snapshot.into()
+  }
+
+  /// Returns the current value of every metric in the Prometheus text format.
+  pub fn to_prometheus(&self) -> String {
+    let mut out = String::with_capacity(4096);
+
+    for (name, family) in self.sorted() {
+      writeln!(out, "# HELP {} {}", name, escape(&family.help, false)).unwrap();
+      writeln!(out, "# TYPE {} {}", name, family.kind).unwrap();
+
+      for (labels, metric) in sorted_metrics(&family) {
+        match metric {
+          Metric::Counter(counter) => {
+            write_sample(&mut out, &name, "", labels, None, counter.get());
+          }
+
+          Metric::Gauge(gauge) => {
+            write_sample(&mut out, &name, "", labels, None, PromFloat(gauge.get()));
+          }
+
+          Metric::Histogram(histogram) => {
+            for (bound, count) in histogram.buckets() {
+              write_sample(&mut out, &name, "_bucket", labels, Some(bound), count);
+            }
+
+            write_sample(&mut out, &name, "_sum", labels, None, PromFloat(histogram.sum()));
+            write_sample(&mut out, &name, "_count", labels, None, histogram.count());
+          }
+        }
+      }
+    }
+
+    out
+  }
+
+  /// Returns the metric with the given name and labels, registering a new
+  /// metric if it does not exist.
+  fn get_or_register(
+    &self,
+    name: &str,
+    help: &str,
+    labels: &[(&str, &str)],
+    create: impl FnOnce() -> Metric,
+  ) -> Metric {
+    let name = Symbol::new(name);
+    let labels = to_labels(labels);
+
+    if let Some(metric) = self.families.read().get(&name).and_then(|f| f.metrics.get(&labels)) {
+      return metric.clone();
+    }
+
+    if !is_valid_name(&name, true) {
+      panic!("Invalid metric name `{}`.", name);
+    }
+
+    let metric = create();
+    let mut families = self.families.write();
+
+    let family = families.entry(name).or_insert_with(|| Family {
+      help: help.into(),
+      kind: metric.kind(),
+      metrics: default(),
+    });
+
+    if family.kind != metric.kind() {
+      panic!("Metric `{}` is already registered as a {}.", name, family.kind);
+    }
+
+    family.metrics.entry(labels).or_insert(metric).clone()
+  }
+
+  /// Returns a copy of every metric family sorted by name.
+  fn sorted(&self) -> Vec<(Symbol, Family)> {
+    let families = self.families.read();
+    let mut sorted: Vec<_> = families.iter().map(|(name, f)| (*name, f.clone())).collect();
+
+    sorted.sort_by_key(|(name, _)| name.as_str());
+    sorted
+  }
+}
+
+impl Metric {
+  /// Returns the Prometheus name of the type of the metric.
+  fn kind(&self) -> &'static str {
+    match self {
+      Self::Counter(_) => "counter",
+      Self::Gauge(_) => "gauge",
+      Self::Histogram(_) => "histogram",
+    }
+  }
+}
+
+/// Returns the metrics of a family sorted by their labels.
+fn sorted_metrics(family: &Family) -> impl Iterator<Item = (&Labels, &Metric)> {
+  let mut metrics: Vec<_> = family.metrics.iter().collect();
+
+  metrics.sort_by(|a, b| {
+    let a = a.0.iter().map(|(k, v)| (k.as_str(), v.as_str()));
+    let b = b.0.iter().map(|(k, v)| (k.as_str(), v.as_str()));
+
+    a.cmp(b)
+  });
+
+  metrics.into_iter()
+}
+
+/// Converts a list of label names and values into sorted symbols.
+fn to_labels(labels: &[(&str, &str)]) -> Labels {
+  let mut labels: Vec<_> = labels
+    .iter()
+    .map(|(name, value)| {
+      if !is_valid_name(name, false) {
+        panic!("Invalid metric label name `{}`.", name);
+      }
+
+      (Symbol::new(*name), Symbol::new(*value))
+    })
+    .collect();
+
+  labels.sort_by_key(|(name, _)| name.as_str());
+  labels.into()
+}
+
+/// Returns `true` if a metric or label name is valid in the Prometheus text
+/// format.
+fn is_valid_name(name: &str, allow_colon: bool) -> bool {
+  let is_valid_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || (allow_colon && c == ':');
+
+  match name.chars().next() {
+    Some(first) if !first.is_ascii_digit() => name.chars().all(is_valid_char),
+    _ => false,
+  }
+}
+
+/// Writes a sample line in the Prometheus text format.
+fn write_sample(
+  out: &mut String,
+  name: &str,
+  suffix: &str,
+  labels: &Labels,
+  le: Option<f64>,
+  value: impl Display,
+) {
+  write!(out, "{}{}", name, suffix).unwrap();
+
+  let le = le.map(|le| (Symbol::new("le"), PromFloat(le).to_string()));
+
+  if !labels.is_empty() || le.is_some() {
+    let labels = labels.iter().map(|(k, v)| (*k, Cow::Borrowed(v.as_str())));
+    let le = le.into_iter().map(|(k, v)| (k, Cow::Owned(v)));
+
+    out.push('{');
+
+    for (i, (name, value)) in labels.chain(le).enumerate() {
+      if i > 0 {
+        out.push(',');
+      }
+
+      write!(out, "{}=\"{}\"", name, escape(&value, true)).unwrap();
+    }
+
+    out.push('}');
+  }
+
+  writeln!(out, " {}", value).unwrap();
+}
+
+/// Escapes backslashes and newlines, and optionally double quotes, for the
+/// Prometheus text format.
+fn escape(value: &str, quotes: bool) -> Cow<'_, str> {
+  if !value.contains(|c| c == '\\' || c == '\n' || (quotes && c == '"')) {
+    return value.into();
+  }
+
+  let mut escaped = String::with_capacity(value.len() + 8);
+
+  for c in value.chars() {
+    match c {
+      '\\' => escaped.push_str("\\\\"),
+      '\n' => escaped.push_str("\\n"),
+      '"' if quotes => escaped.push_str("\\\""),
+      c => escaped.push(c),
+    }
+  }
+
+  escaped.into()
+}
+
+/// Formats a float in the Prometheus text format.
+struct PromFloat(f64);
+
+impl Display for PromFloat {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.0 {
+      v if v.is_nan() => f.write_str("NaN"),
+      v if v == f64::INFINITY => f.write_str("+Inf"),
+      v if v == f64::NEG_INFINITY => f.write_str("-Inf"),
+      v => write!(f, "{}", v),
+    }
+  }
+}
@@ -0,0 +1,183 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Async TCP, UDP, and Unix domain sockets.
+//!
+//! Sockets are registered with the runtime's I/O reactor. Functions that take
+//! an address as a string accept either a socket address such as
+//! `127.0.0.1:80` or a host name and port such as `localhost:80`, which is
+//! resolved with [`resolve()`].
+
+mod tcp;
+mod udp;
+
+#[cfg(unix)]
+mod unix;
+
+pub use self::tcp::{TcpListener, TcpStream};
+pub use self::udp::UdpSocket;
+pub use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr};
+
+#[cfg(unix)]
+pub use self::unix::{UnixListener, UnixStream};
+
+use crate::prelude::*;
+use std::io;
+use std::net::ToSocketAddrs as _;
+
+/// Resolves a host name and port such as `localhost:80` into a list of socket
+/// addresses.
+///
+/// Host names are resolved on a background thread. If the address is already a
+/// socket address, it is returned without a lookup.
+pub async fn resolve(addr: &str) -> Result<Vec<SocketAddr>> {
+  if let Ok(addr) = addr.parse() {
+    return Ok(vec![addr]);
+  }
+
+  let addr = addr.to_string();
+
+  future::unblock! {
+    let addrs: Vec<_> = addr.to_socket_addrs().map_err(failed("resolve", &addr))?.collect();
+
+    if addrs.is_empty() {
+      fail!("Failed to resolve `{}`. No addresses found.", addr);
+    }
+
+    Ok(addrs)
+  }
+}
+
+/// Resolves an address and then runs an operation on each resolved socket
+/// address until one succeeds.
+///
+/// If every attempt fails, the last error is returned.
+async fn try_each<T, F>(action: &str, addr: &str, mut op: impl FnMut(SocketAddr) -> F) -> Result<T>
+where
+  F: Future<Output = io::Result<T>>,
+{
+  let mut last_err = None;
+
+  for resolved in resolve(addr).await? {
+    match op(resolved).await {
+      Ok(value) => return Ok(value),
+      Err(err) => last_err = Some(err),
+    }
+  }
+
+  match last_err {
+    Some(err) => Err(failed(action, addr)(err)),
+    None => fail!("Failed to resolve `{}`. No addresses found.", addr),
+  }
+}
+
+/// Runs a future that connects a socket, failing if it does not complete
+/// within the given timeout.
+async fn connect_timeout<T>(
+  addr: &str,
+  timeout: Duration,
+  connect: impl Future<Output = Result<T>>,
+) -> Result<T> {
+  future::race(connect, async {
+    future::sleep(timeout).await;
+
+    fail!("Failed to connect to `{}`. Timed out after {:?}.", addr, timeout.to_std());
+  })
+  .await
+}
+
+/// Returns a function that describes an IO error that occurred while
+/// performing an action on an address.
+fn failed<'a>(
+  action: &'a str,
+  addr: impl Display + 'a,
+) -> impl FnOnce(io::Error) -> fail::Error + 'a {
+  move |err| fail::Error::join(format_args!("Failed to {} `{}`", action, addr), err)
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::thread;
+  use futures_lite::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+  #[test]
+  fn test_resolve() {
+    thread::block_on(async {
+      assert_eq!(resolve("127.0.0.1:80").await.unwrap(), [SocketAddr::from(([127, 0, 0, 1], 80))]);
+      assert!(resolve("localhost:80").await.unwrap().iter().all(|addr| addr.ip().is_loopback()));
+
+      assert_eq!(
+        resolve("localhost").await.unwrap_err().to_string(),
+        "Failed to resolve `localhost`. Invalid socket address."
+      );
+    });
+  }
+
+  #[test]
+  fn test_tcp() {
+    thread::block_on(async {
+      let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+      let addr = listener.local_addr().unwrap().to_string();
+
+      let server = async {
+        let mut incoming = listener.incoming();
+        let mut stream = incoming.next().await.unwrap().unwrap();
+        let mut buf = [0; 5];
+
+        stream.read_exact(&mut buf).await.unwrap();
+        stream.write_all(&buf).await.unwrap();
+      };
+
+      let client = async {
+        let mut stream = TcpStream::connect_timeout(&addr, Duration::secs(5)).await.unwrap();
+        let mut buf = Vec::new();
+
+        stream.write_all(b"hello").await.unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+        stream.read_to_end(&mut buf).await.unwrap();
+
+        buf
+      };
+
+      let ((), echoed) = future::join(server, client).await;
+
+      assert_eq!(echoed, b"hello");
+    });
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_unix() {
+    thread::block_on(async {
+      let (mut a, mut b) = UnixStream::pair().unwrap();
+      let mut buf = [0; 4];
+
+      a.write_all(b"ping").await.unwrap();
+      b.read_exact(&mut buf).await.unwrap();
+
+      assert_eq!(&buf, b"ping");
+    });
+  }
+
+  #[test]
+  fn test_udp() {
+    thread::block_on(async {
+      let a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+      let b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+      let mut buf = [0; 8];
+
+      a.send_to(b"ping", b.local_addr().unwrap()).await.unwrap();
+
+      let (len, from) = b.recv_from(&mut buf).await.unwrap();
+
+      assert_eq!(&buf[..len], b"ping");
+      assert_eq!(from, a.local_addr().unwrap());
+    });
+  }
+}
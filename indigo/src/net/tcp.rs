@@ -0,0 +1,161 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{failed, Shutdown, SocketAddr};
+use crate::prelude::*;
+use async_io::Async;
+use futures_lite::io::{AsyncRead, AsyncWrite};
+use std::io;
+use std::task::{Context, Poll};
+
+/// A TCP socket listening for connections.
+#[derive(Debug)]
+pub struct TcpListener {
+  io: Async<std::net::TcpListener>,
+}
+
+/// A TCP connection.
+#[derive(Debug)]
+pub struct TcpStream {
+  io: Async<std::net::TcpStream>,
+}
+
+impl TcpListener {
+  /// Binds a new listener to the given address.
+  ///
+  /// If the address resolves to multiple socket addresses, each is tried in
+  /// order until one succeeds. Use port `0` to bind to any available port.
+  pub async fn bind(addr: &str) -> Result<Self> {
+    let bind = |addr| async move { Async::<std::net::TcpListener>::bind(addr) };
+    let io = super::try_each("bind to", addr, bind).await?;
+
+    Ok(Self { io })
+  }
+
+  /// Waits for and accepts a new connection.
+  pub async fn accept(&self) -> Result<(TcpStream, SocketAddr)> {
+    let (io, addr) = self.io.accept().await.map_err(|err| self.failed(err))?;
+
+    Ok((TcpStream { io }, addr))
+  }
+
+  /// Returns a stream of accepted connections.
+  ///
+  /// The stream never ends, but it may return errors.
+  pub fn incoming(&self) -> impl Stream<Item = Result<TcpStream>> + Send + Unpin + '_ {
+    self.io.incoming().map(move |result| match result {
+      Ok(io) => Ok(TcpStream { io }),
+      Err(err) => Err(self.failed(err)),
+    })
+  }
+
+  /// Returns the local address the listener is bound to.
+  pub fn local_addr(&self) -> Result<SocketAddr> {
+    Ok(self.io.get_ref().local_addr()?)
+  }
+
+  /// Describes an IO error that occurred while accepting a connection.
+  fn failed(&self, err: io::Error) -> fail::Error {
+    match self.local_addr() {
+      Ok(addr) => failed("accept a connection on", addr)(err),
+      Err(_) => fail::Error::join("Failed to accept a connection", err),
+    }
+  }
+}
+
+impl TcpStream {
+  /// Connects to the given address.
+  ///
+  /// If the address resolves to multiple socket addresses, each is tried in
+  /// order until one succeeds.
+  pub async fn connect(addr: &str) -> Result<Self> {
+    let io = super::try_each("connect to", addr, Async::<std::net::TcpStream>::connect).await?;
+
+    Ok(Self { io })
+  }
+
+  /// Connects to the given address, failing if the connection is not
+  /// established within the given timeout.
+  ///
+  /// The timeout includes the time spent resolving the address.
+  pub async fn connect_timeout(addr: &str, timeout: Duration) -> Result<Self> {
+    super::connect_timeout(addr, timeout, Self::connect(addr)).await
+  }
+
+  /// Returns the local address of the connection.
+  pub fn local_addr(&self) -> Result<SocketAddr> {
+    Ok(self.io.get_ref().local_addr()?)
+  }
+
+  /// Returns the remote address of the connection.
+  pub fn peer_addr(&self) -> Result<SocketAddr> {
+    Ok(self.io.get_ref().peer_addr()?)
+  }
+
+  /// Receives data without removing it from the queue of incoming data.
+  ///
+  /// Returns the number of bytes read.
+  pub async fn peek(&self, buf: &mut [u8]) -> Result<usize> {
+    Ok(self.io.peek(buf).await?)
+  }
+
+  /// Sets whether the `TCP_NODELAY` option is enabled, which disables Nagle's
+  /// algorithm.
+  pub fn set_nodelay(&self, nodelay: bool) -> Result {
+    Ok(self.io.get_ref().set_nodelay(nodelay)?)
+  }
+
+  /// Shuts down the read half, write half, or both halves of the connection.
+  pub fn shutdown(&self, how: Shutdown) -> Result {
+    Ok(self.io.get_ref().shutdown(how)?)
+  }
+}
+
+// Implement async IO traits.
+
+impl AsyncRead for TcpStream {
+  fn poll_read(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context,
+    buf: &mut [u8],
+  ) -> Poll<io::Result<usize>> {
+    Pin::new(&mut self.io).poll_read(cx, buf)
+  }
+}
+
+impl AsyncRead for &TcpStream {
+  fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+    Pin::new(&mut &self.io).poll_read(cx, buf)
+  }
+}
+
+impl AsyncWrite for TcpStream {
+  fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+    Pin::new(&mut self.io).poll_write(cx, buf)
+  }
+
+  fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+    Pin::new(&mut self.io).poll_flush(cx)
+  }
+
+  fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+    Pin::new(&mut self.io).poll_close(cx)
+  }
+}
+
+impl AsyncWrite for &TcpStream {
+  fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+    Pin::new(&mut &self.io).poll_write(cx, buf)
+  }
+
+  fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+    Pin::new(&mut &self.io).poll_flush(cx)
+  }
+
+  fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+    Pin::new(&mut &self.io).poll_close(cx)
+  }
+}
@@ -0,0 +1,82 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::SocketAddr;
+use crate::prelude::*;
+use async_io::Async;
+
+/// A UDP socket.
+#[derive(Debug)]
+pub struct UdpSocket {
+  io: Async<std::net::UdpSocket>,
+}
+
+impl UdpSocket {
+  /// Binds a new socket to the given address.
+  ///
+  /// If the address resolves to multiple socket addresses, each is tried in
+  /// order until one succeeds. Use port `0` to bind to any available port.
+  pub async fn bind(addr: &str) -> Result<Self> {
+    let bind = |addr| async move { Async::<std::net::UdpSocket>::bind(addr) };
+    let io = super::try_each("bind to", addr, bind).await?;
+
+    Ok(Self { io })
+  }
+
+  /// Connects the socket to a remote address so that [`send()`][Self::send()]
+  /// and [`recv()`][Self::recv()] can be used.
+  ///
+  /// If the address resolves to multiple socket addresses, the first is used.
+  pub async fn connect(&self, addr: &str) -> Result {
+    let socket = self.io.get_ref();
+
+    super::try_each("connect to", addr, |addr| async move { socket.connect(addr) }).await
+  }
+
+  /// Returns the local address the socket is bound to.
+  pub fn local_addr(&self) -> Result<SocketAddr> {
+    Ok(self.io.get_ref().local_addr()?)
+  }
+
+  /// Returns the remote address the socket is connected to.
+  pub fn peer_addr(&self) -> Result<SocketAddr> {
+    Ok(self.io.get_ref().peer_addr()?)
+  }
+
+  /// Receives a datagram from the connected remote address.
+  ///
+  /// Returns the number of bytes read. If the datagram is too long to fit in
+  /// the buffer, the excess bytes are discarded.
+  pub async fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+    Ok(self.io.recv(buf).await?)
+  }
+
+  /// Receives a datagram from any address.
+  ///
+  /// Returns the number of bytes read and the address the datagram came from.
+  pub async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+    Ok(self.io.recv_from(buf).await?)
+  }
+
+  /// Sends a datagram to the connected remote address.
+  ///
+  /// Returns the number of bytes written.
+  pub async fn send(&self, buf: &[u8]) -> Result<usize> {
+    Ok(self.io.send(buf).await?)
+  }
+
+  /// Sends a datagram to the given address.
+  ///
+  /// Returns the number of bytes written.
+  pub async fn send_to(&self, buf: &[u8], addr: impl Into<SocketAddr>) -> Result<usize> {
+    Ok(self.io.send_to(buf, addr).await?)
+  }
+
+  /// Sets whether broadcast datagrams can be sent.
+  pub fn set_broadcast(&self, broadcast: bool) -> Result {
+    Ok(self.io.get_ref().set_broadcast(broadcast)?)
+  }
+}
@@ -0,0 +1,157 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{failed, Shutdown};
+use crate::fs::PathLike;
+use crate::prelude::*;
+use async_io::Async;
+use futures_lite::io::{AsyncRead, AsyncWrite};
+use std::io;
+use std::os::unix::net::SocketAddr;
+use std::task::{Context, Poll};
+
+/// A Unix domain socket listening for connections.
+#[derive(Debug)]
+pub struct UnixListener {
+  io: Async<std::os::unix::net::UnixListener>,
+  path: String,
+}
+
+/// A Unix domain socket connection.
+#[derive(Debug)]
+pub struct UnixStream {
+  io: Async<std::os::unix::net::UnixStream>,
+}
+
+impl UnixListener {
+  /// Binds a new listener to the socket file at the given path.
+  ///
+  /// The socket file must not already exist.
+  pub fn bind<'a>(path: impl PathLike<'a>) -> Result<Self> {
+    let path = path.try_to_owned()?;
+
+    let io = Async::<std::os::unix::net::UnixListener>::bind(&path)
+      .map_err(failed("bind to", fmt::AsPath(&path)))?;
+
+    Ok(Self { io, path })
+  }
+
+  /// Waits for and accepts a new connection.
+  pub async fn accept(&self) -> Result<(UnixStream, SocketAddr)> {
+    let (io, addr) = self.io.accept().await.map_err(|err| self.failed(err))?;
+
+    Ok((UnixStream { io }, addr))
+  }
+
+  /// Returns a stream of accepted connections.
+  ///
+  /// The stream never ends, but it may return errors.
+  pub fn incoming(&self) -> impl Stream<Item = Result<UnixStream>> + Send + Unpin + '_ {
+    self.io.incoming().map(move |result| match result {
+      Ok(io) => Ok(UnixStream { io }),
+      Err(err) => Err(self.failed(err)),
+    })
+  }
+
+  /// Returns the path of the socket file.
+  pub fn path(&self) -> &str {
+    &self.path
+  }
+
+  /// Describes an IO error that occurred while accepting a connection.
+  fn failed(&self, err: io::Error) -> fail::Error {
+    failed("accept a connection on", fmt::AsPath(&self.path))(err)
+  }
+}
+
+impl UnixStream {
+  /// Connects to the socket file at the given path.
+  pub async fn connect<'a>(path: impl PathLike<'a>) -> Result<Self> {
+    let path = path.try_to_cow()?;
+
+    let io = Async::<std::os::unix::net::UnixStream>::connect(&*path)
+      .await
+      .map_err(failed("connect to", fmt::AsPath(&path)))?;
+
+    Ok(Self { io })
+  }
+
+  /// Connects to the socket file at the given path, failing if the connection
+  /// is not established within the given timeout.
+  pub async fn connect_timeout<'a>(path: impl PathLike<'a>, timeout: Duration) -> Result<Self> {
+    let path = path.try_to_cow()?;
+
+    super::connect_timeout(&path, timeout, Self::connect(&*path)).await
+  }
+
+  /// Creates a pair of connected sockets.
+  pub fn pair() -> Result<(Self, Self)> {
+    let (a, b) = Async::<std::os::unix::net::UnixStream>::pair()?;
+
+    Ok((Self { io: a }, Self { io: b }))
+  }
+
+  /// Returns the local address of the connection.
+  pub fn local_addr(&self) -> Result<SocketAddr> {
+    Ok(self.io.get_ref().local_addr()?)
+  }
+
+  /// Returns the remote address of the connection.
+  pub fn peer_addr(&self) -> Result<SocketAddr> {
+    Ok(self.io.get_ref().peer_addr()?)
+  }
+
+  /// Shuts down the read half, write half, or both halves of the connection.
+  pub fn shutdown(&self, how: Shutdown) -> Result {
+    Ok(self.io.get_ref().shutdown(how)?)
+  }
+}
+
+// Implement async IO traits.
+
+impl AsyncRead for UnixStream {
+  fn poll_read(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context,
+    buf: &mut [u8],
+  ) -> Poll<io::Result<usize>> {
+    Pin::new(&mut self.io).poll_read(cx, buf)
+  }
+}
+
+impl AsyncRead for &UnixStream {
+  fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+    Pin::new(&mut &self.io).poll_read(cx, buf)
+  }
+}
+
+impl AsyncWrite for UnixStream {
+  fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+    Pin::new(&mut self.io).poll_write(cx, buf)
+  }
+
+  fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+    Pin::new(&mut self.io).poll_flush(cx)
+  }
+
+  fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+    Pin::new(&mut self.io).poll_close(cx)
+  }
+}
+
+impl AsyncWrite for &UnixStream {
+  fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+    Pin::new(&mut &self.io).poll_write(cx, buf)
+  }
+
+  fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+    Pin::new(&mut &self.io).poll_flush(cx)
+  }
+
+  fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+    Pin::new(&mut &self.io).poll_close(cx)
+  }
+}
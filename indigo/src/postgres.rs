@@ -8,12 +8,19 @@ pub use bytes::BytesMut;
 pub use tokio_postgres::binary_copy;
 pub use tokio_postgres::error::Error;
 pub use tokio_postgres::types::{FromSql, IsNull, ToSql, Type};
-pub use tokio_postgres::{Client as Connection, Config, RowStream, Transaction};
+pub use tokio_postgres::Transaction;
+pub use tokio_postgres::{Client as Connection, Config, Row, RowStream, Statement, ToStatement};
+
+pub use self::pool::{Pool, PoolBuilder, PoolStats, PooledConnection};
+
+mod pool;
 
 use crate::prelude::*;
 use crate::runtime::task;
+use async_trait::async_trait;
 use native_tls::TlsConnector;
 use postgres_native_tls::MakeTlsConnector;
+use tokio_postgres::GenericClient;
 
 /// The type of a query parameter.
 pub type Param<'a> = &'a (dyn ToSql + Sync);
@@ -21,12 +28,63 @@ pub type Param<'a> = &'a (dyn ToSql + Sync);
 pub type FromSqlResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
 pub type ToSqlResult = Result<IsNull, Box<dyn std::error::Error + Send + Sync>>;
 
-/// A generic postgres client, either a connection or a transaction.
-pub trait Client: tokio_postgres::GenericClient + Send + Sync {}
+/// A generic postgres client: a connection, a pooled connection, or a
+/// transaction.
+#[async_trait]
+pub trait Client: Send + Sync {
+  /// Like `Connection::execute`.
+  async fn execute<T>(&self, query: &T, params: &[Param<'_>]) -> Result<u64, Error>
+  where
+    T: ?Sized + ToStatement + Sync + Send;
+
+  /// Like `Connection::execute_raw`.
+  async fn execute_raw<'b, I, T>(&self, statement: &T, params: I) -> Result<u64, Error>
+  where
+    T: ?Sized + ToStatement + Sync + Send,
+    I: IntoIterator<Item = &'b dyn ToSql> + Sync + Send,
+    I::IntoIter: ExactSizeIterator;
+
+  /// Like `Connection::query`.
+  async fn query<T>(&self, query: &T, params: &[Param<'_>]) -> Result<Vec<Row>, Error>
+  where
+    T: ?Sized + ToStatement + Sync + Send;
+
+  /// Like `Connection::query_one`.
+  async fn query_one<T>(&self, statement: &T, params: &[Param<'_>]) -> Result<Row, Error>
+  where
+    T: ?Sized + ToStatement + Sync + Send;
+
+  /// Like `Connection::query_opt`.
+  async fn query_opt<T>(&self, statement: &T, params: &[Param<'_>]) -> Result<Option<Row>, Error>
+  where
+    T: ?Sized + ToStatement + Sync + Send;
+
+  /// Like `Connection::query_raw`.
+  async fn query_raw<'b, T, I>(&self, statement: &T, params: I) -> Result<RowStream, Error>
+  where
+    T: ?Sized + ToStatement + Sync + Send,
+    I: IntoIterator<Item = &'b dyn ToSql> + Sync + Send,
+    I::IntoIter: ExactSizeIterator;
 
-impl<T> Client for T where T: tokio_postgres::GenericClient + Send + Sync {}
+  /// Like `Connection::prepare`.
+  async fn prepare(&self, query: &str) -> Result<Statement, Error>;
 
-/// Returns a connection pool for a database.
+  /// Like `Connection::prepare_typed`.
+  async fn prepare_typed(&self, query: &str, parameter_types: &[Type]) -> Result<Statement, Error>;
+
+  /// Like `Connection::transaction`.
+  async fn transaction(&mut self) -> Result<Transaction<'_>, Error>;
+}
+
+/// A type that implements `Client` by forwarding to a `GenericClient`.
+trait AsGenericClient {
+  type Inner: GenericClient + Send + Sync;
+
+  fn generic(&self) -> &Self::Inner;
+  fn generic_mut(&mut self) -> &mut Self::Inner;
+}
+
+/// Opens a connection to a database.
 pub async fn connect(config: &Config) -> Result<Connection> {
   let tls_connector = TlsConnector::builder()
     .danger_accept_invalid_certs(true)
@@ -35,7 +93,7 @@ pub async fn connect(config: &Config) -> Result<Connection> {
 
   let (client, connection) = config.connect(MakeTlsConnector::new(tls_connector)).await?;
 
-  task::start_detached(async move {
+  task::start_background(async move {
     if let Err(err) = connection.await {
       error!("Postgres connection error — {}.", err);
     }
@@ -43,3 +101,85 @@ pub async fn connect(config: &Config) -> Result<Connection> {
 
   Ok(client)
 }
+
+// Implement `Client` for connections, transactions, and any other
+// `GenericClient`.
+
+impl<G> AsGenericClient for G
+where
+  G: GenericClient + Send + Sync,
+{
+  type Inner = Self;
+
+  fn generic(&self) -> &Self {
+    self
+  }
+
+  fn generic_mut(&mut self) -> &mut Self {
+    self
+  }
+}
+
+#[async_trait]
+impl<C> Client for C
+where
+  C: AsGenericClient + Send + Sync,
+{
+  async fn execute<T>(&self, query: &T, params: &[Param<'_>]) -> Result<u64, Error>
+  where
+    T: ?Sized + ToStatement + Sync + Send,
+  {
+    GenericClient::execute(self.generic(), query, params).await
+  }
+
+  async fn execute_raw<'b, I, T>(&self, statement: &T, params: I) -> Result<u64, Error>
+  where
+    T: ?Sized + ToStatement + Sync + Send,
+    I: IntoIterator<Item = &'b dyn ToSql> + Sync + Send,
+    I::IntoIter: ExactSizeIterator,
+  {
+    GenericClient::execute_raw(self.generic(), statement, params).await
+  }
+
+  async fn query<T>(&self, query: &T, params: &[Param<'_>]) -> Result<Vec<Row>, Error>
+  where
+    T: ?Sized + ToStatement + Sync + Send,
+  {
+    GenericClient::query(self.generic(), query, params).await
+  }
+
+  async fn query_one<T>(&self, statement: &T, params: &[Param<'_>]) -> Result<Row, Error>
+  where
+    T: ?Sized + ToStatement + Sync + Send,
+  {
+    GenericClient::query_one(self.generic(), statement, params).await
+  }
+
+  async fn query_opt<T>(&self, statement: &T, params: &[Param<'_>]) -> Result<Option<Row>, Error>
+  where
+    T: ?Sized + ToStatement + Sync + Send,
+  {
+    GenericClient::query_opt(self.generic(), statement, params).await
+  }
+
+  async fn query_raw<'b, T, I>(&self, statement: &T, params: I) -> Result<RowStream, Error>
+  where
+    T: ?Sized + ToStatement + Sync + Send,
+    I: IntoIterator<Item = &'b dyn ToSql> + Sync + Send,
+    I::IntoIter: ExactSizeIterator,
+  {
+    GenericClient::query_raw(self.generic(), statement, params).await
+  }
+
+  async fn prepare(&self, query: &str) -> Result<Statement, Error> {
+    GenericClient::prepare(self.generic(), query).await
+  }
+
+  async fn prepare_typed(&self, query: &str, parameter_types: &[Type]) -> Result<Statement, Error> {
+    GenericClient::prepare_typed(self.generic(), query, parameter_types).await
+  }
+
+  async fn transaction(&mut self) -> Result<Transaction<'_>, Error> {
+    GenericClient::transaction(self.generic_mut()).await
+  }
+}
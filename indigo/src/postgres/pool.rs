@@ -0,0 +1,661 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{connect, AsGenericClient, Config, Connection};
+use crate::metrics::{self, Counter, Gauge, Histogram};
+use crate::prelude::*;
+use crate::runtime::task;
+use crate::sync::blocking::Mutex;
+use crate::sync::{Permit, Semaphore};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use std::time::Instant;
+
+/// The number of seconds between checks for idle and expired connections.
+const MAINTENANCE_INTERVAL_SECS: u64 = 1;
+
+/// A pool of connections to a database.
+///
+/// Each call to [`get()`][Self::get()] checks out a connection, which returns
+/// to the pool when dropped. At most `max_size` connections can be checked out
+/// at once; other tasks wait for a connection until the acquire timeout.
+///
+/// Idle connections are closed after the idle timeout unless the pool would
+/// have fewer than `min_size` connections, and every connection is closed after
+/// its maximum lifetime. Closed and failing connections are replaced with new
+/// connections as needed.
+#[derive(Clone)]
+pub struct Pool {
+  shared: Arc<Shared>,
+}
+
+/// A builder for a [`Pool`].
+pub struct PoolBuilder {
+  config: Config,
+  name: String,
+  options: Options,
+}
+
+/// Statistics about the connections in a [`Pool`].
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct PoolStats {
+  /// The maximum number of connections that can be checked out at once.
+  pub max_size: usize,
+  /// The number of open connections.
+  pub size: usize,
+  /// The number of open connections waiting in the pool.
+  pub idle: usize,
+  /// The number of open connections that are checked out.
+  pub in_use: usize,
+  /// The number of tasks waiting for a connection.
+  pub waiting: usize,
+  /// The total number of times a task timed out waiting for a connection.
+  pub timeouts: u64,
+}
+
+/// A connection checked out from a [`Pool`], which returns to the pool when
+/// dropped.
+pub struct PooledConnection {
+  conn: Option<Conn>,
+  shared: Arc<Shared>,
+  _permit: Permit,
+}
+
+/// Options for a pool.
+#[derive(Clone, Copy)]
+struct Options {
+  max_size: usize,
+  min_size: usize,
+  acquire_timeout: Duration,
+  idle_timeout: Option<Duration>,
+  max_lifetime: Option<Duration>,
+  health_check: bool,
+}
+
+/// The shared state of a pool.
+struct Shared {
+  config: Config,
+  options: Options,
+  semaphore: Semaphore,
+  idle: Mutex<Vec<Idle>>,
+  counts: Arc<Counts>,
+  metrics: Metrics,
+}
+
+/// The number of open connections and waiting tasks in a pool.
+#[derive(Default)]
+struct Counts {
+  open: AtomicUsize,
+  waiting: AtomicUsize,
+}
+
+/// An open connection, counted by its pool until dropped.
+struct Conn {
+  client: Connection,
+  created_at: Instant,
+  counts: Arc<Counts>,
+}
+
+/// A connection waiting in a pool.
+struct Idle {
+  conn: Conn,
+  since: Instant,
+}
+
+/// Counts a task as waiting for a connection until dropped.
+struct Waiting<'a>(&'a Counts);
+
+/// The metrics reported by a pool.
+struct Metrics {
+  acquire_seconds: Histogram,
+  idle: Gauge,
+  in_use: Gauge,
+  timeouts: Counter,
+  waiting: Gauge,
+}
+
+impl Pool {
+  /// Creates a pool of connections to a database with the default options.
+  ///
+  /// This function must be called from within the runtime.
+  pub fn new(config: Config) -> Self {
+    Self::builder(config).build()
+  }
+
+  /// Returns a builder for a pool of connections to a database.
+  pub fn builder(config: Config) -> PoolBuilder {
+    PoolBuilder {
+      config,
+      name: "default".into(),
+      options: Options {
+        max_size: 10,
+        min_size: 0,
+        acquire_timeout: Duration::secs(30),
+        idle_timeout: Some(Duration::mins(10)),
+        max_lifetime: Some(Duration::mins(30)),
+        health_check: true,
+      },
+    }
+  }
+
+  /// Checks out a connection, waiting for one to be available.
+  ///
+  /// Idle connections are health checked before they are checked out, and a
+  /// new connection is opened if none are usable. Fails if a connection is not
+  /// available within the acquire timeout.
+  pub async fn get(&self) -> Result<PooledConnection> {
+    let shared = &self.shared;
+    let timeout = shared.options.acquire_timeout;
+    let started_at = Instant::now();
+
+    let result = future::race(async { Some(shared.checkout().await) }, async {
+      future::sleep(timeout).await;
+      None
+    })
+    .await;
+
+    shared.metrics.acquire_seconds.observe_duration(started_at.elapsed().into());
+
+    let (conn, permit) = match result {
+      Some(result) => result?,
+
+      None => {
+        shared.metrics.timeouts.increment();
+
+        fail!("Timed out after {:?} waiting for a Postgres connection.", timeout.to_std());
+      }
+    };
+
+    Ok(PooledConnection { conn: Some(conn), shared: shared.clone(), _permit: permit })
+  }
+
+  /// Returns statistics about the connections in the pool.
+  pub fn stats(&self) -> PoolStats {
+    self.shared.stats()
+  }
+}
+
+impl PoolBuilder {
+  /// Sets the name of the pool, which labels its metrics.
+  ///
+  /// The default name is `default`.
+  pub fn name(mut self, name: impl Into<String>) -> Self {
+    self.name = name.into();
+    self
+  }
+
+  /// Sets the maximum number of connections that can be checked out at once.
+  ///
+  /// The default maximum size is `10`.
+  pub fn max_size(mut self, max_size: usize) -> Self {
+    self.options.max_size = max_size;
+    self
+  }
+
+  /// Sets the number of connections the pool keeps open, even when idle.
+  ///
+  /// The default minimum size is `0`.
+  pub fn min_size(mut self, min_size: usize) -> Self {
+    self.options.min_size = min_size;
+    self
+  }
+
+  /// Sets how long [`Pool::get()`] waits for a connection before failing.
+  ///
+  /// The default acquire timeout is 30 seconds.
+  pub fn acquire_timeout(mut self, timeout: Duration) -> Self {
+    self.options.acquire_timeout = timeout;
+    self
+  }
+
+  /// Sets how long a connection can wait in the pool before it is closed, or
+  /// `None` to keep idle connections open.
+  ///
+  /// The default idle timeout is 10 minutes.
+  pub fn idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+    self.options.idle_timeout = timeout;
+    self
+  }
+
+  /// Sets how long a connection can be open before it is closed, or `None` to
+  /// keep connections open indefinitely.
+  ///
+  /// Connections that expire while checked out are closed when they are
+  /// returned. The default maximum lifetime is 30 minutes.
+  pub fn max_lifetime(mut self, lifetime: Option<Duration>) -> Self {
+    self.options.max_lifetime = lifetime;
+    self
+  }
+
+  /// Sets whether idle connections are checked with an empty query before they
+  /// are checked out.
+  ///
+  /// Health checks are enabled by default.
+  pub fn health_check(mut self, enabled: bool) -> Self {
+    self.options.health_check = enabled;
+    self
+  }
+
+  /// Builds the pool.
+  ///
+  /// The pool opens `min_size` connections in the background. This function
+  /// must be called from within the runtime.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `max_size` is zero or less than `min_size`.
+  pub fn build(self) -> Pool {
+    let Self { config, name, options } = self;
+
+    assert!(options.max_size > 0, "Pool size must be greater than zero.");
+    assert!(
+      options.min_size <= options.max_size,
+      "Pool minimum size must not exceed maximum size."
+    );
+
+    let shared = Arc::new(Shared {
+      config,
+      options,
+      semaphore: Semaphore::new(options.max_size),
+      idle: default(),
+      counts: default(),
+      metrics: Metrics::new(&name, options.max_size),
+    });
+
+    task::start_background(maintain(Arc::downgrade(&shared)));
+
+    Pool { shared }
+  }
+}
+
+impl Shared {
+  /// Acquires a permit and then checks out a usable idle connection or opens a
+  /// new connection.
+  async fn checkout(&self) -> Result<(Conn, Permit)> {
+    let permit = {
+      let _waiting = Waiting::new(&self.counts);
+
+      self.semaphore.acquire().await
+    };
+
+    loop {
+      let idle = self.idle.lock().pop();
+
+      match idle {
+        Some(idle) if self.is_usable(&idle.conn).await => return Ok((idle.conn, permit)),
+        Some(_) => continue,
+        None => return Ok((self.open().await?, permit)),
+      }
+    }
+  }
+
+  /// Returns `true` if a connection is open, has not expired, and passes the
+  /// health check.
+  async fn is_usable(&self, conn: &Conn) -> bool {
+    if conn.client.is_closed() || self.is_expired(conn) {
+      return false;
+    }
+
+    if !self.options.health_check {
+      return true;
+    }
+
+    match conn.client.simple_query("").await {
+      Ok(_) => true,
+
+      Err(err) => {
+        warn!("Postgres connection failed a health check — {}.", err);
+        false
+      }
+    }
+  }
+
+  /// Returns `true` if a connection has been open longer than the maximum
+  /// lifetime.
+  fn is_expired(&self, conn: &Conn) -> bool {
+    matches!(self.options.max_lifetime, Some(max) if conn.created_at.elapsed() >= max.to_std())
+  }
+
+  /// Opens a new connection.
+  async fn open(&self) -> Result<Conn> {
+    let client = connect(&self.config).await?;
+
+    self.counts.open.fetch_add(1, Relaxed);
+
+    Ok(Conn { client, created_at: Instant::now(), counts: self.counts.clone() })
+  }
+
+  /// Returns a connection to the pool, or closes it if it is closed, expired,
+  /// or over the maximum size.
+  fn put(&self, conn: Conn) {
+    let is_over_max = self.counts.open.load(Relaxed) > self.options.max_size;
+
+    if is_over_max || conn.client.is_closed() || self.is_expired(&conn) {
+      return;
+    }
+
+    self.idle.lock().push(Idle { conn, since: Instant::now() });
+  }
+
+  /// Closes idle and expired connections and then opens new connections until
+  /// the pool has `min_size` connections.
+  async fn maintain(&self) {
+    let closed = {
+      let mut idle = self.idle.lock();
+
+      let (usable, mut closed): (Vec<_>, Vec<_>) = idle
+        .drain(..)
+        .partition(|idle| !idle.conn.client.is_closed() && !self.is_expired(&idle.conn));
+
+      *idle = usable;
+
+      // Idle connections are in the order they were returned, so the ones that
+      // have been idle the longest are first.
+
+      if let Some(timeout) = self.options.idle_timeout {
+        let open = self.counts.open.load(Relaxed) - closed.len();
+        let excess = open.saturating_sub(self.options.min_size);
+
+        let timed_out =
+          idle.iter().take_while(|idle| idle.since.elapsed() >= timeout.to_std()).count();
+
+        closed.extend(idle.drain(..timed_out.min(excess)));
+      }
+
+      closed
+    };
+
+    drop(closed);
+
+    while self.counts.open.load(Relaxed) < self.options.min_size {
+      let permit = self.semaphore.acquire().await;
+
+      match self.open().await {
+        Ok(conn) => self.put(conn),
+
+        Err(err) => {
+          warn!("Failed to open a Postgres connection. {}", err);
+          break;
+        }
+      }
+
+      permit.release();
+    }
+
+    let stats = self.stats();
+
+    self.metrics.idle.set(stats.idle as f64);
+    self.metrics.in_use.set(stats.in_use as f64);
+    self.metrics.waiting.set(stats.waiting as f64);
+  }
+
+  /// Returns statistics about the connections in the pool.
+  fn stats(&self) -> PoolStats {
+    let idle = self.idle.lock().len();
+    let size = self.counts.open.load(Relaxed);
+
+    PoolStats {
+      max_size: self.options.max_size,
+      size,
+      idle,
+      in_use: size.saturating_sub(idle),
+      waiting: self.counts.waiting.load(Relaxed),
+      timeouts: self.metrics.timeouts.get(),
+    }
+  }
+}
+
+impl<'a> Waiting<'a> {
+  /// Counts a task as waiting until the returned value is dropped.
+  fn new(counts: &'a Counts) -> Self {
+    counts.waiting.fetch_add(1, Relaxed);
+
+    Self(counts)
+  }
+}
+
+impl Metrics {
+  /// Registers the metrics of a pool with the given name.
+  fn new(name: &str, max_size: usize) -> Self {
+    let labels = &[("pool", name)];
+    let connections = |state| {
+      metrics::gauge(
+        "indigo_postgres_pool_connections",
+        "The number of open connections in a Postgres pool.",
+        &[("pool", name), ("state", state)],
+      )
+    };
+
+    metrics::gauge(
+      "indigo_postgres_pool_max_connections",
+      "The maximum number of connections checked out of a Postgres pool.",
+      labels,
+    )
+    .set(max_size as f64);
+
+    Self {
+      acquire_seconds: metrics::histogram(
+        "indigo_postgres_pool_acquire_seconds",
+        "The time spent checking out a connection from a Postgres pool.",
+        labels,
+      ),
+      idle: connections("idle"),
+      in_use: connections("in_use"),
+      timeouts: metrics::counter(
+        "indigo_postgres_pool_timeouts_total",
+        "The number of times a task timed out waiting for a connection from a Postgres pool.",
+        labels,
+      ),
+      waiting: metrics::gauge(
+        "indigo_postgres_pool_waiting",
+        "The number of tasks waiting for a connection from a Postgres pool.",
+        labels,
+      ),
+    }
+  }
+}
+
+/// Maintains a pool in the background until it is dropped.
+async fn maintain(shared: ArcWeak<Shared>) {
+  while let Some(shared) = shared.upgrade() {
+    shared.maintain().await;
+    drop(shared);
+
+    future::sleep(Duration::secs(MAINTENANCE_INTERVAL_SECS)).await;
+  }
+}
+
+// Implement `Client` for pooled connections.
+
+impl AsGenericClient for PooledConnection {
+  type Inner = Connection;
+
+  fn generic(&self) -> &Connection {
+    self
+  }
+
+  fn generic_mut(&mut self) -> &mut Connection {
+    self
+  }
+}
+
+// Implement `Deref` to access the underlying connection.
+
+impl Deref for PooledConnection {
+  type Target = Connection;
+
+  fn deref(&self) -> &Connection {
+    &self.conn.as_ref().expect("Connection was already returned to the pool.").client
+  }
+}
+
+impl DerefMut for PooledConnection {
+  fn deref_mut(&mut self) -> &mut Connection {
+    &mut self.conn.as_mut().expect("Connection was already returned to the pool.").client
+  }
+}
+
+// Implement `Drop` to return connections and update counts.
+
+impl Drop for PooledConnection {
+  fn drop(&mut self) {
+    if let Some(conn) = self.conn.take() {
+      self.shared.put(conn);
+    }
+  }
+}
+
+impl Drop for Conn {
+  fn drop(&mut self) {
+    self.counts.open.fetch_sub(1, Relaxed);
+  }
+}
+
+impl Drop for Waiting<'_> {
+  fn drop(&mut self) {
+    self.0.waiting.fetch_sub(1, Relaxed);
+  }
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::runtime::executor;
+
+  /// Runs a future on the executor with a tokio reactor for connections.
+  fn run<T>(future: impl Future<Output = T>) -> T {
+    let mut tokio = tokio::runtime::Builder::new().enable_all().basic_scheduler().build().unwrap();
+
+    let handle = tokio.handle().clone();
+    let (stop, stopped) = async_channel::bounded::<()>(1);
+
+    let (_, output) =
+      easy_parallel::Parallel::new().add(|| tokio.block_on(stopped.recv())).finish(|| {
+        let output = handle.enter(|| executor().run(future));
+
+        drop(stop);
+        output
+      });
+
+    output
+  }
+
+  /// Starts a server that accepts connections but never responds, and returns
+  /// the config for connecting to it.
+  fn unresponsive_server() -> Config {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut config = Config::new();
+
+    config.host("127.0.0.1").port(listener.local_addr().unwrap().port()).user("indigo");
+
+    std::thread::spawn(move || {
+      let _streams: Vec<_> = listener.incoming().collect();
+    });
+
+    config
+  }
+
+  /// Returns the config of the database in the `INDIGO_TEST_POSTGRES`
+  /// environment variable.
+  fn test_database() -> Config {
+    let config = std::env::var("INDIGO_TEST_POSTGRES")
+      .expect("Set `INDIGO_TEST_POSTGRES` to the config of a test database.");
+
+    config.parse().unwrap()
+  }
+
+  #[test]
+  fn test_acquire_timeout() {
+    let pool = Pool::builder(unresponsive_server())
+      .name("test_acquire_timeout")
+      .acquire_timeout(Duration::secs_f64(0.1));
+
+    run(async {
+      let pool = pool.build();
+
+      assert_eq!(
+        pool.get().await.err().unwrap().to_string(),
+        "Timed out after 100ms waiting for a Postgres connection."
+      );
+
+      assert_eq!(pool.stats().timeouts, 1);
+    });
+  }
+
+  #[test]
+  fn test_max_size() {
+    let pool = Pool::builder(unresponsive_server())
+      .name("test_max_size")
+      .max_size(2)
+      .acquire_timeout(Duration::secs(1));
+
+    run(async {
+      let pool = pool.build();
+
+      // Two tasks open connections, so the third waits for a permit.
+
+      let stats = async {
+        for _ in 0..50 {
+          if pool.stats().waiting > 0 {
+            break;
+          }
+
+          future::sleep(Duration::secs_f64(0.01)).await;
+        }
+
+        pool.stats()
+      };
+
+      let (results, stats) =
+        future::join(future::join_all((0..3).map(|_| pool.get())), stats).await;
+
+      assert_eq!((stats.size, stats.waiting), (0, 1));
+      assert!(results.iter().all(Result::is_err));
+
+      let stats = pool.stats();
+
+      assert_eq!((stats.waiting, stats.timeouts), (0, 3));
+    });
+  }
+
+  /// Requires a database. Run with `INDIGO_TEST_POSTGRES="host=… user=…"
+  /// cargo test --features postgres -- --ignored`.
+  #[test]
+  #[ignore]
+  fn test_health_check() {
+    let config = test_database();
+
+    let pool = Pool::builder(config.clone()).name("test_health_check").max_size(1);
+
+    run(async {
+      let pool = pool.build();
+
+      let backend_pid = |conn: PooledConnection| async move {
+        let row = conn.query_one("SELECT pg_backend_pid()", &[]).await.unwrap();
+
+        row.get::<_, i32>(0)
+      };
+
+      // Healthy connections are reused.
+
+      let pid = backend_pid(pool.get().await.unwrap()).await;
+
+      assert_eq!(backend_pid(pool.get().await.unwrap()).await, pid);
+      assert_eq!(pool.stats().idle, 1);
+
+      // Connections that fail are replaced.
+
+      let other = connect(&config).await.unwrap();
+
+      other.execute("SELECT pg_terminate_backend($1)", &[&pid]).await.unwrap();
+
+      assert_ne!(backend_pid(pool.get().await.unwrap()).await, pid);
+      assert_eq!(pool.stats().size, 1);
+    });
+  }
+}
@@ -18,7 +18,7 @@ pub use {
   crate::log::{self, debug, error, info, trace, warn},
   crate::math::Number,
   crate::random::{self, random, Random},
-  crate::stream::{self, Stream, StreamExt},
+  crate::stream::{self, LiteStreamExt as _, Stream, StreamExt},
   crate::sync::{pin, Lazy},
   crate::time::{self, Date, Duration, Time},
   crate::uuid::{self, Uuid},
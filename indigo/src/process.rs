@@ -0,0 +1,289 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Spawning and managing child processes.
+//!
+//! Child process pipes are registered with the runtime's I/O reactor, so
+//! reading and writing them does not block executor threads.
+
+mod child;
+mod pipe;
+
+pub use self::child::Child;
+pub use self::pipe::{ChildInput, ChildOutput};
+pub use std::process::{ExitStatus, Stdio};
+
+use crate::prelude::*;
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// A builder for spawning a child process.
+pub struct Command {
+  inner: std::process::Command,
+  stdin: Option<Stdio>,
+  stdout: Option<Stdio>,
+  stderr: Option<Stdio>,
+  timeout: Option<Duration>,
+  kill_on_drop: bool,
+  process_group: bool,
+}
+
+/// The output of a finished child process.
+#[derive(Debug)]
+pub struct Output {
+  /// The exit status of the process.
+  pub status: ExitStatus,
+  /// The data the process wrote to stdout.
+  pub stdout: Vec<u8>,
+  /// The data the process wrote to stderr.
+  pub stderr: Vec<u8>,
+}
+
+impl Command {
+  /// Creates a new command for running the given program.
+  ///
+  /// If the program is not a path, it is searched for in the `PATH`
+  /// environment variable.
+  pub fn new(program: impl AsRef<OsStr>) -> Self {
+    Self {
+      inner: std::process::Command::new(program),
+      stdin: None,
+      stdout: None,
+      stderr: None,
+      timeout: None,
+      kill_on_drop: false,
+      process_group: false,
+    }
+  }
+
+  /// Adds an argument to pass to the program.
+  pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+    self.inner.arg(arg);
+    self
+  }
+
+  /// Adds multiple arguments to pass to the program.
+  pub fn args(mut self, args: impl IntoIterator<Item = impl AsRef<OsStr>>) -> Self {
+    self.inner.args(args);
+    self
+  }
+
+  /// Sets the working directory of the process.
+  pub fn current_dir(mut self, path: impl AsRef<Path>) -> Self {
+    self.inner.current_dir(path);
+    self
+  }
+
+  /// Sets an environment variable for the process.
+  pub fn env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+    self.inner.env(key, value);
+    self
+  }
+
+  /// Clears all environment variables the process would inherit.
+  pub fn env_clear(mut self) -> Self {
+    self.inner.env_clear();
+    self
+  }
+
+  /// Removes an environment variable the process would inherit.
+  pub fn env_remove(mut self, key: impl AsRef<OsStr>) -> Self {
+    self.inner.env_remove(key);
+    self
+  }
+
+  /// Sets whether the process is killed when its [`Child`] is dropped.
+  ///
+  /// The default is `false`, in which case the process keeps running.
+  pub fn kill_on_drop(mut self, kill_on_drop: bool) -> Self {
+    self.kill_on_drop = kill_on_drop;
+    self
+  }
+
+  /// Sets whether the process is started in a new process group.
+  ///
+  /// Signals sent with [`Child::signal_group()`] and kills of the process
+  /// also reach any processes it starts in the same group.
+  #[cfg(unix)]
+  pub fn process_group(mut self, process_group: bool) -> Self {
+    self.process_group = process_group;
+    self
+  }
+
+  /// Sets the stdin of the process.
+  pub fn stdin(mut self, stdio: Stdio) -> Self {
+    self.stdin = Some(stdio);
+    self
+  }
+
+  /// Sets the stdout of the process.
+  pub fn stdout(mut self, stdio: Stdio) -> Self {
+    self.stdout = Some(stdio);
+    self
+  }
+
+  /// Sets the stderr of the process.
+  pub fn stderr(mut self, stdio: Stdio) -> Self {
+    self.stderr = Some(stdio);
+    self
+  }
+
+  /// Sets a timeout after which the process is killed while waiting for it.
+  pub fn timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = Some(timeout);
+    self
+  }
+
+  /// Runs the process and waits for it to exit, failing if it does not exit
+  /// successfully.
+  ///
+  /// Unless otherwise configured, the process inherits stdin, stdout, and
+  /// stderr from the current process.
+  pub async fn run(self) -> Result {
+    let mut child = self.spawn()?;
+    let status = child.wait().await?;
+
+    child::check_status(child.command_line(), status, &[])
+  }
+
+  /// Runs the process and collects all of its output, failing if it does not
+  /// exit successfully.
+  ///
+  /// Unless otherwise configured, stdout and stderr are captured and stdin is
+  /// empty. If the process fails, the error message includes its stderr.
+  pub async fn output(mut self) -> Result<Output> {
+    self.stdin = self.stdin.or_else(|| Some(Stdio::null()));
+    self.stdout = self.stdout.or_else(|| Some(Stdio::piped()));
+    self.stderr = self.stderr.or_else(|| Some(Stdio::piped()));
+
+    let output = self.spawn()?.output().await?;
+
+    Ok(output)
+  }
+
+  /// Spawns the process and returns a [`Child`] for managing it.
+  ///
+  /// Unless otherwise configured, the process inherits stdin, stdout, and
+  /// stderr from the current process.
+  pub fn spawn(mut self) -> Result<Child> {
+    let command_line: Arc<str> = describe(&self.inner).into();
+
+    if let Some(stdio) = self.stdin.take() {
+      self.inner.stdin(stdio);
+    }
+
+    if let Some(stdio) = self.stdout.take() {
+      self.inner.stdout(stdio);
+    }
+
+    if let Some(stdio) = self.stderr.take() {
+      self.inner.stderr(stdio);
+    }
+
+    #[cfg(unix)]
+    if self.process_group {
+      std::os::unix::process::CommandExt::process_group(&mut self.inner, 0);
+    }
+
+    let child =
+      self.inner.spawn().map_err(fail::with!("Failed to start command `{}`", command_line))?;
+
+    Child::new(child, command_line, self.timeout, self.kill_on_drop, self.process_group)
+  }
+}
+
+/// Returns a description of the program and arguments of a command, quoting
+/// arguments that contain whitespace or quotes.
+fn describe(command: &std::process::Command) -> String {
+  let mut output = String::new();
+
+  let program = command.get_program();
+  let args = command.get_args();
+
+  for (i, part) in iter::once(program).chain(args).enumerate() {
+    let part = part.to_string_lossy();
+
+    if i > 0 {
+      output.push(' ');
+    }
+
+    match part.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') || part.is_empty() {
+      true => write!(output, "{:?}", part).unwrap(),
+      false => output.push_str(&part),
+    }
+  }
+
+  output
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::thread;
+
+  #[test]
+  fn test_describe() {
+    let command = std::process::Command::new("echo");
+
+    assert_eq!(describe(&command), "echo");
+
+    let mut command = std::process::Command::new("sh");
+
+    command.args(["-c", "echo 'hi there'", ""]);
+
+    assert_eq!(describe(&command), r#"sh -c "echo 'hi there'" """#);
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_output() {
+    thread::block_on(async {
+      let output =
+        Command::new("sh").args(["-c", "echo out; echo err >&2"]).output().await.unwrap();
+
+      assert_eq!(output.stdout, b"out\n");
+      assert_eq!(output.stderr, b"err\n");
+
+      let err = Command::new("sh").args(["-c", "echo bad >&2; exit 3"]).output().await.unwrap_err();
+
+      assert_eq!(
+        err.to_string(),
+        "Command `sh -c \"echo bad >&2; exit 3\"` exited with code 3. Bad."
+      );
+
+      let err =
+        Command::new("sleep").arg("5").timeout(Duration::secs_f64(0.05)).run().await.unwrap_err();
+
+      assert_eq!(err.to_string(), "Command `sleep 5` timed out after 50ms.");
+    });
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_lines() {
+    thread::block_on(async {
+      let mut child = Command::new("sh")
+        .args(["-c", "read x; echo \"$x\"; echo done"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+      let mut stdin = child.stdin.take().unwrap();
+
+      stdin.write_all(b"hello\n").await.unwrap();
+      stdin.close().await.unwrap();
+
+      let lines = child.stdout.take().unwrap().lines();
+      let lines: Vec<String> = lines.map(Result::unwrap).collect().await;
+
+      assert_eq!(lines, ["hello", "done"]);
+      assert!(child.wait().await.unwrap().success());
+    });
+  }
+}
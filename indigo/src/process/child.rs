@@ -0,0 +1,310 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{ChildInput, ChildOutput, ExitStatus, Output};
+use crate::prelude::*;
+use futures_lite::io::AsyncReadExt as _;
+use std::time::Instant;
+
+/// The longest delay between checks of whether a child process has exited.
+const MAX_POLL_DELAY: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// A running child process.
+pub struct Child {
+  inner: std::process::Child,
+  command_line: Arc<str>,
+  timeout: Option<Duration>,
+  deadline: Option<Instant>,
+  kill_on_drop: bool,
+  process_group: bool,
+  status: Option<ExitStatus>,
+  /// The stdin of the process, if it is piped.
+  pub stdin: Option<ChildInput>,
+  /// The stdout of the process, if it is piped.
+  pub stdout: Option<ChildOutput>,
+  /// The stderr of the process, if it is piped.
+  pub stderr: Option<ChildOutput>,
+}
+
+impl Child {
+  /// Wraps a spawned child process, registering its pipes with the runtime.
+  pub(super) fn new(
+    mut inner: std::process::Child,
+    command_line: Arc<str>,
+    timeout: Option<Duration>,
+    kill_on_drop: bool,
+    process_group: bool,
+  ) -> Result<Self> {
+    let stdin = inner.stdin.take().map(|io| ChildInput::new(io, &command_line)).transpose();
+    let stdout = inner.stdout.take().map(|io| ChildOutput::new(io, &command_line)).transpose();
+    let stderr = inner.stderr.take().map(|io| ChildOutput::new(io, &command_line)).transpose();
+
+    let mut child = Self {
+      inner,
+      deadline: timeout.map(|timeout| Instant::now() + timeout.to_std()),
+      timeout,
+      kill_on_drop,
+      process_group,
+      status: None,
+      stdin: None,
+      stdout: None,
+      stderr: None,
+      command_line,
+    };
+
+    // Kill the process on failure, since it has no other owner.
+
+    match (stdin, stdout, stderr) {
+      (Ok(stdin), Ok(stdout), Ok(stderr)) => {
+        child.stdin = stdin;
+        child.stdout = stdout;
+        child.stderr = stderr;
+
+        Ok(child)
+      }
+
+      (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => {
+        child.kill_on_drop = true;
+
+        Err(err)
+      }
+    }
+  }
+
+  /// Returns a description of the program and arguments of the process.
+  pub fn command_line(&self) -> &str {
+    &self.command_line
+  }
+
+  /// Returns the OS-assigned process ID of the process.
+  pub fn id(&self) -> u32 {
+    self.inner.id()
+  }
+
+  /// Kills the process.
+  ///
+  /// If the process was started in a new process group, the entire group is
+  /// killed. If the process has already exited, this function does nothing.
+  pub fn kill(&mut self) -> Result {
+    if self.status.is_some() {
+      return Ok(());
+    }
+
+    #[cfg(unix)]
+    if self.process_group {
+      return self.signal_group(libc::SIGKILL);
+    }
+
+    self.inner.kill().map_err(fail::with!("Failed to kill command `{}`", self.command_line))
+  }
+
+  /// Waits for the process to exit while collecting all of its output, failing
+  /// if it does not exit successfully.
+  ///
+  /// Only output from piped stdout and stderr is collected.
+  pub async fn output(mut self) -> Result<Output> {
+    let stdout = self.stdout.take();
+    let stderr = self.stderr.take();
+
+    let outputs = future::join(read_to_end(stdout), read_to_end(stderr));
+    let ((stdout, stderr), status) = future::join(outputs, self.wait()).await;
+
+    let output = Output { status: status?, stdout: stdout?, stderr: stderr? };
+
+    check_status(&self.command_line, output.status, &output.stderr)?;
+
+    Ok(output)
+  }
+
+  /// Sends a signal to the process.
+  ///
+  /// If the process has already exited, this function does nothing.
+  #[cfg(unix)]
+  pub fn signal(&self, signal: i32) -> Result {
+    self.send_signal(signal, false)
+  }
+
+  /// Sends a signal to every process in the process group of the process.
+  ///
+  /// The process must have been started in a new process group with
+  /// [`Command::process_group()`][super::Command::process_group()]. If the
+  /// process has already exited, this function does nothing.
+  #[cfg(unix)]
+  pub fn signal_group(&self, signal: i32) -> Result {
+    self.send_signal(signal, true)
+  }
+
+  /// Waits for the process to exit and returns its exit status.
+  ///
+  /// If a timeout was set and the process does not exit in time, it is killed
+  /// and this function fails. The stdin of the process is closed before
+  /// waiting to prevent deadlocks.
+  pub async fn wait(&mut self) -> Result<ExitStatus> {
+    drop(self.stdin.take());
+
+    // Check whether the process has exited with exponential backoff, similar to
+    // how `fs::Lock` waits for a lock.
+
+    let mut delay = std::time::Duration::from_millis(1);
+
+    loop {
+      if let Some(status) = self.try_wait()? {
+        return Ok(status);
+      }
+
+      let mut sleep_for = delay;
+
+      if let Some(deadline) = self.deadline {
+        let now = Instant::now();
+
+        if now >= deadline {
+          return Err(self.time_out());
+        }
+
+        sleep_for = cmp::min(sleep_for, deadline - now);
+      }
+
+      future::sleep(sleep_for.into()).await;
+
+      delay = cmp::min(delay * 2, MAX_POLL_DELAY);
+    }
+  }
+
+  /// Returns the exit status of the process if it has exited.
+  pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+    if self.status.is_none() {
+      self.status = self
+        .inner
+        .try_wait()
+        .map_err(fail::with!("Failed to wait for command `{}`", self.command_line))?;
+    }
+
+    Ok(self.status)
+  }
+
+  /// Sends a signal to the process or its process group.
+  #[cfg(unix)]
+  fn send_signal(&self, signal: i32, group: bool) -> Result {
+    if self.status.is_some() {
+      return Ok(());
+    }
+
+    let pid = self.inner.id() as libc::pid_t;
+
+    let result = match group {
+      true => unsafe { libc::killpg(pid, signal) },
+      false => unsafe { libc::kill(pid, signal) },
+    };
+
+    match result {
+      0 => Ok(()),
+
+      _ => Err(fail::Error::join(
+        format_args!("Failed to send signal {} to command `{}`", signal, self.command_line),
+        std::io::Error::last_os_error(),
+      )),
+    }
+  }
+
+  /// Kills and reaps the process after its timeout elapses and returns an
+  /// error describing the timeout.
+  fn time_out(&mut self) -> fail::Error {
+    if let Err(err) = self.kill() {
+      return err;
+    }
+
+    if let Ok(status) = self.inner.wait() {
+      self.status = Some(status);
+    }
+
+    fail::err!(
+      "Command `{}` timed out after {:?}.",
+      self.command_line,
+      self.timeout.unwrap_or_default().to_std()
+    )
+  }
+}
+
+/// Returns an error if the given exit status is not successful.
+///
+/// If any stderr output is given, it is included in the error message.
+pub(super) fn check_status(command_line: &str, status: ExitStatus, stderr: &[u8]) -> Result {
+  if status.success() {
+    return Ok(());
+  }
+
+  let message = match status.code() {
+    Some(code) => format!("Command `{}` exited with code {}", command_line, code),
+    None => format!("Command `{}` was terminated by {}", command_line, describe_signal(status)),
+  };
+
+  let stderr = String::from_utf8_lossy(stderr);
+
+  match stderr.trim() {
+    "" => Err(fail::Error::new(message)),
+    stderr => Err(fail::Error::join(message, stderr)),
+  }
+}
+
+/// Describes the signal that terminated a process.
+#[cfg(unix)]
+fn describe_signal(status: ExitStatus) -> String {
+  use std::os::unix::process::ExitStatusExt as _;
+
+  match status.signal() {
+    Some(signal) => format!("signal {}", signal),
+    None => "a signal".into(),
+  }
+}
+
+/// Describes the signal that terminated a process.
+#[cfg(not(unix))]
+fn describe_signal(_: ExitStatus) -> String {
+  "a signal".into()
+}
+
+/// Reads all data from an optional child process output.
+async fn read_to_end(output: Option<ChildOutput>) -> Result<Vec<u8>> {
+  let mut buf = Vec::new();
+
+  if let Some(mut output) = output {
+    output.read_to_end(&mut buf).await.map_err(|err| output.failed(err))?;
+  }
+
+  Ok(buf)
+}
+
+// Implement `Drop` to kill the process if configured.
+
+impl Drop for Child {
+  fn drop(&mut self) {
+    if !self.kill_on_drop || self.status.is_some() {
+      return;
+    }
+
+    if let Err(err) = self.kill() {
+      warn!("{}", err);
+      return;
+    }
+
+    // Reap the process so it does not remain a zombie. This is quick because
+    // the process was just killed.
+
+    let _ = self.inner.wait();
+  }
+}
+
+// Implement formatting.
+
+impl Debug for Child {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("Child")
+      .field("id", &self.id())
+      .field("command_line", &self.command_line)
+      .field("status", &self.status)
+      .finish()
+  }
+}
@@ -0,0 +1,170 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::prelude::*;
+use futures_lite::io::{self, AsyncBufReadExt as _, AsyncRead, AsyncReadExt as _, AsyncWrite};
+use futures_lite::io::{AsyncWriteExt as _, BufReader};
+use std::task::{Context, Poll};
+
+/// The size of the buffer used to read chunks of output.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// The piped stdin of a child process.
+pub struct ChildInput {
+  io: Pin<Box<dyn AsyncWrite + Send>>,
+  command_line: Arc<str>,
+}
+
+/// The piped stdout or stderr of a child process.
+pub struct ChildOutput {
+  io: Pin<Box<dyn AsyncRead + Send>>,
+  command_line: Arc<str>,
+}
+
+impl ChildInput {
+  /// Registers a child process pipe with the runtime.
+  pub(super) fn new(io: std::process::ChildStdin, command_line: &Arc<str>) -> Result<Self> {
+    Ok(Self { io: Box::pin(register(io, command_line)?), command_line: command_line.clone() })
+  }
+
+  /// Closes the pipe, signaling the end of input to the process.
+  pub async fn close(&mut self) -> Result {
+    self.io.close().await.map_err(|err| self.failed(err))
+  }
+
+  /// Writes all of the given data to the pipe.
+  pub async fn write_all(&mut self, data: &[u8]) -> Result {
+    self.io.write_all(data).await.map_err(|err| self.failed(err))
+  }
+
+  /// Describes an IO error that occurred while writing to the pipe.
+  fn failed(&self, err: io::Error) -> fail::Error {
+    fail::Error::join(format_args!("Failed to write to command `{}`", self.command_line), err)
+  }
+}
+
+impl ChildOutput {
+  /// Registers a child process pipe with the runtime.
+  pub(super) fn new<T>(io: T, command_line: &Arc<str>) -> Result<Self>
+  where
+    T: std::io::Read + AsRawPipe + Send + 'static,
+  {
+    Ok(Self { io: Box::pin(register(io, command_line)?), command_line: command_line.clone() })
+  }
+
+  /// Returns a stream of chunks of output as they are received.
+  pub fn chunks(self) -> impl Stream<Item = Result<Vec<u8>>> + Send + Unpin {
+    Box::pin(stream::unfold(Some(self), |output| async move {
+      let mut output = output?;
+      let mut buf = vec![0; CHUNK_SIZE];
+
+      match output.read(&mut buf).await {
+        Ok(0) => None,
+
+        Ok(len) => {
+          buf.truncate(len);
+          Some((Ok(buf), Some(output)))
+        }
+
+        Err(err) => Some((Err(output.failed(err)), None)),
+      }
+    }))
+  }
+
+  /// Returns a stream of lines of output without line endings.
+  pub fn lines(self) -> impl Stream<Item = Result<String>> + Send + Unpin {
+    let command_line = self.command_line.clone();
+
+    BufReader::new(self).lines().map(move |line| {
+      line.map_err(|err| {
+        fail::Error::join(format_args!("Failed to read from command `{}`", command_line), err)
+      })
+    })
+  }
+
+  /// Describes an IO error that occurred while reading from the pipe.
+  pub(super) fn failed(&self, err: io::Error) -> fail::Error {
+    fail::Error::join(format_args!("Failed to read from command `{}`", self.command_line), err)
+  }
+}
+
+/// A trait for child process pipes that can be registered with the runtime.
+#[cfg(unix)]
+pub(super) trait AsRawPipe: std::os::unix::io::AsRawFd {}
+
+#[cfg(unix)]
+impl<T: std::os::unix::io::AsRawFd> AsRawPipe for T {}
+
+/// A trait for child process pipes that can be registered with the runtime.
+#[cfg(not(unix))]
+pub(super) trait AsRawPipe {}
+
+#[cfg(not(unix))]
+impl<T> AsRawPipe for T {}
+
+/// Registers a pipe with the I/O reactor of the runtime.
+///
+/// On platforms where pipes cannot be polled, blocking operations on the pipe
+/// run on a thread pool instead.
+#[cfg(unix)]
+fn register<T>(io: T, command_line: &str) -> Result<async_io::Async<T>>
+where
+  T: AsRawPipe,
+{
+  async_io::Async::new(io).map_err(fail::with!("Failed to open pipe to command `{}`", command_line))
+}
+
+/// Registers a pipe with the I/O reactor of the runtime.
+///
+/// On platforms where pipes cannot be polled, blocking operations on the pipe
+/// run on a thread pool instead.
+#[cfg(not(unix))]
+fn register<T>(io: T, _: &str) -> Result<blocking::Unblock<T>>
+where
+  T: Send + 'static,
+{
+  Ok(blocking::Unblock::new(io))
+}
+
+// Implement async IO traits.
+
+impl AsyncRead for ChildOutput {
+  fn poll_read(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context,
+    buf: &mut [u8],
+  ) -> Poll<io::Result<usize>> {
+    self.io.as_mut().poll_read(cx, buf)
+  }
+}
+
+impl AsyncWrite for ChildInput {
+  fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+    self.io.as_mut().poll_write(cx, buf)
+  }
+
+  fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+    self.io.as_mut().poll_flush(cx)
+  }
+
+  fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+    self.io.as_mut().poll_close(cx)
+  }
+}
+
+// Implement formatting.
+
+impl Debug for ChildInput {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("ChildInput").field("command_line", &self.command_line).finish()
+  }
+}
+
+impl Debug for ChildOutput {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("ChildOutput").field("command_line", &self.command_line).finish()
+  }
+}
@@ -6,19 +6,72 @@
 
 //! Contains the main entry point code for running Indigo applications.
 
+pub mod admin;
 pub mod logger;
 pub mod task;
 
 pub use indigo_proc_macros::runtime_main as main;
 
+use crate::metrics::{self, Counter, Gauge};
 use crate::prelude::*;
-use crate::sync::AtomicBool;
+use crate::sync::{AtomicBool, CancellationToken};
 use crate::thread;
 use async_executor::Executor;
 use easy_parallel::Parallel;
 use event_listener::Event;
 use std::process::exit;
 
+/// Statistics about the runtime.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct Stats {
+  /// The number of executor threads, or `0` if the runtime is not running.
+  pub executor_threads: usize,
+  /// The number of tasks that have started and not yet completed or stopped.
+  pub tasks_running: usize,
+  /// The total number of tasks started.
+  pub tasks_started: u64,
+}
+
+/// Metrics of the runtime.
+struct Metrics {
+  executor_threads: Gauge,
+  tasks_running: Gauge,
+  tasks_started: Counter,
+}
+
+/// The number of seconds to wait for tasks to complete after the root
+/// cancellation token is cancelled.
+const DRAIN_TIMEOUT_SECS: u64 = 5;
+
+/// The root cancellation token of the runtime, cancelled when the runtime
+/// shuts down.
+static ROOT_TOKEN: Lazy<CancellationToken> = Lazy::new(default);
+
+/// Metrics of the runtime, registered in the global metrics registry.
+static METRICS: Lazy<Metrics> = Lazy::new(|| Metrics {
+  executor_threads: metrics::gauge(
+    "indigo_runtime_executor_threads",
+    "Number of executor threads.",
+    &[],
+  ),
+  tasks_running: metrics::gauge(
+    "indigo_runtime_tasks_running",
+    "Number of tasks that have started and not yet completed.",
+    &[],
+  ),
+  tasks_started: metrics::counter(
+    "indigo_runtime_tasks_started_total",
+    "Total number of tasks started.",
+    &[],
+  ),
+});
+
+/// Returns a new cancellation token that is cancelled when the runtime shuts
+/// down.
+pub fn cancellation_token() -> CancellationToken {
+  ROOT_TOKEN.child()
+}
+
 /// Runs the indigo runtime until the given future completes, then exits the
 /// process.
 pub fn run(future: impl Future<Output = Result> + Send + 'static) -> ! {
@@ -37,6 +90,8 @@ pub fn run(future: impl Future<Output = Result> + Send + 'static) -> ! {
   let shutdown = Event::new();
   let threads = num_cpus::get();
 
+  METRICS.executor_threads.set(threads as f64);
+
   #[cfg(not(feature = "tokio-compat"))]
   let (_, result) = {
     Parallel::new()
@@ -86,6 +141,15 @@ pub fn run(future: impl Future<Output = Result> + Send + 'static) -> ! {
   exit(0)
 }
 
+/// Returns statistics about the runtime.
+pub fn stats() -> Stats {
+  Stats {
+    executor_threads: METRICS.executor_threads.get() as usize,
+    tasks_running: METRICS.tasks_running.get() as usize,
+    tasks_started: METRICS.tasks_started.get(),
+  }
+}
+
 /// Returns a reference to the async executor.
 pub(crate) fn executor() -> &'static Executor {
   static EXECUTOR: Lazy<Executor> = Lazy::new(default);
@@ -94,6 +158,39 @@ pub(crate) fn executor() -> &'static Executor {
 }
 
 /// Runs the main thread.
+///
+/// The admin server is started if it is configured. When the main future
+/// completes, the root cancellation token is cancelled and running tasks are
+/// given a few seconds to complete.
 fn main(future: impl Future<Output = Result> + Send + 'static) -> Result {
-  thread::block_on(future)
+  admin::start_from_env();
+
+  let result = thread::block_on(future);
+
+  ROOT_TOKEN.cancel();
+
+  let drained = thread::block_on(future::race(
+    async {
+      task::drained().await;
+      true
+    },
+    async {
+      future::sleep(Duration::secs(DRAIN_TIMEOUT_SECS)).await;
+      false
+    },
+  ));
+
+  if !drained {
+    // Write directly to stderr because the logger may not get a chance to
+    // output anything before the process exits.
+
+    let _ = writeln!(
+      console::Term::stderr(),
+      "Stopped waiting for {} running tasks after {}s.",
+      task::draining(),
+      DRAIN_TIMEOUT_SECS
+    );
+  }
+
+  result
 }
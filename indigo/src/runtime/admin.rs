@@ -0,0 +1,211 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! An admin HTTP server that reports health checks and runtime statistics.
+//!
+//! If the `INDIGO_ADMIN_PORT` environment variable is set, [`run()`] starts
+//! the admin server on that port of the loopback interface. It has the
+//! following endpoints:
+//!
+//! - `GET /health/live` runs the checks added with [`add_liveness_check()`].
+//! - `GET /health/ready` runs the checks added with [`add_readiness_check()`].
+//! - `GET /metrics` reports the global [metrics registry][crate::metrics] in
+//!   the Prometheus text format.
+//! - `GET /metrics.json` reports the same metrics as JSON.
+//!
+//! Health endpoints respond with `200 OK` if every check passes and `503
+//! Service Unavailable` otherwise, with the result of each check in a JSON
+//! body.
+//!
+//! [`run()`]: super::run()
+
+use super::task;
+use crate::env;
+use crate::http::{self, Response, Router, Server, Status};
+use crate::metrics;
+use crate::prelude::*;
+use crate::sync::blocking::RwLock;
+
+/// The environment variable that enables the admin server on a port.
+const PORT_VAR: &str = "INDIGO_ADMIN_PORT";
+
+/// The number of seconds a health check can take before it fails.
+const CHECK_TIMEOUT_SECS: u64 = 5;
+
+/// A boxed health check.
+type Check = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = Result> + Send>> + Send + Sync>;
+
+/// The kind of a health check.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Kind {
+  Liveness,
+  Readiness,
+}
+
+/// The registered health checks.
+static CHECKS: Lazy<RwLock<Vec<(Kind, String, Check)>>> = Lazy::new(default);
+
+/// Adds a liveness check, which fails if the process should be restarted.
+pub fn add_liveness_check<F, R>(name: impl Into<String>, check: F)
+where
+  F: Fn() -> R + Send + Sync + 'static,
+  R: Future<Output = Result> + Send + 'static,
+{
+  add_check(Kind::Liveness, name.into(), check);
+}
+
+/// Adds a readiness check, which fails if the process cannot currently handle
+/// work, such as while a database is unreachable.
+pub fn add_readiness_check<F, R>(name: impl Into<String>, check: F)
+where
+  F: Fn() -> R + Send + Sync + 'static,
+  R: Future<Output = Result> + Send + 'static,
+{
+  add_check(Kind::Readiness, name.into(), check);
+}
+
+/// Returns a router that handles the admin endpoints.
+///
+/// Use this to serve the admin endpoints from a custom server.
+pub fn router() -> Router {
+  Router::new()
+    .get("/health/live", |_| health(Kind::Liveness))
+    .get("/health/ready", |_| health(Kind::Readiness))
+    .get("/metrics", |_| async {
+      let text = metrics::registry().to_prometheus();
+
+      Ok::<_, http::Error>(
+        Response::text(text)
+          .with_header("Content-Type", "text/plain; version=0.0.4; charset=utf-8"),
+      )
+    })
+    .get("/metrics.json", |_| async { Response::json(&metrics::registry().snapshot()) })
+}
+
+/// Runs an admin server on the given address until the runtime shuts down.
+pub async fn serve(addr: &str) -> Result {
+  Server::bind(addr, router()).await?.run().await
+}
+
+/// Starts the admin server in the background if the `INDIGO_ADMIN_PORT`
+/// environment variable is set.
+pub(super) fn start_from_env() {
+  let port = match env::var_parsed::<u16>(PORT_VAR) {
+    Ok(Some(port)) => port,
+    Ok(None) => return,
+
+    Err(err) => {
+      error!("Failed to start the admin server. {}", err);
+      return;
+    }
+  };
+
+  task::start_detached(async move {
+    if let Err(err) = serve(&format!("127.0.0.1:{}", port)).await {
+      error!("Failed to start the admin server. {}", err);
+    }
+  });
+}
+
+/// Adds a health check of the given kind.
+fn add_check<F, R>(kind: Kind, name: String, check: F)
+where
+  F: Fn() -> R + Send + Sync + 'static,
+  R: Future<Output = Result> + Send + 'static,
+{
+  let check: Check = Arc::new(move || Box::pin(check()));
+
+  CHECKS.write().push((kind, name, check));
+}
+
+/// Runs all health checks of the given kind concurrently and responds with
+/// their results.
+async fn health(kind: Kind) -> Result<Response, http::Error> {
+  let checks: Vec<_> = CHECKS
+    .read()
+    .iter()
+    .filter(|(k, ..)| *k == kind)
+    .map(|(_, name, check)| (name.clone(), check.clone()))
+    .collect();
+
+  let results = future::join_all(checks.into_iter().map(|(name, check)| async move {
+    let result = future::race(check(), async {
+      future::sleep(Duration::secs(CHECK_TIMEOUT_SECS)).await;
+
+      fail!("Timed out after {}s.", CHECK_TIMEOUT_SECS);
+    });
+
+    (name, result.await)
+  }))
+  .await;
+
+  let mut checks = json::Map::new();
+  let mut is_ok = true;
+
+  for (name, result) in results {
+    let value = match result {
+      Ok(()) => json::json!({ "ok": true }),
+
+      Err(err) => {
+        is_ok = false;
+
+        json::json!({ "ok": false, "error": err })
+      }
+    };
+
+    checks.insert(name, value);
+  }
+
+  let (status, text) = match is_ok {
+    true => (Status::OK, "ok"),
+    false => (Status::SERVICE_UNAVAILABLE, "failing"),
+  };
+
+  Ok(Response::json(&json::json!({ "status": text, "checks": checks }))?.with_status(status))
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::http::Request;
+  use crate::thread;
+
+  #[test]
+  fn test_router() {
+    metrics::counter("test_admin_total", "A test.", &[]).increment();
+    add_liveness_check("test_ok", || async { Ok(()) });
+    add_readiness_check("test_failing", || async { fail!("Not ready") });
+
+    thread::block_on(async {
+      let router = router();
+
+      let res = router.handle(Request::get("/health/live")).await;
+
+      assert_eq!(res.status(), Status::OK);
+      assert_eq!(res.text_body().unwrap(), r#"{"checks":{"test_ok":{"ok":true}},"status":"ok"}"#);
+
+      let res = router.handle(Request::get("/health/ready")).await;
+
+      assert_eq!(res.status(), Status::SERVICE_UNAVAILABLE);
+
+      assert_eq!(
+        res.text_body().unwrap(),
+        r#"{"checks":{"test_failing":{"error":"Not ready.","ok":false}},"status":"failing"}"#
+      );
+
+      let res = router.handle(Request::get("/metrics")).await;
+
+      assert!(res.text_body().unwrap().contains("\ntest_admin_total 1\n"));
+
+      let res = router.handle(Request::get("/metrics.json")).await;
+      let snapshot: json::Value = res.json_body().unwrap();
+
+      assert_eq!(snapshot["test_admin_total"]["values"][0]["value"], 1);
+    });
+  }
+}
@@ -7,21 +7,37 @@
 pub use indigo_macros::logger_init as init;
 
 use crate::log::Level;
+use crate::metrics::{self, Counter};
 use crate::prelude::*;
 use crate::runtime::task;
 use crate::sync::blocking::RwLock;
-use crate::sync::channel;
+use crate::sync::{channel, KeyedRateLimiter};
 use dashmap::DashMap;
 use log_crate::LevelFilter;
 use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
 use std::sync::atomic::{self, AtomicUsize};
 
+/// Statistics about the logger.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct Stats {
+  /// The total number of messages dropped because the output queue was full.
+  pub dropped_messages: u64,
+  /// The total number of messages suppressed because they were repeated too
+  /// often.
+  pub suppressed_messages: u64,
+}
+
 /// A logger to register with the `log` crate.
 struct Logger {
   dropped_messages: AtomicUsize,
   max_level: RwLock<LevelFilter>,
   max_level_of: DashMap<String, LevelFilter>,
   output: (channel::Sender<String>, channel::Receiver<String>),
+  repeat_limiter: KeyedRateLimiter<u64>,
+  suppressed_messages: AtomicUsize,
+  total_dropped: Counter,
+  total_suppressed: Counter,
 }
 
 /// The shared logger instance.
@@ -30,6 +46,18 @@ static LOGGER: Lazy<Logger> = Lazy::new(|| Logger {
   max_level: RwLock::new(LevelFilter::Warn),
   max_level_of: default(),
   output: channel::bounded(16384),
+  repeat_limiter: KeyedRateLimiter::token_bucket(32, Duration::secs(1)),
+  suppressed_messages: default(),
+  total_dropped: metrics::counter(
+    "indigo_logger_dropped_messages_total",
+    "Total number of log messages dropped because the output queue was full.",
+    &[],
+  ),
+  total_suppressed: metrics::counter(
+    "indigo_logger_suppressed_messages_total",
+    "Total number of log messages suppressed because they were repeated too often.",
+    &[],
+  ),
 });
 
 thread_local! {
@@ -45,7 +73,7 @@ pub fn init() {
 
   log_crate::set_max_level(LevelFilter::Trace);
 
-  task::start_detached(output_messages());
+  task::start_background(output_messages());
 }
 
 /// Sets the level of the logger.
@@ -68,6 +96,14 @@ pub fn set_level_of(name: impl Into<String>, level: impl Into<Option<Level>>) {
   LOGGER.max_level_of.insert(name, level);
 }
 
+/// Returns statistics about the logger.
+pub fn stats() -> Stats {
+  Stats {
+    dropped_messages: LOGGER.total_dropped.get(),
+    suppressed_messages: LOGGER.total_suppressed.get(),
+  }
+}
+
 /// Writes each message received from the given channel to stderr.
 async fn output_messages() {
   let mut buffer = String::with_capacity(128);
@@ -76,28 +112,43 @@ async fn output_messages() {
   let mut stderr = console::Term::stderr();
 
   while let Ok(message) = messages.recv().await {
-    // If one or more messages were dropped, write an error message about it.
+    // If one or more messages were dropped or suppressed, write an error
+    // message about it.
 
     let dropped_messages = logger.dropped_messages.swap(0, atomic::Ordering::Relaxed);
 
     if dropped_messages > 0 {
-      write_message(
-        Time::now(),
-        &log_crate::RecordBuilder::new()
-          .level(Level::Error)
-          .target(module_path!())
-          .args(format_args!(
-            "Too many messages. {} {} dropped.",
-            dropped_messages,
-            match dropped_messages {
-              1 => "message",
-              _ => "messages",
-            }
-          ))
-          .build(),
+      write_notice(
+        format_args!(
+          "Too many messages. {} {} dropped.",
+          dropped_messages,
+          match dropped_messages {
+            1 => "message",
+            _ => "messages",
+          }
+        ),
         &mut buffer,
-      )
-      .unwrap();
+      );
+
+      writeln!(stderr, "{}", buffer).unwrap();
+
+      buffer.clear();
+    }
+
+    let suppressed_messages = logger.suppressed_messages.swap(0, atomic::Ordering::Relaxed);
+
+    if suppressed_messages > 0 {
+      write_notice(
+        format_args!(
+          "Too many repeated messages. {} {} suppressed.",
+          suppressed_messages,
+          match suppressed_messages {
+            1 => "message",
+            _ => "messages",
+          }
+        ),
+        &mut buffer,
+      );
 
       writeln!(stderr, "{}", buffer).unwrap();
 
@@ -110,6 +161,16 @@ async fn output_messages() {
   }
 }
 
+/// Writes an error message from the logger itself to the given string.
+fn write_notice(args: fmt::Arguments, f: &mut String) {
+  write_message(
+    Time::now(),
+    &log_crate::RecordBuilder::new().level(Level::Error).target(module_path!()).args(args).build(),
+    f,
+  )
+  .unwrap();
+}
+
 /// Writes a record to the given string.
 fn write_message(time: Time, record: &log_crate::Record, f: &mut String) -> fmt::Result {
   use console::style;
@@ -167,6 +228,33 @@ fn write_message(time: Time, record: &log_crate::Record, f: &mut String) -> fmt:
   write!(f, "{}", styled)
 }
 
+impl Logger {
+  /// Returns `true` if the same message has been logged to the same target
+  /// too often and the record should be suppressed.
+  fn is_repeated_too_often(&self, record: &log_crate::Record) -> bool {
+    let mut hasher = HashWriter(DefaultHasher::new());
+
+    record.target().hash(&mut hasher.0);
+
+    if fmt::write(&mut hasher, *record.args()).is_err() {
+      return false;
+    }
+
+    !self.repeat_limiter.try_acquire(hasher.0.finish())
+  }
+}
+
+/// Feeds formatted text into a hasher without allocating.
+struct HashWriter<H>(H);
+
+impl<H: Hasher> fmt::Write for HashWriter<H> {
+  fn write_str(&mut self, s: &str) -> fmt::Result {
+    self.0.write(s.as_bytes());
+
+    Ok(())
+  }
+}
+
 // Implement `Log` to send messages to the output task.
 
 impl log_crate::Log for Logger {
@@ -195,6 +283,12 @@ impl log_crate::Log for Logger {
       return;
     }
 
+    if self.is_repeated_too_often(record) {
+      self.suppressed_messages.fetch_add(1, atomic::Ordering::Relaxed);
+      self.total_suppressed.increment();
+      return;
+    }
+
     let time = Time::now();
 
     let message = THREAD_BUFFER.with(|buffer| {
@@ -209,9 +303,45 @@ impl log_crate::Log for Logger {
     // count.
 
     if self.output.0.try_send(message).is_err() {
-      LOGGER.dropped_messages.fetch_add(1, atomic::Ordering::Relaxed);
+      self.dropped_messages.fetch_add(1, atomic::Ordering::Relaxed);
+      self.total_dropped.increment();
     }
   }
 
   fn flush(&self) {}
 }
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns `true` if the shared logger suppresses a record with the given
+  /// target and message.
+  fn is_suppressed(target: &str, args: fmt::Arguments) -> bool {
+    LOGGER.is_repeated_too_often(
+      &log_crate::RecordBuilder::new().level(Level::Info).target(target).args(args).build(),
+    )
+  }
+
+  #[test]
+  fn test_repeat_suppression() {
+    let target = "indigo::runtime::logger::tests::test_repeat_suppression";
+
+    for i in 0..32 {
+      assert!(!is_suppressed(target, format_args!("Repeated message.")));
+      assert!(!is_suppressed(target, format_args!("Message {}.", i)));
+    }
+
+    // Only the repeated message is suppressed, even though every message
+    // comes from the same line of code.
+
+    assert!(is_suppressed(target, format_args!("Repeated message.")));
+    assert!(!is_suppressed(target, format_args!("Message {}.", 32)));
+
+    // The same message to a different target is not suppressed.
+
+    assert!(!is_suppressed("indigo::runtime::logger::tests", format_args!("Repeated message.")));
+  }
+}
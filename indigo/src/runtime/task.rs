@@ -6,8 +6,10 @@
 
 //! Asynchronous tasks.
 
-use super::executor;
+use super::{executor, METRICS};
 use crate::prelude::*;
+use crate::sync::{CancellationToken, Event};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// A handle to a task running a future on the Indigo runtime.
 ///
@@ -18,14 +20,26 @@ pub struct Task<T> {
   inner: async_executor::Task<T>,
 }
 
+/// The number of running tasks that the runtime waits for when it shuts down.
+static DRAINING: AtomicUsize = AtomicUsize::new(0);
+
+/// An event notified when the last task that the runtime waits for completes.
+static DRAINED: Lazy<Event> = Lazy::new(Event::new);
+
 /// Starts a new asynchronous task.
+///
+/// When the runtime shuts down, it waits up to 5 seconds for tasks started
+/// with this function to complete, which delays the exit of the process. Use
+/// [`start_cancellable()`] with a token from
+/// [`cancellation_token()`][crate::runtime::cancellation_token()] for tasks
+/// that should stop when the runtime shuts down.
 #[cfg(feature = "runtime")]
 pub fn start<F>(future: F) -> Task<F::Output>
 where
   F: Future + Send + 'static,
   F::Output: Send + 'static,
 {
-  Task { inner: executor().spawn(future) }
+  spawn(future, true)
 }
 
 /// Starts a new asynchronous task that runs to completion in thebackground.
@@ -42,6 +56,92 @@ where
   .detach()
 }
 
+/// Starts a new asynchronous task that is stopped when the given token is
+/// cancelled.
+///
+/// If the token is cancelled before the task completes, the task outputs
+/// `None`.
+pub fn start_cancellable<F>(token: CancellationToken, future: F) -> Task<Option<F::Output>>
+where
+  F: Future + Send + 'static,
+  F::Output: Send + 'static,
+{
+  start(async move { future::until_cancelled(&token, future).await })
+}
+
+/// Starts a background task of the runtime, such as a connection driver, that
+/// the runtime does not wait for when it shuts down.
+pub(crate) fn start_background<F>(future: F)
+where
+  F: Future + Send + 'static,
+{
+  spawn(
+    async move {
+      future.await;
+    },
+    false,
+  )
+  .detach()
+}
+
+/// Waits until every running task that is not a background task of the
+/// runtime has completed.
+pub(super) async fn drained() {
+  loop {
+    let listener = DRAINED.listen();
+
+    if DRAINING.load(Ordering::Acquire) == 0 {
+      return;
+    }
+
+    listener.await;
+  }
+}
+
+/// Returns the number of running tasks that are not background tasks of the
+/// runtime.
+pub(super) fn draining() -> usize {
+  DRAINING.load(Ordering::Acquire)
+}
+
+/// Spawns a new task on the executor.
+fn spawn<F>(future: F, drains: bool) -> Task<F::Output>
+where
+  F: Future + Send + 'static,
+  F::Output: Send + 'static,
+{
+  let guard = RunningGuard::new(drains);
+
+  Task {
+    inner: executor().spawn(async move {
+      let _guard = guard;
+
+      future.await
+    }),
+  }
+}
+
+/// A guard that counts a task as running until it is dropped.
+struct RunningGuard {
+  drains: bool,
+}
+
+impl RunningGuard {
+  /// Counts a new task as started and running.
+  ///
+  /// If `drains` is `true`, the runtime waits for the task when it shuts down.
+  fn new(drains: bool) -> Self {
+    METRICS.tasks_running.increment();
+    METRICS.tasks_started.increment();
+
+    if drains {
+      DRAINING.fetch_add(1, Ordering::AcqRel);
+    }
+
+    Self { drains }
+  }
+}
+
 impl<T> Task<T> {
   /// Stops the task, dropping the original future.
   ///
@@ -56,6 +156,18 @@ impl<T> Task<T> {
   }
 }
 
+// Implement `Drop` to count the task as no longer running.
+
+impl Drop for RunningGuard {
+  fn drop(&mut self) {
+    METRICS.tasks_running.decrement();
+
+    if self.drains && DRAINING.fetch_sub(1, Ordering::AcqRel) == 1 {
+      DRAINED.notify(usize::MAX);
+    }
+  }
+}
+
 // Implement `Future` to poll the inner task.
 
 impl<T> Future for Task<T> {
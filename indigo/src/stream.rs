@@ -6,4 +6,196 @@
 
 //! Utilities for working with streams and async iteration.
 
+mod map_concurrent;
+mod merge;
+
+#[cfg(feature = "runtime")]
+mod chunks_timeout;
+#[cfg(feature = "runtime")]
+mod debounce;
+#[cfg(feature = "runtime")]
+mod throttle;
+
+pub use self::map_concurrent::{MapConcurrent, TryForEachConcurrent, TryMapConcurrent};
+pub use self::merge::{select_all, Merge, SelectAll};
 pub use futures_lite::stream::*;
+
+/// The stream extension trait provided by the `futures_lite` crate.
+#[doc(no_inline)]
+pub use futures_lite::stream::StreamExt as LiteStreamExt;
+
+#[cfg(feature = "runtime")]
+pub use self::{chunks_timeout::ChunksTimeout, debounce::Debounce, throttle::Throttle};
+
+use crate::prelude::*;
+
+/// An extension trait for streams that provides additional combinators.
+pub trait StreamExt: Stream + Sized {
+  /// Collects items into chunks of up to `size` items.
+  ///
+  /// A chunk is output when it is full or when `timeout` has elapsed since
+  /// its first item was received, whichever happens first.
+  #[cfg(feature = "runtime")]
+  fn chunks_timeout(self, size: usize, timeout: Duration) -> ChunksTimeout<Self> {
+    ChunksTimeout::new(self, size, timeout)
+  }
+
+  /// Outputs an item only after no other items have been received for the
+  /// given period of time.
+  ///
+  /// Items that are followed by another item within the period are dropped.
+  /// When the stream ends, the last item is output immediately.
+  #[cfg(feature = "runtime")]
+  fn debounce(self, period: Duration) -> Debounce<Self> {
+    Debounce::new(self, period)
+  }
+
+  /// Maps each item to a future and runs up to `limit` of the futures
+  /// concurrently, outputting their results in the original order.
+  fn map_concurrent<F, Fut>(self, limit: usize, func: F) -> MapConcurrent<Self, F, Fut>
+  where
+    F: FnMut(Self::Item) -> Fut,
+    Fut: Future,
+  {
+    MapConcurrent::new(self, limit, func)
+  }
+
+  /// Merges this stream with another stream, outputting items from both as
+  /// they become available.
+  ///
+  /// The two streams are polled in alternating order so that neither can
+  /// starve the other.
+  fn merge<S>(self, other: S) -> Merge<Self, S>
+  where
+    S: Stream<Item = Self::Item>,
+  {
+    Merge::new(self, other)
+  }
+
+  /// Limits the stream to output at most one item per period of time.
+  ///
+  /// Items are delayed rather than dropped.
+  #[cfg(feature = "runtime")]
+  fn throttle(self, period: Duration) -> Throttle<Self> {
+    Throttle::new(self, period)
+  }
+
+  /// Maps each item to a fallible future and runs up to `limit` of the
+  /// futures concurrently, stopping at the first error.
+  ///
+  /// Results are output in the original order. After an `Err` is output, the
+  /// stream ends.
+  fn try_map_concurrent<F, Fut, T, E>(self, limit: usize, func: F) -> TryMapConcurrent<Self, F, Fut>
+  where
+    F: FnMut(Self::Item) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+  {
+    TryMapConcurrent::new(self, limit, func)
+  }
+
+  /// Runs a fallible future for each item with up to `limit` futures running
+  /// concurrently, stopping at the first error.
+  fn try_for_each_concurrent<F, Fut, E>(
+    self,
+    limit: usize,
+    func: F,
+  ) -> TryForEachConcurrent<Self, F, Fut>
+  where
+    F: FnMut(Self::Item) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+  {
+    TryForEachConcurrent::new(self, limit, func)
+  }
+}
+
+impl<S: Stream> StreamExt for S {}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::thread;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  /// Returns a stream that outputs each item after a delay in milliseconds.
+  #[cfg(feature = "runtime")]
+  fn delayed<T>(items: Vec<(u64, T)>) -> impl Stream<Item = T> {
+    stream::iter(items).map_concurrent(1, |(ms, item)| async move {
+      future::sleep(Duration::secs_f64(ms as f64 / 1000.0)).await;
+      item
+    })
+  }
+
+  #[test]
+  fn test_map_concurrent() {
+    let running = AtomicUsize::new(0);
+    let max_running = AtomicUsize::new(0);
+
+    let output: Vec<_> = thread::block_on(
+      stream::iter(0..10)
+        .map_concurrent(3, |i| {
+          let running = &running;
+          let max_running = &max_running;
+
+          async move {
+            let count = running.fetch_add(1, Ordering::SeqCst) + 1;
+
+            max_running.fetch_max(count, Ordering::SeqCst);
+
+            // Later items complete first.
+
+            for _ in 0..10 - i {
+              futures_lite::future::yield_now().await;
+            }
+
+            running.fetch_sub(1, Ordering::SeqCst);
+
+            i * 2
+          }
+        })
+        .collect(),
+    );
+
+    assert_eq!(output, (0..10).map(|i| i * 2).collect::<Vec<_>>());
+    assert_eq!(max_running.load(Ordering::SeqCst), 3);
+  }
+
+  #[cfg(feature = "runtime")]
+  #[test]
+  fn test_chunks_timeout() {
+    let items = delayed(vec![(0, 1), (0, 2), (0, 3), (200, 4)]);
+    let chunks: Vec<_> =
+      thread::block_on(items.chunks_timeout(2, Duration::secs_f64(0.02)).collect());
+
+    assert_eq!(chunks, [vec![1, 2], vec![3], vec![4]]);
+  }
+
+  #[cfg(feature = "runtime")]
+  #[test]
+  fn test_debounce() {
+    let items = delayed(vec![(0, 1), (0, 2), (0, 3), (200, 4), (0, 5)]);
+    let output: Vec<_> = thread::block_on(items.debounce(Duration::secs_f64(0.05)).collect());
+
+    assert_eq!(output, [3, 5]);
+  }
+
+  #[cfg(feature = "runtime")]
+  #[test]
+  fn test_merge() {
+    let merged = stream::iter(vec![1, 2, 3]).merge(delayed(vec![(50, 10)]));
+    let mut output: Vec<_> = thread::block_on(merged.collect());
+
+    output.sort_unstable();
+
+    assert_eq!(output, [1, 2, 3, 10]);
+
+    let streams = vec![delayed(vec![(0, 1), (30, 2)]), delayed(vec![(60, 3)]), delayed(vec![])];
+
+    let mut output: Vec<_> = thread::block_on(select_all(streams).collect());
+
+    output.sort_unstable();
+
+    assert_eq!(output, [1, 2, 3]);
+  }
+}
@@ -0,0 +1,96 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::future::Poll;
+use crate::prelude::*;
+use async_io::Timer;
+
+/// A stream that collects items into chunks with a timeout.
+///
+/// This stream is returned by [`StreamExt::chunks_timeout()`].
+pub struct ChunksTimeout<S: Stream> {
+  stream: Option<Pin<Box<S>>>,
+  size: usize,
+  timeout: std::time::Duration,
+  timer: Option<Timer>,
+  chunk: Vec<S::Item>,
+}
+
+impl<S: Stream> ChunksTimeout<S> {
+  /// Creates a new stream that collects items into chunks.
+  pub(super) fn new(stream: S, size: usize, timeout: Duration) -> Self {
+    assert!(size > 0, "Chunk size must be greater than zero.");
+
+    Self {
+      stream: Some(Box::pin(stream)),
+      size,
+      timeout: timeout.to_std(),
+      timer: None,
+      chunk: Vec::with_capacity(size),
+    }
+  }
+
+  /// Takes the current chunk and replaces it with a new, empty chunk.
+  fn take_chunk(&mut self) -> Vec<S::Item> {
+    self.timer = None;
+
+    mem::replace(&mut self.chunk, Vec::with_capacity(self.size))
+  }
+}
+
+// Implement `Unpin` because the stream is boxed.
+
+impl<S: Stream> Unpin for ChunksTimeout<S> {}
+
+// Implement `Stream`.
+
+impl<S: Stream> Stream for ChunksTimeout<S> {
+  type Item = Vec<S::Item>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut future::Context) -> Poll<Option<Self::Item>> {
+    let this = self.get_mut();
+
+    // Receive items until the chunk is full or the stream is pending.
+
+    while let Some(stream) = &mut this.stream {
+      match stream.as_mut().poll_next(cx) {
+        Poll::Ready(Some(item)) => {
+          if this.chunk.is_empty() {
+            this.timer = Some(Timer::new(this.timeout));
+          }
+
+          this.chunk.push(item);
+
+          if this.chunk.len() >= this.size {
+            return Poll::Ready(Some(this.take_chunk()));
+          }
+        }
+
+        Poll::Ready(None) => this.stream = None,
+        Poll::Pending => break,
+      }
+    }
+
+    // Output a partial chunk if the stream ended or the timeout elapsed.
+
+    if this.chunk.is_empty() {
+      return match this.stream {
+        Some(_) => Poll::Pending,
+        None => Poll::Ready(None),
+      };
+    }
+
+    let timed_out = match &mut this.timer {
+      Some(timer) => Pin::new(timer).poll(cx).is_ready(),
+      None => true,
+    };
+
+    match this.stream.is_none() || timed_out {
+      true => Poll::Ready(Some(this.take_chunk())),
+      false => Poll::Pending,
+    }
+  }
+}
@@ -0,0 +1,69 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::future::Poll;
+use crate::prelude::*;
+use async_io::Timer;
+
+/// A stream that only outputs items after a period without new items.
+///
+/// This stream is returned by [`StreamExt::debounce()`].
+pub struct Debounce<S: Stream> {
+  stream: Option<Pin<Box<S>>>,
+  period: std::time::Duration,
+  timer: Timer,
+  item: Option<S::Item>,
+}
+
+impl<S: Stream> Debounce<S> {
+  /// Creates a new stream that debounces items.
+  pub(super) fn new(stream: S, period: Duration) -> Self {
+    let period = period.to_std();
+
+    Self { stream: Some(Box::pin(stream)), period, timer: Timer::new(period), item: None }
+  }
+}
+
+// Implement `Unpin` because the stream is boxed.
+
+impl<S: Stream> Unpin for Debounce<S> {}
+
+// Implement `Stream`.
+
+impl<S: Stream> Stream for Debounce<S> {
+  type Item = S::Item;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut future::Context) -> Poll<Option<Self::Item>> {
+    let this = self.get_mut();
+
+    // Receive all available items, keeping only the latest and restarting the
+    // timer each time.
+
+    while let Some(stream) = &mut this.stream {
+      match stream.as_mut().poll_next(cx) {
+        Poll::Ready(Some(item)) => {
+          this.item = Some(item);
+          this.timer.reset(this.period);
+        }
+
+        Poll::Ready(None) => this.stream = None,
+        Poll::Pending => break,
+      }
+    }
+
+    // Output the latest item if the stream ended or the timer elapsed.
+
+    if this.stream.is_none() {
+      return Poll::Ready(this.item.take());
+    }
+
+    if this.item.is_some() && Pin::new(&mut this.timer).poll(cx).is_ready() {
+      return Poll::Ready(this.item.take());
+    }
+
+    Poll::Pending
+  }
+}
@@ -0,0 +1,179 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::future::{Buffered, Poll};
+use crate::prelude::*;
+use std::collections::VecDeque;
+
+/// A stream that maps items to futures and runs them concurrently.
+///
+/// This stream is returned by [`StreamExt::map_concurrent()`].
+pub struct MapConcurrent<S, F, Fut: Future> {
+  stream: Option<Pin<Box<S>>>,
+  func: F,
+  limit: usize,
+  running: VecDeque<Buffered<Pin<Box<Fut>>>>,
+}
+
+/// A stream that maps items to fallible futures and runs them concurrently
+/// until one fails.
+///
+/// This stream is returned by [`StreamExt::try_map_concurrent()`].
+pub struct TryMapConcurrent<S, F, Fut: Future> {
+  inner: Option<MapConcurrent<S, F, Fut>>,
+}
+
+/// A future that runs a fallible future for each item of a stream
+/// concurrently until one fails.
+///
+/// This future is returned by [`StreamExt::try_for_each_concurrent()`].
+pub struct TryForEachConcurrent<S, F, Fut: Future> {
+  inner: TryMapConcurrent<S, F, Fut>,
+}
+
+impl<S, F, Fut> MapConcurrent<S, F, Fut>
+where
+  S: Stream,
+  F: FnMut(S::Item) -> Fut,
+  Fut: Future,
+{
+  /// Creates a new stream that maps items to futures and runs up to `limit`
+  /// of them concurrently.
+  pub(super) fn new(stream: S, limit: usize, func: F) -> Self {
+    assert!(limit > 0, "Concurrency limit must be greater than zero.");
+
+    Self { stream: Some(Box::pin(stream)), func, limit, running: VecDeque::with_capacity(limit) }
+  }
+}
+
+impl<S, F, Fut> TryMapConcurrent<S, F, Fut>
+where
+  S: Stream,
+  F: FnMut(S::Item) -> Fut,
+  Fut: Future,
+{
+  /// Creates a new stream that maps items to fallible futures and runs up to
+  /// `limit` of them concurrently.
+  pub(super) fn new(stream: S, limit: usize, func: F) -> Self {
+    Self { inner: Some(MapConcurrent::new(stream, limit, func)) }
+  }
+}
+
+impl<S, F, Fut> TryForEachConcurrent<S, F, Fut>
+where
+  S: Stream,
+  F: FnMut(S::Item) -> Fut,
+  Fut: Future,
+{
+  /// Creates a new future that runs up to `limit` fallible futures
+  /// concurrently.
+  pub(super) fn new(stream: S, limit: usize, func: F) -> Self {
+    Self { inner: TryMapConcurrent::new(stream, limit, func) }
+  }
+}
+
+// Implement `Unpin` because the stream and futures are boxed.
+
+impl<S, F, Fut: Future> Unpin for MapConcurrent<S, F, Fut> {}
+impl<S, F, Fut: Future> Unpin for TryMapConcurrent<S, F, Fut> {}
+impl<S, F, Fut: Future> Unpin for TryForEachConcurrent<S, F, Fut> {}
+
+// Implement `Stream` and `Future`.
+
+impl<S, F, Fut> Stream for MapConcurrent<S, F, Fut>
+where
+  S: Stream,
+  F: FnMut(S::Item) -> Fut,
+  Fut: Future,
+{
+  type Item = Fut::Output;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut future::Context) -> Poll<Option<Self::Item>> {
+    let this = self.get_mut();
+
+    // Start futures for new items until the limit is reached.
+
+    while this.running.len() < this.limit {
+      let stream = match &mut this.stream {
+        Some(stream) => stream,
+        None => break,
+      };
+
+      match stream.as_mut().poll_next(cx) {
+        Poll::Ready(Some(item)) => {
+          this.running.push_back(Buffered::new(Box::pin((this.func)(item))));
+        }
+
+        Poll::Ready(None) => this.stream = None,
+        Poll::Pending => break,
+      }
+    }
+
+    // Poll all running futures, then output the first one if it is ready.
+
+    for future in &mut this.running {
+      let _ = Pin::new(future).poll(cx);
+    }
+
+    match this.running.front() {
+      Some(future) if future.is_ready() => {
+        Poll::Ready(this.running.pop_front().map(Buffered::into_output))
+      }
+
+      None if this.stream.is_none() => Poll::Ready(None),
+      _ => Poll::Pending,
+    }
+  }
+}
+
+impl<S, F, Fut, T, E> Stream for TryMapConcurrent<S, F, Fut>
+where
+  S: Stream,
+  F: FnMut(S::Item) -> Fut,
+  Fut: Future<Output = Result<T, E>>,
+{
+  type Item = Result<T, E>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut future::Context) -> Poll<Option<Self::Item>> {
+    let this = self.get_mut();
+
+    let inner = match &mut this.inner {
+      Some(inner) => inner,
+      None => return Poll::Ready(None),
+    };
+
+    let result = futures_lite::ready!(Pin::new(inner).poll_next(cx));
+
+    // Drop the inner stream and any running futures after the first error.
+
+    if let None | Some(Err(_)) = &result {
+      this.inner = None;
+    }
+
+    Poll::Ready(result)
+  }
+}
+
+impl<S, F, Fut, E> Future for TryForEachConcurrent<S, F, Fut>
+where
+  S: Stream,
+  F: FnMut(S::Item) -> Fut,
+  Fut: Future<Output = Result<(), E>>,
+{
+  type Output = Result<(), E>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut future::Context) -> Poll<Self::Output> {
+    let inner = &mut self.get_mut().inner;
+
+    loop {
+      match futures_lite::ready!(Pin::new(&mut *inner).poll_next(cx)) {
+        Some(Ok(())) => continue,
+        Some(Err(err)) => return Poll::Ready(Err(err)),
+        None => return Poll::Ready(Ok(())),
+      }
+    }
+  }
+}
@@ -0,0 +1,146 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::future::Poll;
+use crate::prelude::*;
+
+/// A stream that merges two streams.
+///
+/// This stream is returned by [`StreamExt::merge()`].
+pub struct Merge<A, B> {
+  a: Option<Pin<Box<A>>>,
+  b: Option<Pin<Box<B>>>,
+  poll_b_first: bool,
+}
+
+/// A stream that merges any number of streams of the same type.
+///
+/// This stream is returned by [`select_all()`].
+pub struct SelectAll<S> {
+  streams: Vec<Pin<Box<S>>>,
+  next: usize,
+}
+
+/// Merges any number of streams of the same type, outputting items from each
+/// as they become available.
+///
+/// The streams are polled in round-robin order so that none of them can starve
+/// the others.
+pub fn select_all<S: Stream>(streams: impl IntoIterator<Item = S>) -> SelectAll<S> {
+  SelectAll { streams: streams.into_iter().map(Box::pin).collect(), next: 0 }
+}
+
+impl<A: Stream, B: Stream<Item = A::Item>> Merge<A, B> {
+  /// Creates a new stream that merges two streams.
+  pub(super) fn new(a: A, b: B) -> Self {
+    Self { a: Some(Box::pin(a)), b: Some(Box::pin(b)), poll_b_first: true }
+  }
+}
+
+impl<S> SelectAll<S> {
+  /// Adds a stream to the set of merged streams.
+  pub fn push(&mut self, stream: S) {
+    self.streams.push(Box::pin(stream));
+  }
+
+  /// Returns the number of merged streams that have not ended.
+  pub fn len(&self) -> usize {
+    self.streams.len()
+  }
+
+  /// Returns `true` if all merged streams have ended.
+  pub fn is_empty(&self) -> bool {
+    self.streams.is_empty()
+  }
+}
+
+/// Polls an optional stream, setting it to `None` when it ends.
+fn poll_next_opt<S: Stream>(
+  stream: &mut Option<Pin<Box<S>>>,
+  cx: &mut future::Context,
+) -> Poll<Option<S::Item>> {
+  let inner = match stream {
+    Some(inner) => inner,
+    None => return Poll::Ready(None),
+  };
+
+  let item = futures_lite::ready!(inner.as_mut().poll_next(cx));
+
+  if item.is_none() {
+    *stream = None;
+  }
+
+  Poll::Ready(item)
+}
+
+// Implement `Unpin` because the streams are boxed.
+
+impl<A, B> Unpin for Merge<A, B> {}
+impl<S> Unpin for SelectAll<S> {}
+
+// Implement `Stream`.
+
+impl<A: Stream, B: Stream<Item = A::Item>> Stream for Merge<A, B> {
+  type Item = A::Item;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut future::Context) -> Poll<Option<Self::Item>> {
+    let this = self.get_mut();
+
+    this.poll_b_first = !this.poll_b_first;
+
+    for i in 0..2 {
+      let item = match this.poll_b_first == (i == 0) {
+        true => poll_next_opt(&mut this.b, cx),
+        false => poll_next_opt(&mut this.a, cx),
+      };
+
+      if let Poll::Ready(Some(item)) = item {
+        return Poll::Ready(Some(item));
+      }
+    }
+
+    match this.a.is_none() && this.b.is_none() {
+      true => Poll::Ready(None),
+      false => Poll::Pending,
+    }
+  }
+}
+
+impl<S: Stream> Stream for SelectAll<S> {
+  type Item = S::Item;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut future::Context) -> Poll<Option<Self::Item>> {
+    let this = self.get_mut();
+    let mut remaining = this.streams.len();
+
+    while remaining > 0 {
+      if this.next >= this.streams.len() {
+        this.next = 0;
+      }
+
+      match this.streams[this.next].as_mut().poll_next(cx) {
+        Poll::Ready(Some(item)) => {
+          this.next += 1;
+
+          return Poll::Ready(Some(item));
+        }
+
+        Poll::Ready(None) => {
+          this.streams.swap_remove(this.next);
+        }
+
+        Poll::Pending => this.next += 1,
+      }
+
+      remaining -= 1;
+    }
+
+    match this.streams.is_empty() {
+      true => Poll::Ready(None),
+      false => Poll::Pending,
+    }
+  }
+}
@@ -0,0 +1,55 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::future::Poll;
+use crate::prelude::*;
+use async_io::Timer;
+
+/// A stream that outputs at most one item per period of time.
+///
+/// This stream is returned by [`StreamExt::throttle()`].
+pub struct Throttle<S> {
+  stream: Pin<Box<S>>,
+  period: std::time::Duration,
+  timer: Option<Timer>,
+}
+
+impl<S: Stream> Throttle<S> {
+  /// Creates a new stream that throttles items.
+  pub(super) fn new(stream: S, period: Duration) -> Self {
+    Self { stream: Box::pin(stream), period: period.to_std(), timer: None }
+  }
+}
+
+// Implement `Unpin` because the stream is boxed.
+
+impl<S> Unpin for Throttle<S> {}
+
+// Implement `Stream`.
+
+impl<S: Stream> Stream for Throttle<S> {
+  type Item = S::Item;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut future::Context) -> Poll<Option<Self::Item>> {
+    let this = self.get_mut();
+
+    // Wait for the period since the last item to elapse.
+
+    if let Some(timer) = &mut this.timer {
+      futures_lite::ready!(Pin::new(timer).poll(cx));
+
+      this.timer = None;
+    }
+
+    let item = futures_lite::ready!(this.stream.as_mut().poll_next(cx));
+
+    if item.is_some() {
+      this.timer = Some(Timer::new(this.period));
+    }
+
+    Poll::Ready(item)
+  }
+}
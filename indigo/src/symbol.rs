@@ -120,7 +120,7 @@ impl Eq for Symbol {}
 
 impl Hash for Symbol {
   fn hash<H: Hasher>(&self, state: &mut H) {
-    state.write_usize(unsafe { mem::transmute(&self.0.as_bytes()[0]) })
+    state.write_usize(self.0.as_ptr() as usize)
   }
 }
 
@@ -7,11 +7,15 @@
 //! Synchronization primitives and concurrency utilties.
 
 mod atomic;
+mod cancellation;
 pub mod channel;
+mod rate_limiter;
 mod semaphore;
 
 pub use self::atomic::*;
-pub use self::semaphore::Semaphore;
+pub use self::cancellation::CancellationToken;
+pub use self::rate_limiter::RateLimiter;
+pub use self::semaphore::{Permit, Semaphore};
 pub use event_listener::{Event, EventListener};
 pub use futures_lite::pin;
 pub use once_cell::sync::{Lazy, OnceCell};
@@ -19,3 +23,6 @@ pub use once_cell::sync::{Lazy, OnceCell};
 /// Blocking concurrency primitives provided by the `parking_lot` crate.
 #[doc(inline)]
 pub use parking_lot as blocking;
+
+#[cfg(feature = "runtime")]
+pub use self::rate_limiter::KeyedRateLimiter;
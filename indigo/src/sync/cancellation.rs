@@ -0,0 +1,140 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Cooperative cancellation.
+
+use crate::prelude::*;
+use crate::sync::blocking::Mutex;
+use crate::sync::AtomicBool;
+
+/// A cloneable token for cooperatively cancelling work.
+///
+/// Cancelling a token also cancels all of its child tokens, but cancelling a
+/// child token does not affect its parent.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+  inner: Arc<Inner>,
+}
+
+/// The shared state of a [`CancellationToken`].
+#[derive(Default)]
+struct Inner {
+  cancelled: AtomicBool,
+  children: Mutex<Vec<ArcWeak<Inner>>>,
+}
+
+impl CancellationToken {
+  /// Creates a new token that has not been cancelled.
+  pub fn new() -> Self {
+    default()
+  }
+
+  /// Creates a new child token that is cancelled when this token is
+  /// cancelled.
+  pub fn child(&self) -> Self {
+    let child = Self::new();
+    let mut children = self.inner.children.lock();
+
+    if self.is_cancelled() {
+      child.inner.cancelled.store(true);
+    } else {
+      // Forget children that have been dropped before adding the new one.
+
+      children.retain(|c| c.strong_count() > 0);
+      children.push(Arc::downgrade(&child.inner));
+    }
+
+    child
+  }
+
+  /// Cancels this token and all of its child tokens.
+  pub fn cancel(&self) {
+    self.inner.cancel();
+  }
+
+  /// Waits until this token is cancelled.
+  pub async fn cancelled(&self) {
+    self.inner.cancelled.until_eq(true).await;
+  }
+
+  /// Returns `true` if this token has been cancelled.
+  pub fn is_cancelled(&self) -> bool {
+    self.inner.cancelled.load()
+  }
+
+  /// Runs a future until it completes or this token is cancelled.
+  ///
+  /// If this token is cancelled first, the future is dropped and this function
+  /// returns `None`.
+  pub async fn run<F: Future>(&self, future: F) -> Option<F::Output> {
+    future::until_cancelled(self, future).await
+  }
+}
+
+impl Inner {
+  /// Cancels this token and all of its child tokens.
+  fn cancel(&self) {
+    if self.cancelled.swap(true) {
+      return;
+    }
+
+    let children = mem::take(&mut *self.children.lock());
+
+    for child in children.iter().filter_map(ArcWeak::upgrade) {
+      child.cancel();
+    }
+  }
+}
+
+// Implement formatting.
+
+impl Debug for CancellationToken {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("CancellationToken").field("is_cancelled", &self.is_cancelled()).finish()
+  }
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::thread;
+
+  #[test]
+  fn test_children() {
+    let parent = CancellationToken::new();
+    let child = parent.child();
+    let grandchild = child.child();
+    let sibling = parent.child();
+
+    child.cancel();
+
+    assert!(child.is_cancelled());
+    assert!(grandchild.is_cancelled());
+    assert!(!parent.is_cancelled());
+    assert!(!sibling.is_cancelled());
+
+    parent.cancel();
+
+    assert!(sibling.is_cancelled());
+    assert!(parent.child().is_cancelled());
+  }
+
+  #[test]
+  fn test_cancelled() {
+    let parent = CancellationToken::new();
+    let child = parent.child();
+
+    thread::block_on(async {
+      // The first future waits before the second cancels the parent.
+
+      future::join(child.cancelled(), async { parent.cancel() }).await;
+
+      assert_eq!(child.run(future::pending::<()>()).await, None);
+    });
+  }
+}
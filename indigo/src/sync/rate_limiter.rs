@@ -0,0 +1,327 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Rate limiters.
+
+use crate::prelude::*;
+use crate::sync::blocking::Mutex;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+#[cfg(feature = "runtime")]
+use dashmap::DashMap;
+
+/// A rate limiter that allows a limited number of operations per period of
+/// time.
+///
+/// Tasks can acquire a single permission to proceed by awaiting the
+/// `acquire()` method, or check for one without waiting with the
+/// `try_acquire()` method.
+pub struct RateLimiter {
+  config: Config,
+  state: Mutex<State>,
+}
+
+/// A set of rate limiters, one for each unique key.
+///
+/// Each key is limited independently as if it had its own [`RateLimiter`].
+/// Limiters of idle keys are removed at most once per period so that memory
+/// use does not grow with every distinct key.
+#[cfg(feature = "runtime")]
+pub struct KeyedRateLimiter<K: Eq + Hash> {
+  config: Config,
+  limiters: DashMap<K, RateLimiter>,
+  pruned_at: Mutex<Instant>,
+}
+
+/// The configuration of a [`RateLimiter`].
+#[derive(Clone, Copy)]
+struct Config {
+  kind: Kind,
+  limit: usize,
+  period: std::time::Duration,
+}
+
+/// One of the possible rate limiting algorithms.
+#[derive(Clone, Copy)]
+enum Kind {
+  SlidingWindow,
+  TokenBucket,
+}
+
+/// The mutable state of a [`RateLimiter`].
+enum State {
+  SlidingWindow { times: VecDeque<Instant> },
+  TokenBucket { tokens: f64, updated_at: Instant },
+}
+
+impl RateLimiter {
+  /// Creates a new rate limiter that allows at most `limit` operations in any
+  /// window of time equal to `period`.
+  ///
+  /// A sliding window limiter is exact but remembers the time of every
+  /// operation in the current window.
+  pub fn sliding_window(limit: usize, period: Duration) -> Self {
+    Config::new(Kind::SlidingWindow, limit, period).build()
+  }
+
+  /// Creates a new rate limiter that allows bursts of up to `limit` operations
+  /// and refills at a rate of `limit` operations per `period`.
+  pub fn token_bucket(limit: usize, period: Duration) -> Self {
+    Config::new(Kind::TokenBucket, limit, period).build()
+  }
+
+  /// Waits until an operation is allowed and then acquires permission for it.
+  #[cfg(feature = "runtime")]
+  pub async fn acquire(&self) {
+    while let Err(wait) = self.check(Instant::now()) {
+      future::sleep(wait.into()).await;
+    }
+  }
+
+  /// Attempts to immediately acquire permission for an operation.
+  ///
+  /// If the rate limit has been reached, this function returns `false`.
+  pub fn try_acquire(&self) -> bool {
+    self.check(Instant::now()).is_ok()
+  }
+
+  /// Acquires permission for an operation at the given instant or returns how
+  /// long to wait before trying again.
+  fn check(&self, now: Instant) -> Result<(), std::time::Duration> {
+    let Config { limit, period, .. } = self.config;
+    let mut state = self.state.lock();
+
+    match &mut *state {
+      State::SlidingWindow { times } => {
+        // Forget operations that are no longer in the window.
+
+        while let Some(time) = times.front() {
+          if now.duration_since(*time) < period {
+            break;
+          }
+
+          times.pop_front();
+        }
+
+        if times.len() < limit {
+          times.push_back(now);
+
+          return Ok(());
+        }
+
+        Err(period - now.duration_since(times[0]))
+      }
+
+      State::TokenBucket { tokens, updated_at } => {
+        // Refill tokens for the time elapsed since the last update.
+
+        let rate = limit as f64 / period.as_secs_f64();
+
+        *tokens =
+          (*tokens + now.duration_since(*updated_at).as_secs_f64() * rate).min(limit as f64);
+        *updated_at = now;
+
+        if *tokens >= 1.0 {
+          *tokens -= 1.0;
+
+          return Ok(());
+        }
+
+        Err(std::time::Duration::from_secs_f64((1.0 - *tokens) / rate))
+      }
+    }
+  }
+
+  /// Returns `true` if the limiter would allow a full burst of operations at
+  /// the given instant, meaning it can be discarded without changing limits.
+  fn is_idle(&self, now: Instant) -> bool {
+    let Config { limit, period, .. } = self.config;
+    let state = self.state.lock();
+
+    match &*state {
+      State::SlidingWindow { times } => {
+        times.back().is_none_or(|time| now.duration_since(*time) >= period)
+      }
+
+      State::TokenBucket { tokens, updated_at } => {
+        let rate = limit as f64 / period.as_secs_f64();
+
+        tokens + now.duration_since(*updated_at).as_secs_f64() * rate >= limit as f64
+      }
+    }
+  }
+}
+
+#[cfg(feature = "runtime")]
+impl<K: Eq + Hash> KeyedRateLimiter<K> {
+  /// Creates a new set of sliding window rate limiters.
+  ///
+  /// See [`RateLimiter::sliding_window()`] for more information.
+  pub fn sliding_window(limit: usize, period: Duration) -> Self {
+    Self::new(Config::new(Kind::SlidingWindow, limit, period))
+  }
+
+  /// Creates a new set of token bucket rate limiters.
+  ///
+  /// See [`RateLimiter::token_bucket()`] for more information.
+  pub fn token_bucket(limit: usize, period: Duration) -> Self {
+    Self::new(Config::new(Kind::TokenBucket, limit, period))
+  }
+
+  /// Waits until an operation with the given key is allowed and then acquires
+  /// permission for it.
+  pub async fn acquire(&self, key: K)
+  where
+    K: Clone,
+  {
+    while let Err(wait) = self.check(key.clone(), Instant::now()) {
+      future::sleep(wait.into()).await;
+    }
+  }
+
+  /// Attempts to immediately acquire permission for an operation with the
+  /// given key.
+  ///
+  /// If the rate limit for the key has been reached, this function returns
+  /// `false`.
+  pub fn try_acquire(&self, key: K) -> bool {
+    self.check(key, Instant::now()).is_ok()
+  }
+
+  /// Removes the rate limiter for the given key, resetting its limit.
+  pub fn remove(&self, key: &K) {
+    self.limiters.remove(key);
+  }
+
+  /// Removes all rate limiters, resetting the limits of all keys.
+  pub fn clear(&self) {
+    self.limiters.clear();
+  }
+
+  /// Removes the rate limiters of idle keys, which have no effect on future
+  /// operations.
+  ///
+  /// This is also done automatically at most once per period.
+  pub fn prune(&self) {
+    let now = Instant::now();
+
+    *self.pruned_at.lock() = now;
+    self.limiters.retain(|_, limiter| !limiter.is_idle(now));
+  }
+
+  /// Creates a new set of rate limiters with the given configuration.
+  fn new(config: Config) -> Self {
+    Self { config, limiters: default(), pruned_at: Mutex::new(Instant::now()) }
+  }
+
+  /// Acquires permission for an operation with the given key at the given
+  /// instant or returns how long to wait before trying again.
+  fn check(&self, key: K, now: Instant) -> Result<(), std::time::Duration> {
+    let config = self.config;
+
+    let should_prune = {
+      let mut pruned_at = self.pruned_at.lock();
+      let should_prune = now.duration_since(*pruned_at) >= config.period;
+
+      if should_prune {
+        *pruned_at = now;
+      }
+
+      should_prune
+    };
+
+    if should_prune {
+      self.limiters.retain(|_, limiter| !limiter.is_idle(now));
+    }
+
+    self.limiters.entry(key).or_insert_with(|| config.build()).check(now)
+  }
+}
+
+impl Config {
+  /// Creates a new rate limiter configuration.
+  fn new(kind: Kind, limit: usize, period: Duration) -> Self {
+    assert!(limit > 0, "Rate limit must be greater than zero.");
+
+    Self { kind, limit, period: period.to_std() }
+  }
+
+  /// Builds a new rate limiter from this configuration.
+  fn build(self) -> RateLimiter {
+    let state = match self.kind {
+      Kind::SlidingWindow => State::SlidingWindow { times: VecDeque::with_capacity(self.limit) },
+
+      Kind::TokenBucket => {
+        State::TokenBucket { tokens: self.limit as f64, updated_at: Instant::now() }
+      }
+    };
+
+    RateLimiter { config: self, state: Mutex::new(state) }
+  }
+}
+
+// Unit tests.
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_sliding_window() {
+    let limiter = RateLimiter::sliding_window(2, Duration::secs(1));
+    let start = Instant::now();
+    let later = |ms| start + std::time::Duration::from_millis(ms);
+
+    assert!(limiter.check(start).is_ok());
+    assert!(limiter.check(later(500)).is_ok());
+    assert_eq!(limiter.check(later(600)), Err(std::time::Duration::from_millis(400)));
+    assert!(limiter.check(later(1000)).is_ok());
+    assert!(limiter.check(later(1200)).is_err());
+    assert!(limiter.check(later(1500)).is_ok());
+  }
+
+  #[test]
+  fn test_token_bucket() {
+    let limiter = RateLimiter::token_bucket(2, Duration::secs(1));
+    let start = Instant::now();
+    let later = |ms| start + std::time::Duration::from_millis(ms);
+
+    assert!(limiter.check(start).is_ok());
+    assert!(limiter.check(start).is_ok());
+    assert!(limiter.check(start).is_err());
+    assert!(limiter.check(later(250)).is_err());
+    assert!(limiter.check(later(500)).is_ok());
+    assert!(limiter.check(later(5000)).is_ok());
+    assert!(limiter.check(later(5000)).is_ok());
+    assert!(limiter.check(later(5000)).is_err());
+  }
+
+  #[cfg(feature = "runtime")]
+  #[test]
+  fn test_keyed_pruning() {
+    let limiter = KeyedRateLimiter::sliding_window(1, Duration::secs(1));
+    let start = Instant::now();
+    let later = |ms| start + std::time::Duration::from_millis(ms);
+
+    assert!(limiter.check("a", start).is_ok());
+    assert!(limiter.check("b", later(500)).is_ok());
+    assert!(limiter.check("a", later(600)).is_err());
+    assert_eq!(limiter.limiters.len(), 2);
+
+    // Key `a` is idle after one period and is removed, but `b` is not.
+
+    assert!(limiter.check("c", later(1000)).is_ok());
+    assert!(limiter.limiters.get("a").is_none());
+    assert_eq!(limiter.limiters.len(), 2);
+
+    limiter.limiters.clear();
+    limiter.check("d", later(1000)).unwrap();
+    limiter.prune();
+
+    assert!(limiter.limiters.get("d").is_some());
+  }
+}
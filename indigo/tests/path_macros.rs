@@ -0,0 +1,25 @@
+// Copyright © 2020 Alexandra Frydl
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use indigo::fs::path;
+
+#[test]
+fn test_literals() {
+  // Adjacent literals are joined the same way as at run time.
+
+  assert_eq!(path::join!("a", "b/", "c"), path::join_parts(&["a", "b/", "c"]));
+  assert_eq!(path::join!("a\\", "b"), path::join_parts(&["a\\", "b"]));
+  assert_eq!(path::join!("a", "", "b"), path::join_parts(&["a", "", "b"]));
+  assert_eq!(path::join!("a", "/b", "c"), path::join_parts(&["a", "/b", "c"]));
+}
+
+#[test]
+fn test_strict() {
+  let cases = trybuild::TestCases::new();
+
+  cases.pass("tests/ui/path/strict_pass.rs");
+  cases.compile_fail("tests/ui/path/strict_fail_*.rs");
+}
@@ -0,0 +1,7 @@
+use indigo::fs::path;
+
+fn main() {
+  let dir = "/var";
+
+  path::join!(strict; dir, ".", "etc");
+}
@@ -0,0 +1,8 @@
+use indigo::fs::path;
+
+fn main() {
+  let dir = String::from("/var");
+
+  assert_eq!(path::join!(strict; &dir, "lib", "app"), "/var/lib/app");
+  assert_eq!(path::normalize!(strict; &dir, "log", "app"), "/var/log/app");
+}